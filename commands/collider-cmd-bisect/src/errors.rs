@@ -6,8 +6,8 @@ use collider_common::{
 #[derive(Debug, Error, Diagnostic)]
 pub enum BisectError {
     #[error(transparent)]
-    #[diagnostic(code(collider::bisect::http_error))]
-    HttpError(#[from] reqwest::Error),
+    #[diagnostic(code(collider::bisect::electron_error))]
+    ElectronError(#[from] collider_electron::ElectronError),
 
     #[error(transparent)]
     #[diagnostic(code(collider::bisect::io_error))]
@@ -17,6 +17,17 @@ pub enum BisectError {
     #[diagnostic(code(collider::bisect::semver_error))]
     SemverError(#[from] node_semver::SemverError),
 
+    #[error("Unknown way to combine test results: `{0}`. Expected `and` or `or`.")]
+    #[diagnostic(code(collider::bisect::unknown_combine))]
+    UnknownCombine(String),
+
+    #[error("--interactive can't be combined with --json.")]
+    #[diagnostic(
+        code(collider::bisect::interactive_json),
+        help("Prompting for each version and printing machine-readable output conflict. Drop one of them: without --interactive, Electron's exit code (or --command's) decides each version.")
+    )]
+    InteractiveJson,
+
     #[error("Electron process exited with an error")]
     #[diagnostic(code(collider::bisect::electron_error))]
     ElectronFailed,
@@ -1,21 +1,25 @@
-use std::path::PathBuf;
-
-use async_compat::CompatExt;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
 use collider_command::{
     async_trait::async_trait,
     clap::{self, Clap},
     collider_config::{self, ColliderConfigLayer},
-    tracing, ColliderCommand,
+    shell_quote, tracing, ColliderCommand, ColliderEvent, ConfirmRetry, Webhook,
 };
 
 use collider_common::{
     miette::{IntoDiagnostic, Result},
     serde::Deserialize,
-    smol::process::Command,
+    serde_json,
+    smol::{fs, process::Command},
 };
 
-use collider_electron::ElectronOpts;
+use collider_electron::{CacheDirs, Electron, ElectronError, ElectronOpts};
 
 use dialoguer::{theme::ColorfulTheme, Confirm};
 
@@ -33,10 +37,18 @@ struct ElectronVersion {
 #[derive(Debug, Clap, ColliderConfigLayer)]
 pub struct BisectCmd {
     #[clap(
-        about = "Path to Electron app that causes the issue. Must be an index.js file, a folder containing a package.json file, a folder containing an index.json file, and .html/.htm file, or an http/https/file URL.",
+        about = "Path to Electron app that causes the issue. Must be an index.js file, a folder containing a package.json file, a folder containing an index.json file, and .html/.htm file, or an http/https/file URL. Can be given more than once to test several cases at each step: see `--combine`.",
         default_value = "."
     )]
-    path: PathBuf,
+    path: Vec<PathBuf>,
+
+    #[clap(
+        long,
+        about = "How to combine the results of several test cases. With `and`, a version is good only if every case passes. With `or`, one passing case is enough. A case that exits with code 125 can't test that version; if the other cases don't settle it, the version is skipped.",
+        default_value = "and",
+        possible_values = &["and", "or"]
+    )]
+    combine: Combine,
 
     #[clap(
         long,
@@ -61,103 +73,435 @@ pub struct BisectCmd {
     )]
     interactive: bool,
 
+    #[clap(
+        long,
+        about = "Shell command that tests each version, instead of launching the app directly. It gets the Electron executable as $ELECTRON_EXE (also $COLLIDER_ELECTRON_PATH) and the app path as $ELECTRON_APP. Exiting 0 means good, 125 means the version can't be tested, and anything else means bad. Runs once per path."
+    )]
+    command: Option<String>,
+
+    #[clap(
+        long,
+        short = 'p',
+        about = "Include prerelease versions in the set of versions to bisect."
+    )]
+    include_prerelease: bool,
+
+    #[clap(
+        long,
+        short,
+        about = "GitHub API Token (no permissions needed). Defaults to the `GH_TOKEN` environment variable, then `GITHUB_TOKEN`."
+    )]
+    github_token: Option<String>,
+
+    #[clap(
+        long,
+        about = "Fetch the list of Electron releases again, even if the cached copy is still fresh."
+    )]
+    refresh: bool,
+
+    #[clap(
+        long,
+        about = "Don't read or write the cached list of Electron releases at all, to see exactly what a fresh lookup does. Unlike --refresh, the cache is left as it was, and there's no falling back to it if fetching fails."
+    )]
+    no_cache: bool,
+
+    #[clap(
+        long,
+        about = "How many hours the cached list of Electron releases stays fresh before bisect fetches it again. If fetching fails, a stale copy is used anyway.",
+        default_value = "6"
+    )]
+    releases_ttl: u64,
+
+    #[clap(
+        long,
+        about = "If a version can't be downloaded or launched, skip it and keep bisecting instead of aborting. Skipped versions are never counted as good or bad."
+    )]
+    keep_going: bool,
+
+    #[clap(
+        long,
+        about = "List the versions bisect would choose from, and roughly how many steps it would take, without downloading or running anything."
+    )]
+    dry_run: bool,
+
+    #[clap(
+        long = "electron-arg",
+        number_of_values = 1,
+        allow_hyphen_values = true,
+        about = "Extra argument to pass to Electron itself, e.g. `--electron-arg=--disable-gpu`. Can be given multiple times. These are added after any `electron_args` from config files, rather than replacing them."
+    )]
+    #[collider_config(append)]
+    electron_args: Vec<String>,
+
+    #[clap(
+        last = true,
+        about = "Arguments to add after the app path on every run, after `--`, e.g. `collider bisect . -- --disable-gpu`. These are added after any `app_args` from config files. With --command, they're passed along in $ELECTRON_EXTRA_ARGS instead."
+    )]
+    #[collider_config(append)]
+    app_args: Vec<String>,
+
     #[clap(from_global)]
     verbosity: tracing::Level,
     #[clap(from_global)]
     quiet: bool,
     #[clap(from_global)]
     json: bool,
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+    #[clap(from_global)]
+    webhook: Option<String>,
+    #[clap(from_global)]
+    webhook_header: Option<String>,
 }
 
 #[async_trait]
 impl ColliderCommand for BisectCmd {
     async fn execute(self) -> Result<()> {
-        let versions_response = reqwest::get("https://releases.electronjs.org/releases.json")
-            .compat()
-            .await
-            .into_diagnostic()?;
+        if self.interactive && self.json {
+            return Err(BisectError::InteractiveJson.into());
+        }
+        // With --json, the only thing on stdout is the final result.
+        let say = |msg: String| {
+            if !self.quiet && !self.json {
+                println!("{}", msg);
+            }
+        };
+        let webhook = Webhook::from_opts(self.webhook.as_deref(), self.webhook_header.as_deref())?;
         let all_versions: Vec<ElectronVersion> =
-            versions_response.json().await.into_diagnostic()?;
+            serde_json::from_str(&self.releases_json().await?).into_diagnostic()?;
         let start_version = self.get_version(
             &self.start,
             &all_versions[all_versions.len() - 1].version.to_string(),
         )?;
         let end_version = self.get_version(&self.end, &all_versions[0].version.to_string())?;
-        let mut bisect_versions: Vec<ElectronVersion> = all_versions
-            .into_iter()
-            .filter(|version| {
-                !version.version.is_prerelease()
-                    && version.version >= start_version
-                    && version.version <= end_version
-            })
-            .collect();
-        bisect_versions.reverse();
+        let bisect_versions = self.candidate_versions(all_versions, &start_version, &end_version);
+
+        if self.dry_run {
+            self.print_dry_run(&bisect_versions);
+            return Ok(());
+        }
 
-        println!("Bisecting... {} to {}", start_version, end_version);
+        say(format!("Bisecting... {} to {}", start_version, end_version));
 
-        let mut min_rev = 0;
-        let mut max_rev = bisect_versions.len() - 1;
-        let mut pivot = (max_rev - min_rev) / 2;
-        let mut is_bisect_over = false;
-        while !is_bisect_over {
-            if max_rev - min_rev <= 1 {
-                is_bisect_over = true;
-            }
+        // `good` and `bad` are indices of the newest known-good and oldest
+        // known-bad versions. The endpoints are assumed, not tested.
+        let mut good = 0;
+        let mut bad = bisect_versions.len() - 1;
+        let mut skipped = HashSet::new();
+        // Every verdict reached, in order, for --json.
+        let mut decisions = Vec::new();
+        'versions: while let Some(pivot) = next_pivot(good, bad, &skipped) {
             let target_version = &bisect_versions[pivot];
-            println!("Testing {}", target_version.version);
-            let range = target_version
-                .version
-                .to_string()
-                .parse::<Range>()
-                .map_err(BisectError::SemverError)?;
-            let opts = ElectronOpts::new().range(range).include_prerelease(true);
-
-            let electron = opts.ensure_electron().await?;
-            println!(
+            say(format!("Testing {}", target_version.version));
+            let mut opts = self.electron_opts(&target_version.version)?;
+            if let Some(webhook) = &webhook {
+                opts = opts.on_event(webhook.download_milestones("bisect"));
+            }
+
+            // Failing to get or launch Electron says nothing about whether
+            // the version is good or bad, so with --keep-going those versions
+            // get skipped instead of being counted as failures.
+            let electron = match opts.ensure_electron().await {
+                Ok(electron) => electron,
+                Err(e) if self.keep_going => {
+                    tracing::warn!("Failed to get {}: {}", target_version.version, e);
+                    say(format!(
+                        "Skipping {}: could not download it.",
+                        target_version.version
+                    ));
+                    skipped.insert(pivot);
+                    decisions.push((&target_version.version, Outcome::Skip));
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(webhook) = &webhook {
+                webhook
+                    .send(&ColliderEvent::Resolved {
+                        command: "bisect".into(),
+                        electron: electron.info(),
+                    })
+                    .await;
+            }
+            say(format!(
                 "Successfully got {}; now running test",
                 target_version.version
-            );
-            let mut cmd = Command::new(electron.exe());
-            cmd.arg(&self.path);
-            let status = cmd.status().await.into_diagnostic()?;
-            let mut test_passed = status.success();
+            ));
+            let mut outcomes = Vec::new();
+            for path in &self.path {
+                let outcome = match self.run_case(&electron, path).await {
+                    Ok(outcome) => outcome,
+                    Err(e) if self.keep_going => {
+                        tracing::warn!("Failed to launch {}: {}", target_version.version, e);
+                        say(format!(
+                            "Skipping {}: could not launch it.",
+                            target_version.version
+                        ));
+                        skipped.insert(pivot);
+                        decisions.push((&target_version.version, Outcome::Skip));
+                        continue 'versions;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                outcomes.push(outcome);
+                if outcome == self.combine.decisive() {
+                    // The rest of the cases can't change the verdict.
+                    break;
+                }
+            }
+            let mut verdict = self.combine.verdict(&outcomes);
 
             if self.interactive {
-                test_passed = Confirm::with_theme(&ColorfulTheme::default())
+                let passed = Confirm::with_theme(&ColorfulTheme::default())
                     .with_prompt(format!(
                         "Did test case pass for {}?",
                         target_version.version
                     ))
                     .interact()
                     .into_diagnostic()?;
+                verdict = if passed { Outcome::Pass } else { Outcome::Fail };
             }
 
-            if test_passed {
-                println!("{} passed testing.", target_version.version);
-                let up_pivot = ((max_rev - pivot) / 2) + pivot;
-                min_rev = pivot;
-                if up_pivot != max_rev && up_pivot != pivot {
-                    pivot = up_pivot;
-                } else {
-                    is_bisect_over = true;
+            decisions.push((&target_version.version, verdict));
+            match verdict {
+                Outcome::Pass => {
+                    say(format!("{} passed testing.", target_version.version));
+                    good = pivot;
+                }
+                Outcome::Fail => {
+                    say(format!("{} failed testing.", target_version.version));
+                    bad = pivot;
                 }
-            } else {
-                println!("{} failed testing.", target_version.version);
-                let down_pivot = ((pivot - min_rev) / 2) + min_rev;
-                max_rev = pivot;
-                if down_pivot != min_rev && down_pivot != pivot {
-                    pivot = down_pivot;
-                } else {
-                    is_bisect_over = true;
+                Outcome::Skip => {
+                    say(format!(
+                        "Skipping {}: the test exited with code 125, so it can't be tested.",
+                        target_version.version
+                    ));
+                    skipped.insert(pivot);
                 }
             }
         }
-        println!("Bisect complete. Check the range {min_rev}...{max_rev} at https://github.com/electron/electron/compare/v{min_rev}...v{max_rev}", min_rev = &bisect_versions[min_rev].version, max_rev = &bisect_versions[max_rev].version);
+        let untested = (good + 1..bad).filter(|i| skipped.contains(i)).count();
+        if untested > 0 {
+            say(format!(
+                "Skipped {} version(s) in this range that could not be tested.",
+                untested
+            ));
+        }
+        let good = &bisect_versions[good].version;
+        let bad = &bisect_versions[bad].version;
+        let compare_url = format!(
+            "https://github.com/electron/electron/compare/v{}...v{}",
+            good, bad
+        );
+        if self.json {
+            let output = serde_json::json!({
+                "good_version": good.to_string(),
+                "bad_version": bad.to_string(),
+                "compare_url": compare_url,
+                "decisions": decisions
+                    .iter()
+                    .map(|(version, outcome)| serde_json::json!({
+                        "version": version.to_string(),
+                        "result": outcome.as_str(),
+                    }))
+                    .collect::<Vec<_>>(),
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&output).into_diagnostic()?
+            );
+        }
+        say(format!(
+            "Bisect complete. Check the range {}...{} at {}",
+            good, bad, compare_url
+        ));
         Ok(())
     }
 }
 
 impl BisectCmd {
+    /// releases.json, out of the cache if it's fresh enough, and from the
+    /// network otherwise. A stale cache still beats failing outright.
+    async fn releases_json(&self) -> Result<String> {
+        let cache = self.releases_cache();
+        let ttl = Duration::from_secs(self.releases_ttl * 60 * 60);
+        if let Some(cache) = &cache {
+            if !self.refresh {
+                if let Some(json) = fresh_cache(cache, ttl).await {
+                    tracing::debug!("Using cached releases from {}", cache.display());
+                    return Ok(json);
+                }
+            }
+        }
+        match self.base_opts().fetch_releases_json().await {
+            Ok(json) => {
+                if let Some(cache) = &cache {
+                    // Not being able to cache shouldn't stop the bisect.
+                    if let Err(e) = write_cache(cache, &json).await {
+                        tracing::warn!("Failed to cache releases at {}: {}", cache.display(), e);
+                    }
+                }
+                Ok(json)
+            }
+            Err(e) => {
+                let stale = match &cache {
+                    Some(cache) => fresh_cache(cache, Duration::MAX)
+                        .await
+                        .map(|json| (cache, json)),
+                    None => None,
+                };
+                match stale {
+                    Some((cache, json)) => {
+                        tracing::warn!(
+                            "Failed to fetch Electron releases ({}). Using the cached list from {}, which may be out of date.",
+                            e,
+                            cache_age(cache)
+                                .await
+                                .map(|age| format!("{} hour(s) ago", age.as_secs() / 3600))
+                                .unwrap_or_else(|| "an unknown time".into())
+                        );
+                        Ok(json)
+                    }
+                    None => Err(e.into()),
+                }
+            }
+        }
+    }
+
+    /// Where releases.json is cached, unless that's been turned off or
+    /// there's nowhere to put it.
+    fn releases_cache(&self) -> Option<PathBuf> {
+        if self.no_cache {
+            return None;
+        }
+        CacheDirs::resolve(self.cache_dir.as_deref())
+            .ok()
+            .map(|dirs| dirs.releases_json())
+    }
+
+    /// Versions between `start` and `end` (inclusive) to bisect over, oldest
+    /// first.
+    fn candidate_versions(
+        &self,
+        all_versions: Vec<ElectronVersion>,
+        start: &Version,
+        end: &Version,
+    ) -> Vec<ElectronVersion> {
+        let mut versions: Vec<ElectronVersion> = all_versions
+            .into_iter()
+            .filter(|version| {
+                (self.include_prerelease || !version.version.is_prerelease())
+                    && &version.version >= start
+                    && &version.version <= end
+            })
+            .collect();
+        versions.reverse();
+        versions
+    }
+
+    /// Options for fetching one specific version during the bisect. These
+    /// use the same prerelease setting as [`Self::candidate_versions`], so
+    /// anything that made it into the candidate set can actually be fetched.
+    fn electron_opts(&self, version: &Version) -> Result<ElectronOpts, BisectError> {
+        let range = version.to_string().parse::<Range>()?;
+        Ok(self
+            .base_opts()
+            .range(range)
+            .include_prerelease(self.include_prerelease))
+    }
+
+    /// Options shared by everything bisect fetches, releases.json included.
+    fn base_opts(&self) -> ElectronOpts {
+        let mut opts = ElectronOpts::new().progress_bar(!self.quiet && !self.json);
+        if let Some(token) = &self.github_token {
+            opts = opts.github_token(token);
+        }
+        if let Some(cache_dir) = &self.cache_dir {
+            opts = opts.cache_dir(cache_dir);
+        }
+        if let Some(prompt) = ConfirmRetry::if_interactive(self.quiet, self.json) {
+            opts = opts.retry_prompt(prompt);
+        }
+        opts
+    }
+
+    /// Tests one version against one case: either by running `--command`,
+    /// or by launching the app and going by Electron's exit code.
+    async fn run_case(&self, electron: &Electron, path: &Path) -> Result<Outcome, ElectronError> {
+        let result = match &self.command {
+            Some(command) => run_test_command(command, electron.exe(), path, &self.extra_args())
+                .await
+                .map_err(|e| {
+                    ElectronError::IoError(format!("Failed to run test command `{}`", command), e)
+                })
+                .and_then(|status| {
+                    if status.success() {
+                        Ok(status)
+                    } else {
+                        Err(ElectronError::ElectronFailed(status))
+                    }
+                }),
+            None => electron.launch(&self.electron_argv(path)).await,
+        };
+        match result {
+            Ok(_) => Ok(Outcome::Pass),
+            // A failing test is exactly what we're looking for.
+            Err(ElectronError::ElectronFailed(status)) => Ok(Outcome::of_failure(status)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// What Electron gets run with to test `path`.
+    fn electron_argv(&self, path: &Path) -> Vec<OsString> {
+        let mut args = self
+            .electron_args
+            .iter()
+            .map(OsString::from)
+            .collect::<Vec<_>>();
+        args.push(path.into());
+        args.extend(self.app_args.iter().map(OsString::from));
+        args
+    }
+
+    /// Everything besides the app path that Electron would have been run
+    /// with, quoted for a shell, for `--command` scripts to pass along.
+    fn extra_args(&self) -> String {
+        self.electron_args
+            .iter()
+            .chain(&self.app_args)
+            .map(|arg| shell_quote(arg.as_ref()).into_owned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn print_dry_run(&self, versions: &[ElectronVersion]) {
+        let steps = estimated_steps(versions.len());
+        let first = versions.first().map(|v| v.version.to_string());
+        let last = versions.last().map(|v| v.version.to_string());
+        if self.json {
+            let output = serde_json::json!({
+                "count": versions.len(),
+                "min": first,
+                "max": last,
+                "estimated_steps": steps,
+                "versions": versions.iter().map(|v| v.version.to_string()).collect::<Vec<_>>(),
+            });
+            println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        } else if !self.quiet {
+            for version in versions {
+                println!("{}", version.version);
+            }
+            println!(
+                "{} candidate versions, from {} to {}. Bisecting should take about {} steps.",
+                versions.len(),
+                first.as_deref().unwrap_or("(none)"),
+                last.as_deref().unwrap_or("(none)"),
+                steps
+            );
+        }
+    }
+
     fn get_version(
         &self,
         specified_version: &str,
@@ -170,3 +514,362 @@ impl BisectCmd {
         }
     }
 }
+
+/// What a single test case said about a version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Fail => "fail",
+            Outcome::Skip => "skip",
+        }
+    }
+
+    /// Like `git bisect run`, exit code 125 means the version couldn't be
+    /// tested at all. Any other failure means it's bad.
+    fn of_failure(status: ExitStatus) -> Self {
+        if status.code() == Some(125) {
+            Outcome::Skip
+        } else {
+            Outcome::Fail
+        }
+    }
+}
+
+/// How the outcomes of several test cases add up to a verdict on a version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combine {
+    And,
+    Or,
+}
+
+impl Combine {
+    /// The outcome that settles the verdict by itself, whatever the other
+    /// cases say.
+    fn decisive(self) -> Outcome {
+        match self {
+            Combine::And => Outcome::Fail,
+            Combine::Or => Outcome::Pass,
+        }
+    }
+
+    /// A decisive outcome wins. Otherwise, a skip anywhere means the version
+    /// can't be judged, so it's skipped as a whole.
+    fn verdict(self, outcomes: &[Outcome]) -> Outcome {
+        if outcomes.contains(&self.decisive()) {
+            self.decisive()
+        } else if outcomes.contains(&Outcome::Skip) {
+            Outcome::Skip
+        } else {
+            match self {
+                Combine::And => Outcome::Pass,
+                Combine::Or => Outcome::Fail,
+            }
+        }
+    }
+}
+
+impl FromStr for Combine {
+    type Err = BisectError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "and" => Ok(Combine::And),
+            "or" => Ok(Combine::Or),
+            _ => Err(BisectError::UnknownCombine(s.into())),
+        }
+    }
+}
+
+/// The cached releases.json at `path`, if it's no older than `ttl`.
+async fn fresh_cache(path: &Path, ttl: Duration) -> Option<String> {
+    if cache_age(path).await? > ttl {
+        return None;
+    }
+    fs::read_to_string(path).await.ok()
+}
+
+async fn cache_age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).await.ok()?.modified().ok()?;
+    // A timestamp from the future counts as brand new.
+    Some(
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default(),
+    )
+}
+
+async fn write_cache(path: &Path, json: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    // Write it whole under another name first, so a reader never sees half
+    // of it.
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).await?;
+    fs::rename(&tmp, path).await
+}
+
+/// Runs a `--command` test through the shell, pointed at `exe` and `app`.
+async fn run_test_command(
+    command: &str,
+    exe: &Path,
+    app: &Path,
+    extra_args: &str,
+) -> std::io::Result<ExitStatus> {
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    tracing::debug!("Running test command: {}", command);
+    cmd.arg(command)
+        .env("ELECTRON_EXE", exe)
+        .env("COLLIDER_ELECTRON_PATH", exe)
+        .env("ELECTRON_APP", app)
+        .env("ELECTRON_EXTRA_ARGS", extra_args)
+        .status()
+        .await
+}
+
+/// Roughly how many versions a bisect over `count` candidates will have to
+/// test: `ceil(log2(count))`.
+fn estimated_steps(count: usize) -> u32 {
+    if count <= 1 {
+        0
+    } else {
+        (count as f64).log2().ceil() as u32
+    }
+}
+
+/// Picks the next untested, unskipped version strictly between `good` and
+/// `bad`, preferring whichever is closest to the midpoint. Returns `None`
+/// once there's nothing left to test.
+fn next_pivot(good: usize, bad: usize, skipped: &HashSet<usize>) -> Option<usize> {
+    if bad <= good + 1 {
+        return None;
+    }
+    let mid = good + (bad - good) / 2;
+    let candidates = (good + 1..bad).filter(|i| !skipped.contains(i));
+    candidates.min_by_key(|i| if *i > mid { i - mid } else { mid - i })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parsing with clap needs the global args from the toplevel `Collider`
+    // app, so build these by hand instead.
+    fn bisect_cmd() -> BisectCmd {
+        BisectCmd {
+            path: vec![".".into()],
+            combine: Combine::And,
+            start: "*".into(),
+            end: "*".into(),
+            interactive: false,
+            include_prerelease: false,
+            github_token: None,
+            command: None,
+            refresh: false,
+            no_cache: false,
+            releases_ttl: 6,
+            keep_going: false,
+            dry_run: false,
+            electron_args: Vec::new(),
+            app_args: Vec::new(),
+            verbosity: tracing::Level::WARN,
+            quiet: true,
+            json: false,
+            cache_dir: None,
+            webhook: None,
+            webhook_header: None,
+        }
+    }
+
+    fn versions(versions: &[&str]) -> Vec<ElectronVersion> {
+        // releases.json lists newest first.
+        versions
+            .iter()
+            .rev()
+            .map(|v| ElectronVersion {
+                version: v.parse().unwrap(),
+            })
+            .collect()
+    }
+
+    fn version_strings(versions: &[ElectronVersion]) -> Vec<String> {
+        versions.iter().map(|v| v.version.to_string()).collect()
+    }
+
+    #[test]
+    fn prerelease_candidates_follow_flag() {
+        let all = || versions(&["12.0.0", "13.0.0-beta.1", "13.0.0", "14.0.0"]);
+        let start = "12.0.0".parse().unwrap();
+        let end = "13.0.0".parse().unwrap();
+
+        let mut cmd = bisect_cmd();
+        assert_eq!(
+            version_strings(&cmd.candidate_versions(all(), &start, &end)),
+            vec!["12.0.0", "13.0.0"]
+        );
+
+        cmd.include_prerelease = true;
+        assert_eq!(
+            version_strings(&cmd.candidate_versions(all(), &start, &end)),
+            vec!["12.0.0", "13.0.0-beta.1", "13.0.0"]
+        );
+    }
+
+    #[test]
+    fn github_token_is_threaded_into_opts() {
+        let url = "https://github.com/electron/electron/releases/download/v13.0.0/SHASUMS256.txt";
+        let version = "13.0.0".parse().unwrap();
+
+        let mut cmd = bisect_cmd();
+        let opts = cmd.electron_opts(&version).unwrap();
+        assert_eq!(opts.github_auth_header(url), None);
+
+        cmd.github_token = Some("abc123".into());
+        let opts = cmd.electron_opts(&version).unwrap();
+        assert_eq!(opts.github_auth_header(url), Some("token abc123".into()));
+    }
+
+    #[test]
+    fn releases_cache_follows_cache_dir() {
+        let mut cmd = bisect_cmd();
+        cmd.cache_dir = Some("/ci/collider".into());
+        assert_eq!(
+            cmd.releases_cache(),
+            Some(PathBuf::from("/ci/collider/downloads/releases.json"))
+        );
+
+        cmd.no_cache = true;
+        assert_eq!(cmd.releases_cache(), None);
+    }
+
+    #[test]
+    fn step_estimate() {
+        assert_eq!(estimated_steps(0), 0);
+        assert_eq!(estimated_steps(1), 0);
+        assert_eq!(estimated_steps(2), 1);
+        assert_eq!(estimated_steps(64), 6);
+        assert_eq!(estimated_steps(65), 7);
+    }
+
+    #[test]
+    fn outcomes_combine() {
+        use Outcome::*;
+        assert_eq!(Combine::And.verdict(&[Pass, Pass]), Pass);
+        assert_eq!(Combine::And.verdict(&[Pass, Fail]), Fail);
+        assert_eq!(Combine::And.verdict(&[Skip, Fail]), Fail);
+        assert_eq!(Combine::And.verdict(&[Pass, Skip]), Skip);
+        assert_eq!(Combine::Or.verdict(&[Fail, Pass]), Pass);
+        assert_eq!(Combine::Or.verdict(&[Skip, Pass]), Pass);
+        assert_eq!(Combine::Or.verdict(&[Fail, Skip]), Skip);
+        assert_eq!(Combine::Or.verdict(&[Fail, Fail]), Fail);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exit_code_125_skips() {
+        use std::os::unix::process::ExitStatusExt;
+        // Raw wait statuses keep the exit code in the second byte.
+        assert_eq!(
+            Outcome::of_failure(ExitStatus::from_raw(125 << 8)),
+            Outcome::Skip
+        );
+        assert_eq!(
+            Outcome::of_failure(ExitStatus::from_raw(1 << 8)),
+            Outcome::Fail
+        );
+    }
+
+    #[test]
+    fn pivot_avoids_skipped() {
+        let mut skipped = HashSet::new();
+        assert_eq!(next_pivot(0, 10, &skipped), Some(5));
+        skipped.insert(5);
+        assert_eq!(next_pivot(0, 10, &skipped), Some(4));
+        skipped.insert(4);
+        assert_eq!(next_pivot(0, 10, &skipped), Some(6));
+        assert_eq!(next_pivot(3, 4, &skipped), None);
+        assert_eq!(next_pivot(3, 6, &skipped), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_sees_electron_and_app() {
+        let run = |command| {
+            collider_common::smol::block_on(run_test_command(
+                command,
+                Path::new("/opt/electron"),
+                Path::new("my app"),
+                "--disable-gpu",
+            ))
+            .unwrap()
+        };
+        assert!(
+            run(r#"test "$ELECTRON_EXE" = /opt/electron && test "$ELECTRON_APP" = "my app""#)
+                .success()
+        );
+        assert!(run(r#"test "$ELECTRON_EXTRA_ARGS" = --disable-gpu"#).success());
+        assert!(run(r#"test "$COLLIDER_ELECTRON_PATH" = /opt/electron"#).success());
+        assert_eq!(run("exit 125").code(), Some(125));
+        assert!(!run("false").success());
+    }
+
+    #[test]
+    fn release_cache_expires() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("releases.json");
+        let hour = Duration::from_secs(60 * 60);
+        collider_common::smol::block_on(async {
+            assert_eq!(fresh_cache(&cache, hour).await, None);
+            write_cache(&cache, "[]").await.unwrap();
+            assert_eq!(fresh_cache(&cache, hour).await, Some("[]".into()));
+            std::thread::sleep(Duration::from_millis(10));
+            assert_eq!(fresh_cache(&cache, Duration::ZERO).await, None);
+        });
+    }
+
+    #[test]
+    fn interactive_json_conflict() {
+        let mut cmd = bisect_cmd();
+        cmd.interactive = true;
+        cmd.json = true;
+        let err = collider_common::smol::block_on(cmd.execute()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<BisectError>(),
+            Some(BisectError::InteractiveJson)
+        ));
+    }
+
+    #[test]
+    fn extra_args_surround_the_path() {
+        let mut cmd = bisect_cmd();
+        cmd.electron_args = vec!["--enable-logging".into()];
+        cmd.app_args = vec!["--disable-gpu".into(), "my flag".into()];
+        assert_eq!(
+            cmd.electron_argv(Path::new("app")),
+            vec!["--enable-logging", "app", "--disable-gpu", "my flag"]
+        );
+        assert_eq!(
+            cmd.extra_args(),
+            format!(
+                "--enable-logging --disable-gpu {}",
+                shell_quote("my flag".as_ref())
+            )
+        );
+    }
+}
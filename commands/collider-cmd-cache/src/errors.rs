@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+
+use collider_common::{
+    miette::{self, Diagnostic},
+    thiserror::{self, Error},
+};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum CacheError {
+    #[error(transparent)]
+    #[diagnostic(code(collider::cache::electron_error))]
+    ElectronError(#[from] collider_electron::ElectronError),
+
+    #[error("{0}")]
+    #[diagnostic(code(collider::cache::io_error))]
+    IoError(String, #[source] std::io::Error),
+
+    #[error(transparent)]
+    #[diagnostic(code(collider::cache::semver_error))]
+    SemverError(#[from] node_semver::SemverError),
+
+    #[error("Cache archive not found: {}", .0.display())]
+    #[diagnostic(code(collider::cache::archive_not_found))]
+    ArchiveNotFound(PathBuf),
+}
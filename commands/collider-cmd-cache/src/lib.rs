@@ -0,0 +1,740 @@
+use std::path::{Path, PathBuf};
+
+use collider_command::{
+    async_trait::async_trait,
+    clap::{self, ArgMatches, Clap},
+    collider_config::{self, ColliderConfig, ColliderConfigLayer},
+    tracing, ColliderCommand,
+};
+use collider_common::{
+    chrono::{DateTime, Duration, Local},
+    chrono_humanize::HumanTime,
+    miette::Result,
+    serde::Serialize,
+    serde_json,
+    smol::{self, fs, stream::StreamExt},
+};
+use collider_electron::{
+    current_collider_version, dir_size, extraction_dir, move_into_place, parse_triple, CacheDirs,
+    ElectronError,
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use node_semver::{Range, Version};
+use tar::{Archive, Builder};
+
+pub use errors::CacheError;
+
+mod errors;
+
+#[derive(Debug, Clap)]
+pub struct CacheCmd {
+    #[clap(subcommand)]
+    subcommand: CacheSubCmd,
+}
+
+#[derive(Debug, Clap)]
+pub enum CacheSubCmd {
+    #[clap(
+        about = "Archive cached Electron builds into a .tar.gz, e.g. to move them to another machine or a CI cache.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    Export(ExportCmd),
+    #[clap(
+        about = "Unpack an archive made by `collider cache export` into the local cache.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    Import(ImportCmd),
+    #[clap(
+        about = "List cached Electron builds, with their size on disk and when they were last used.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    List(ListCmd),
+    #[clap(
+        about = "Remove cached Electron builds.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    Clean(CleanCmd),
+}
+
+#[derive(Debug, Clap, ColliderConfigLayer)]
+pub struct ExportCmd {
+    #[clap(about = "Path of the .tar.gz file to write.")]
+    file: PathBuf,
+
+    #[clap(
+        long,
+        short,
+        about = "Only export Electron versions matching this range."
+    )]
+    using: Option<String>,
+
+    #[clap(from_global)]
+    quiet: bool,
+
+    #[clap(from_global)]
+    json: bool,
+
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clap, ColliderConfigLayer)]
+pub struct ImportCmd {
+    #[clap(about = "Path of a .tar.gz file made by `collider cache export`.")]
+    file: PathBuf,
+
+    #[clap(from_global)]
+    quiet: bool,
+
+    #[clap(from_global)]
+    json: bool,
+
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clap, ColliderConfigLayer)]
+pub struct ListCmd {
+    #[clap(from_global)]
+    quiet: bool,
+
+    #[clap(from_global)]
+    json: bool,
+
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clap, ColliderConfigLayer)]
+pub struct CleanCmd {
+    #[clap(
+        long,
+        short,
+        visible_alias = "keep-latest",
+        about = "Keep builds for this many of the newest cached Electron versions, and remove the rest."
+    )]
+    keep: Option<usize>,
+
+    #[clap(
+        long,
+        about = "Only remove builds that haven't been used in this many days."
+    )]
+    older_than: Option<u64>,
+
+    #[clap(
+        long,
+        short,
+        about = "Also remove the Electron version collider itself is running under."
+    )]
+    force: bool,
+
+    #[clap(
+        long,
+        about = "Print what would be removed, without removing anything."
+    )]
+    dry_run: bool,
+
+    #[clap(from_global)]
+    quiet: bool,
+
+    #[clap(from_global)]
+    json: bool,
+
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+}
+
+#[async_trait]
+impl ColliderCommand for CacheCmd {
+    async fn execute(self) -> Result<()> {
+        match self.subcommand {
+            CacheSubCmd::Export(cmd) => cmd.execute().await,
+            CacheSubCmd::Import(cmd) => cmd.execute().await,
+            CacheSubCmd::List(cmd) => cmd.execute().await,
+            CacheSubCmd::Clean(cmd) => cmd.execute().await,
+        }
+    }
+}
+
+impl ColliderConfigLayer for CacheCmd {
+    fn layer_config(&mut self, args: &ArgMatches, conf: &ColliderConfig) -> Result<()> {
+        let (cmd, match_name): (&mut dyn ColliderConfigLayer, &str) = match self.subcommand {
+            CacheSubCmd::Export(ref mut cmd) => (cmd, "export"),
+            CacheSubCmd::Import(ref mut cmd) => (cmd, "import"),
+            CacheSubCmd::List(ref mut cmd) => (cmd, "list"),
+            CacheSubCmd::Clean(ref mut cmd) => (cmd, "clean"),
+        };
+        cmd.layer_config(args.subcommand_matches(match_name).unwrap(), conf)
+    }
+}
+
+#[async_trait]
+impl ColliderCommand for ExportCmd {
+    async fn execute(self) -> Result<()> {
+        let range = self
+            .using
+            .as_ref()
+            .map(|using| using.parse::<Range>())
+            .transpose()
+            .map_err(CacheError::SemverError)?;
+        let exported = export_cache(
+            &electron_cache_dir(self.cache_dir.as_deref())?,
+            &self.file,
+            range.as_ref(),
+        )
+        .await?;
+        report(&exported, "Exported", &self.file, self.quiet, self.json);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ColliderCommand for ImportCmd {
+    async fn execute(self) -> Result<()> {
+        let imported =
+            import_cache(&self.file, &electron_cache_dir(self.cache_dir.as_deref())?).await?;
+        report(&imported, "Imported", &self.file, self.quiet, self.json);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ColliderCommand for ListCmd {
+    async fn execute(self) -> Result<()> {
+        let builds = cached_builds(&electron_cache_dir(self.cache_dir.as_deref())?).await?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&builds).unwrap());
+        } else if !self.quiet {
+            for build in &builds {
+                println!(
+                    "{}\t{:.1} MB\tlast used {}",
+                    build.triple,
+                    build.size as f64 / 1_000_000.0,
+                    build
+                        .accessed
+                        .map(|at| HumanTime::from(at).to_string())
+                        .unwrap_or_else(|| "at an unknown time".into())
+                );
+            }
+            let total: u64 = builds.iter().map(|b| b.size).sum();
+            println!(
+                "{} cached build(s), {:.1} MB total.",
+                builds.len(),
+                total as f64 / 1_000_000.0
+            );
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ColliderCommand for CleanCmd {
+    async fn execute(self) -> Result<()> {
+        let current = if self.force {
+            None
+        } else {
+            current_collider_version().await?
+        };
+        let removed = self
+            .clean_in(
+                &electron_cache_dir(self.cache_dir.as_deref())?,
+                current.as_ref(),
+            )
+            .await?;
+        if self.json {
+            let triples = removed.iter().map(|b| &b.triple).collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&triples).unwrap());
+        } else if !self.quiet {
+            for build in &removed {
+                println!(
+                    "{}\t{:.1} MB\t{}",
+                    build.triple,
+                    build.size as f64 / 1_000_000.0,
+                    build.path.display()
+                );
+            }
+            let total: u64 = removed.iter().map(|b| b.size).sum();
+            println!(
+                "{} {} cached build(s), {:.1} MB total.",
+                if self.dry_run {
+                    "Would remove"
+                } else {
+                    "Removed"
+                },
+                removed.len(),
+                total as f64 / 1_000_000.0
+            );
+        }
+        Ok(())
+    }
+}
+
+impl CleanCmd {
+    /// Removes the selected builds from `cache_dir` (or, with `--dry-run`,
+    /// just picks them), and returns them.
+    async fn clean_in(
+        &self,
+        cache_dir: &Path,
+        current: Option<&Version>,
+    ) -> Result<Vec<CachedBuild>, CacheError> {
+        let builds = cached_builds(cache_dir).await?;
+        let unused_since = self
+            .older_than
+            .map(|days| Local::now() - Duration::days(days as i64));
+        let (remove, spared) = select_for_removal(&builds, self.keep, unused_since, current);
+        for build in &spared {
+            tracing::warn!(
+                "Not removing {}, since collider is running under it. Pass --force to remove it anyway.",
+                build.triple
+            );
+        }
+        let mut removed = Vec::new();
+        for build in remove {
+            if !self.dry_run {
+                tracing::debug!("Removing {}", build.path.display());
+                fs::remove_dir_all(&build.path).await.map_err(|e| {
+                    CacheError::IoError(format!("Failed to remove {}", build.path.display()), e)
+                })?;
+            }
+            removed.push(build.clone());
+        }
+        Ok(removed)
+    }
+}
+
+/// A build in the Electron cache, as reported by `collider cache list`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedBuild {
+    pub triple: String,
+    #[serde(skip)]
+    version: Version,
+    pub path: PathBuf,
+    /// Total size on disk, in bytes.
+    pub size: u64,
+    /// When the build was last accessed, if the filesystem tracks that.
+    pub accessed: Option<DateTime<Local>>,
+}
+
+/// Every build in `cache_dir`, newest version first.
+pub async fn cached_builds(cache_dir: &Path) -> Result<Vec<CachedBuild>, CacheError> {
+    let cache_dir = cache_dir.to_owned();
+    smol::unblock(move || {
+        let mut builds = Vec::new();
+        let entries = match std::fs::read_dir(&cache_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(builds),
+            Err(e) => {
+                return Err(CacheError::IoError(
+                    format!("Failed to read cache directory at {}", cache_dir.display()),
+                    e,
+                ))
+            }
+        };
+        for entry in entries {
+            let entry =
+                entry.map_err(|e| CacheError::IoError("Failed to read cache entry".into(), e))?;
+            let name = entry.file_name();
+            let (triple, version) = match name
+                .to_str()
+                .and_then(|name| Some((name, parse_triple(name)?.0)))
+            {
+                Some((triple, version)) => (triple.to_owned(), version),
+                None => continue,
+            };
+            let path = entry.path();
+            let io_err =
+                |e| CacheError::IoError(format!("Failed to inspect {}", path.display()), e);
+            let accessed = entry
+                .metadata()
+                .and_then(|meta| meta.accessed())
+                .ok()
+                .map(DateTime::<Local>::from);
+            builds.push(CachedBuild {
+                triple,
+                version,
+                size: dir_size(&path).map_err(io_err)?,
+                accessed,
+                path,
+            });
+        }
+        builds.sort_by(|a, b| {
+            b.version
+                .cmp(&a.version)
+                .then_with(|| a.triple.cmp(&b.triple))
+        });
+        Ok(builds)
+    })
+    .await
+}
+
+/// Picks which of `builds` (sorted newest first) `collider cache clean`
+/// should remove. With `keep`, builds of the `keep` newest versions are left
+/// alone. With `unused_since`, so are builds accessed after it, or whose
+/// access time is unknown. Builds of `current` (the version collider is
+/// running under) are never removed, and are returned separately so the
+/// caller can say so.
+fn select_for_removal<'a>(
+    builds: &'a [CachedBuild],
+    keep: Option<usize>,
+    unused_since: Option<DateTime<Local>>,
+    current: Option<&Version>,
+) -> (Vec<&'a CachedBuild>, Vec<&'a CachedBuild>) {
+    let mut kept_versions = Vec::new();
+    let mut remove = Vec::new();
+    let mut spared = Vec::new();
+    for build in builds {
+        if kept_versions.contains(&&build.version) {
+            continue;
+        }
+        if kept_versions.len() < keep.unwrap_or(0) {
+            kept_versions.push(&build.version);
+            continue;
+        }
+        if let Some(cutoff) = unused_since {
+            if build.accessed.map(|at| at > cutoff).unwrap_or(true) {
+                continue;
+            }
+        }
+        if Some(&build.version) == current {
+            spared.push(build);
+        } else {
+            remove.push(build);
+        }
+    }
+    (remove, spared)
+}
+
+fn electron_cache_dir(cache_dir: Option<&Path>) -> Result<PathBuf, ElectronError> {
+    Ok(CacheDirs::resolve(cache_dir)?.builds)
+}
+
+fn report(triples: &[String], verb: &str, file: &Path, quiet: bool, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(triples).unwrap());
+    } else if !quiet {
+        for triple in triples {
+            println!("{}", triple);
+        }
+        println!(
+            "{} {} build(s) via {}.",
+            verb,
+            triples.len(),
+            file.display()
+        );
+    }
+}
+
+/// Writes every cached Electron build (optionally only those matching
+/// `range`) into a gzipped tarball at `dest`, one top-level directory per
+/// target triple. Returns the triples that were exported.
+pub async fn export_cache(
+    cache_dir: &Path,
+    dest: &Path,
+    range: Option<&Range>,
+) -> Result<Vec<String>, CacheError> {
+    let mut triples = Vec::new();
+    if fs::metadata(cache_dir).await.is_ok() {
+        let read_err = |e| {
+            CacheError::IoError(
+                format!("Failed to read cache directory at {}", cache_dir.display()),
+                e,
+            )
+        };
+        let mut entries = fs::read_dir(cache_dir).await.map_err(read_err)?;
+        while let Some(entry) = entries.next().await {
+            let name = entry
+                .map_err(|e| CacheError::IoError("Failed to read cache entry".into(), e))?
+                .file_name();
+            let name = match name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+            if let Some((version, _, _)) = parse_triple(name) {
+                if range.map(|r| r.satisfies(&version)).unwrap_or(true) {
+                    triples.push(name.to_owned());
+                }
+            }
+        }
+    }
+    triples.sort();
+
+    let cache_dir = cache_dir.to_owned();
+    let dest = dest.to_owned();
+    let to_archive = triples.clone();
+    smol::unblock(move || {
+        let io_err = |e| {
+            CacheError::IoError(
+                format!("Failed to write cache archive to {}", dest.display()),
+                e,
+            )
+        };
+        let file = std::fs::File::create(&dest).map_err(io_err)?;
+        let mut builder = Builder::new(GzEncoder::new(file, Compression::default()));
+        // macOS builds are full of framework symlinks. Keep them as links.
+        builder.follow_symlinks(false);
+        for triple in &to_archive {
+            tracing::debug!("Archiving {}", triple);
+            builder
+                .append_dir_all(triple, cache_dir.join(triple))
+                .map_err(io_err)?;
+        }
+        builder
+            .into_inner()
+            .and_then(|gz| gz.finish())
+            .map_err(io_err)?;
+        Ok::<_, CacheError>(())
+    })
+    .await?;
+    Ok(triples)
+}
+
+/// Unpacks an archive created by [`export_cache`] into `cache_dir`. Anything
+/// in the archive that isn't under a target triple directory is ignored.
+/// Nothing shows up in the cache until the whole archive has been unpacked,
+/// and builds that are already cached are kept. Returns the triples that
+/// were imported.
+pub async fn import_cache(archive: &Path, cache_dir: &Path) -> Result<Vec<String>, CacheError> {
+    if fs::metadata(archive).await.is_err() {
+        return Err(CacheError::ArchiveNotFound(archive.to_owned()));
+    }
+    fs::create_dir_all(cache_dir).await.map_err(|e| {
+        CacheError::IoError(
+            format!(
+                "Failed to create cache directory at {}",
+                cache_dir.display()
+            ),
+            e,
+        )
+    })?;
+    // Hidden and named like any other extraction, so an interrupted import
+    // gets cleaned up the same way.
+    let staging = extraction_dir(&cache_dir.join("import"));
+    let unpacked = {
+        let archive = archive.to_owned();
+        let staging = staging.clone();
+        smol::unblock(move || unpack_builds(&archive, &staging)).await
+    };
+    let triples = match unpacked {
+        Ok(triples) => triples,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging).await;
+            return Err(e);
+        }
+    };
+    for triple in &triples {
+        let moved = move_into_place(&staging.join(triple), &cache_dir.join(triple), false).await;
+        if let Err(e) = moved {
+            let _ = fs::remove_dir_all(&staging).await;
+            return Err(e.into());
+        }
+    }
+    let _ = fs::remove_dir_all(&staging).await;
+    Ok(triples)
+}
+
+/// Unpacks the target triple directories in `archive` into `dest`.
+fn unpack_builds(archive: &Path, dest: &Path) -> Result<Vec<String>, CacheError> {
+    let io_err = |e| {
+        CacheError::IoError(
+            format!("Failed to extract cache archive {}", archive.display()),
+            e,
+        )
+    };
+    let file = std::fs::File::open(archive).map_err(io_err)?;
+    std::fs::create_dir_all(dest).map_err(io_err)?;
+    let mut tarball = Archive::new(GzDecoder::new(file));
+    let mut triples = Vec::new();
+    for entry in tarball.entries().map_err(io_err)? {
+        let mut entry = entry.map_err(io_err)?;
+        let triple = entry
+            .path()
+            .map_err(io_err)?
+            .components()
+            .next()
+            .and_then(|c| c.as_os_str().to_str())
+            .filter(|name| parse_triple(name).is_some())
+            .map(String::from);
+        let triple = match triple {
+            Some(triple) => triple,
+            None => {
+                tracing::warn!("Skipping unexpected archive entry: {:?}", entry.path());
+                continue;
+            }
+        };
+        entry.unpack_in(dest).map_err(io_err)?;
+        if !triples.contains(&triple) {
+            triples.push(triple);
+        }
+    }
+    Ok(triples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_build(cache: &Path, triple: &str) {
+        let dir = cache.join(triple);
+        std::fs::create_dir_all(dir.join("resources")).unwrap();
+        std::fs::write(dir.join("electron"), triple).unwrap();
+        std::fs::write(dir.join("resources").join("default_app.asar"), "asar").unwrap();
+    }
+
+    #[test]
+    fn clean_keeps_newest_and_current() {
+        let cache = tempfile::tempdir().unwrap();
+        for triple in &[
+            "v12.0.0-linux-x64",
+            "v13.1.7-linux-x64",
+            "v13.1.7-win32-ia32",
+            "v14.0.0-linux-x64",
+        ] {
+            fake_build(cache.path(), triple);
+        }
+        std::fs::create_dir_all(cache.path().join("shims")).unwrap();
+        let builds = smol::block_on(cached_builds(cache.path())).unwrap();
+        assert_eq!(builds.len(), 4);
+        assert_eq!(builds[0].triple, "v14.0.0-linux-x64");
+        assert_eq!(builds[0].size, "v14.0.0-linux-x64".len() as u64 + 4);
+
+        fn triples(builds: Vec<&CachedBuild>) -> Vec<&str> {
+            builds.into_iter().map(|b| b.triple.as_str()).collect()
+        }
+        let (remove, spared) = select_for_removal(&builds, Some(2), None, None);
+        assert_eq!(triples(remove), vec!["v12.0.0-linux-x64"]);
+        assert!(spared.is_empty());
+
+        let current = "13.1.7".parse().unwrap();
+        let (remove, spared) = select_for_removal(&builds, None, None, Some(&current));
+        assert_eq!(
+            triples(remove),
+            vec!["v14.0.0-linux-x64", "v12.0.0-linux-x64"]
+        );
+        assert_eq!(
+            triples(spared),
+            vec!["v13.1.7-linux-x64", "v13.1.7-win32-ia32"]
+        );
+
+        let mut builds = builds;
+        let now = Local::now();
+        builds[0].accessed = Some(now);
+        builds[1].accessed = None;
+        builds[2].accessed = Some(now - Duration::days(40));
+        builds[3].accessed = Some(now - Duration::days(40));
+        let cutoff = Some(now - Duration::days(30));
+        let (remove, _) = select_for_removal(&builds, None, cutoff, None);
+        assert_eq!(
+            triples(remove),
+            vec![builds[2].triple.as_str(), "v12.0.0-linux-x64"]
+        );
+    }
+
+    #[test]
+    fn dry_run_reports_what_clean_removes() {
+        let cache = tempfile::tempdir().unwrap();
+        for triple in &[
+            "v12.0.0-linux-x64",
+            "v13.1.7-linux-x64",
+            "v14.0.0-linux-x64",
+        ] {
+            fake_build(cache.path(), triple);
+        }
+        let mut cmd = CleanCmd {
+            keep: Some(1),
+            older_than: None,
+            force: true,
+            dry_run: true,
+            quiet: true,
+            json: false,
+            cache_dir: None,
+        };
+        let paths =
+            |builds: Vec<CachedBuild>| builds.into_iter().map(|b| b.path).collect::<Vec<_>>();
+
+        let would_remove = paths(smol::block_on(cmd.clean_in(cache.path(), None)).unwrap());
+        assert_eq!(would_remove.len(), 2);
+        assert!(would_remove.iter().all(|path| path.exists()));
+
+        cmd.dry_run = false;
+        let removed = paths(smol::block_on(cmd.clean_in(cache.path(), None)).unwrap());
+        assert_eq!(removed, would_remove);
+        assert!(removed.iter().all(|path| !path.exists()));
+        assert!(cache.path().join("v14.0.0-linux-x64").exists());
+    }
+
+    #[test]
+    fn export_clean_import_roundtrip() {
+        let cache = tempfile::tempdir().unwrap();
+        fake_build(cache.path(), "v12.0.0-linux-x64");
+        fake_build(cache.path(), "v13.1.7-linux-x64");
+        fake_build(cache.path(), "v13.1.7-win32-ia32");
+        std::fs::create_dir_all(cache.path().join("not-a-build")).unwrap();
+
+        let out = tempfile::tempdir().unwrap();
+        let archive = out.path().join("cache.tar.gz");
+        let range = "^13".parse::<Range>().unwrap();
+        let exported = smol::block_on(export_cache(cache.path(), &archive, Some(&range))).unwrap();
+        assert_eq!(exported, vec!["v13.1.7-linux-x64", "v13.1.7-win32-ia32"]);
+
+        // Simulate moving to a fresh machine.
+        let fresh = tempfile::tempdir().unwrap();
+        let imported = smol::block_on(import_cache(&archive, fresh.path())).unwrap();
+        assert_eq!(imported, exported);
+        assert_eq!(
+            std::fs::read_to_string(fresh.path().join("v13.1.7-linux-x64").join("electron"))
+                .unwrap(),
+            "v13.1.7-linux-x64"
+        );
+        assert!(fresh
+            .path()
+            .join("v13.1.7-win32-ia32/resources/default_app.asar")
+            .exists());
+        assert!(!fresh.path().join("v12.0.0-linux-x64").exists());
+        assert!(!fresh.path().join("not-a-build").exists());
+    }
+
+    #[test]
+    fn interrupted_import_leaves_no_builds() {
+        let cache = tempfile::tempdir().unwrap();
+        fake_build(cache.path(), "v13.1.7-linux-x64");
+        std::fs::write(
+            cache.path().join("v13.1.7-linux-x64").join("big"),
+            vec![7u8; 64 * 1024],
+        )
+        .unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let archive = out.path().join("cache.tar.gz");
+        smol::block_on(export_cache(cache.path(), &archive, None)).unwrap();
+        let bytes = std::fs::read(&archive).unwrap();
+        std::fs::write(&archive, &bytes[..bytes.len() / 2]).unwrap();
+
+        let fresh = tempfile::tempdir().unwrap();
+        assert!(smol::block_on(import_cache(&archive, fresh.path())).is_err());
+        assert_eq!(std::fs::read_dir(fresh.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn unparsable_config_is_an_error() {
+        use collider_command::clap::IntoApp;
+
+        let args = ["clean"];
+        let matches = CleanCmd::into_app().get_matches_from(args);
+        let mut cmd = CleanCmd::try_parse_from(args).unwrap();
+        let mut config = ColliderConfig::new();
+        config.set("older_than", "a while").unwrap();
+        assert!(cmd.layer_config(&matches, &config).is_err());
+
+        config.set("older_than", "30").unwrap();
+        let mut cmd = CleanCmd::try_parse_from(args).unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+        assert_eq!(cmd.older_than, Some(30));
+    }
+}
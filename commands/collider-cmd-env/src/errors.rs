@@ -0,0 +1,15 @@
+use collider_common::{
+    miette::{self, Diagnostic},
+    thiserror::{self, Error},
+};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum EnvError {
+    #[error("{0}")]
+    #[diagnostic(code(collider::env::io_error))]
+    IoError(String, #[source] std::io::Error),
+
+    #[error(transparent)]
+    #[diagnostic(code(collider::env::semver_error))]
+    SemverError(#[from] node_semver::SemverError),
+}
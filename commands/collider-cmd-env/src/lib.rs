@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+
+use collider_command::{
+    async_trait::async_trait,
+    clap::{self, Clap},
+    collider_config::{self, ColliderConfigLayer},
+    ColliderCommand, ConfirmRetry,
+};
+use collider_common::{miette::Result, serde_json, smol::fs};
+use collider_electron::{CacheDirs, Electron, ElectronOpts};
+use node_semver::Range;
+
+pub use errors::EnvError;
+
+mod errors;
+
+/// Shells `collider env` knows how to write setup lines for.
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+#[derive(Debug, Clap, ColliderConfigLayer)]
+pub struct EnvCmd {
+    #[clap(
+        long,
+        short,
+        about = "Electron version to put on PATH. Defaults to the `electron` entry in the current directory's .tool-versions file, if there is one, and otherwise the latest version.",
+        default_value = "*"
+    )]
+    using: String,
+
+    #[clap(
+        long,
+        short = 'p',
+        about = "Include prerelease versions when trying to find a version match."
+    )]
+    include_prerelease: bool,
+
+    #[clap(
+        long,
+        about = "Shell to print setup lines for. Defaults to guessing from $SHELL.",
+        possible_values = SHELLS
+    )]
+    shell: Option<String>,
+
+    #[clap(from_global)]
+    quiet: bool,
+
+    #[clap(from_global)]
+    json: bool,
+
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+}
+
+#[async_trait]
+impl ColliderCommand for EnvCmd {
+    async fn execute(self) -> Result<()> {
+        let mut opts = ElectronOpts::new()
+            .include_prerelease(self.include_prerelease)
+            .progress_bar(!self.quiet && !self.json)
+            .project_dir(".");
+        if let Some(cache_dir) = &self.cache_dir {
+            opts = opts.cache_dir(cache_dir);
+        }
+        if let Some(prompt) = ConfirmRetry::if_interactive(self.quiet, self.json) {
+            opts = opts.retry_prompt(prompt);
+        }
+        if self.using != "*" {
+            opts = opts.range(self.using.parse::<Range>().map_err(EnvError::SemverError)?);
+        }
+        let electron = opts.ensure_electron().await?;
+        let bin_dir = CacheDirs::resolve(self.cache_dir.as_deref())?
+            .builds
+            .join("shims")
+            .join(electron.triple());
+        write_shim(&electron, &bin_dir).await?;
+
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "bin_dir": bin_dir,
+                    "electron": electron.info(),
+                }))
+                .unwrap()
+            );
+        } else {
+            // This is meant to be eval'd, so it's printed even with --quiet.
+            let shell = self.shell.as_deref().unwrap_or_else(|| detect_shell());
+            println!("{}", path_setup(shell, &bin_dir));
+        }
+        Ok(())
+    }
+}
+
+/// Puts an `electron` in `bin_dir` that runs `electron`'s executable. On
+/// Unix, that's just a symlink. Windows gets a `.cmd` wrapper instead, since
+/// symlinks there need special privileges.
+async fn write_shim(electron: &Electron, bin_dir: &Path) -> Result<PathBuf, EnvError> {
+    fs::create_dir_all(bin_dir).await.map_err(|e| {
+        EnvError::IoError(
+            format!("Failed to create shim directory at {}", bin_dir.display()),
+            e,
+        )
+    })?;
+    let shim = if cfg!(windows) {
+        bin_dir.join("electron.cmd")
+    } else {
+        bin_dir.join("electron")
+    };
+    // Always rewrite it, in case the cached build moved or was reinstalled.
+    let _ = fs::remove_file(&shim).await;
+    let written = if cfg!(windows) {
+        fs::write(&shim, format!("@\"{}\" %*\r\n", electron.exe().display())).await
+    } else {
+        link_exe(electron.exe(), &shim)
+    };
+    written
+        .map_err(|e| EnvError::IoError(format!("Failed to write shim at {}", shim.display()), e))?;
+    Ok(shim)
+}
+
+#[cfg(unix)]
+fn link_exe(exe: &Path, shim: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(exe, shim)
+}
+
+#[cfg(not(unix))]
+fn link_exe(_exe: &Path, _shim: &Path) -> std::io::Result<()> {
+    unreachable!("Only Unix uses symlinked shims.")
+}
+
+fn detect_shell() -> &'static str {
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let name = Path::new(&shell)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    match name {
+        "zsh" => "zsh",
+        "fish" => "fish",
+        "bash" => "bash",
+        _ if cfg!(windows) => "powershell",
+        _ => "bash",
+    }
+}
+
+/// The line to `eval` in `shell` to put `bin_dir` at the front of PATH.
+fn path_setup(shell: &str, bin_dir: &Path) -> String {
+    let dir = bin_dir.display().to_string();
+    match shell {
+        "fish" => format!(
+            "set -gx PATH '{}' $PATH",
+            dir.replace('\\', "\\\\").replace('\'', "\\'")
+        ),
+        "powershell" => format!(
+            "$env:PATH = '{}' + [IO.Path]::PathSeparator + $env:PATH",
+            dir.replace('\'', "''")
+        ),
+        _ => format!("export PATH='{}':\"$PATH\"", dir.replace('\'', "'\\''")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_setup_per_shell() {
+        let dir = Path::new("/home/me/it's/shims");
+        assert_eq!(
+            path_setup("bash", dir),
+            r#"export PATH='/home/me/it'\''s/shims':"$PATH""#
+        );
+        assert_eq!(path_setup("zsh", dir), path_setup("bash", dir));
+        assert_eq!(
+            path_setup("fish", dir),
+            r#"set -gx PATH '/home/me/it\'s/shims' $PATH"#
+        );
+        assert_eq!(
+            path_setup("powershell", dir),
+            "$env:PATH = '/home/me/it''s/shims' + [IO.Path]::PathSeparator + $env:PATH"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_compat::CompatExt;
+use collider_command::{
+    async_trait::async_trait,
+    clap::{self, Clap},
+    collider_config::{self, ColliderConfigLayer},
+    owo_colors::OwoColorize,
+    tool_command, ColliderCommand, PackageManager, PACKAGE_MANAGERS,
+};
+use collider_common::{
+    miette::{IntoDiagnostic, Result},
+    serde::Serialize,
+    serde_json, smol,
+};
+use collider_electron::{build_http_client, CacheDirs, ElectronError};
+use node_semver::Version;
+
+use crate::errors::InfoError;
+
+/// Tools `collider doctor` looks for when the project uses `pm`: the oldest
+/// version that'll do, and whether collider is any use without it.
+fn doctor_tools(pm: PackageManager) -> Vec<(&'static str, &'static str, bool)> {
+    let mut tools = match pm {
+        // `npx <pkg>@<version>` without prompting, and lockfile v2.
+        PackageManager::Npm => vec![("npm", "7.0.0", true), ("npx", "7.0.0", true)],
+        // Yarn runs one-off packages through npx.
+        PackageManager::Yarn => vec![("yarn", "1.0.0", true), ("npx", "7.0.0", true)],
+        // The first with `pnpm dlx`.
+        PackageManager::Pnpm => vec![("pnpm", "6.13.0", true)],
+    };
+    // Only `collider new` needs it.
+    tools.push(("git", "2.0.0", false));
+    tools
+}
+
+/// The feed every Electron version lookup starts from.
+const RELEASES_URL: &str = "https://releases.electronjs.org/releases.json";
+
+/// How long to wait on the network check.
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clap, ColliderConfigLayer)]
+pub struct DoctorCmd {
+    #[clap(
+        long,
+        about = "Skip checking that Electron's release feed is reachable, e.g. on machines that only use already-downloaded versions."
+    )]
+    offline: bool,
+
+    #[clap(
+        long,
+        about = "Package manager to check for. By default, this is detected from the lockfile in the current directory, falling back to npm.",
+        possible_values = PACKAGE_MANAGERS
+    )]
+    package_manager: Option<String>,
+
+    #[clap(from_global)]
+    quiet: bool,
+
+    #[clap(from_global)]
+    json: bool,
+
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+}
+
+/// The result of one thing `collider doctor` checks.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: String,
+    /// Whether collider can't do its job without this passing. Failed
+    /// optional checks are only warnings.
+    pub required: bool,
+    pub ok: bool,
+    /// What was found, or what's wrong.
+    pub detail: String,
+}
+
+#[async_trait]
+impl ColliderCommand for DoctorCmd {
+    async fn execute(self) -> Result<()> {
+        let pm = PackageManager::resolve(
+            self.package_manager.as_deref(),
+            &std::env::current_dir().into_diagnostic()?,
+        )?;
+        let mut checks = Vec::new();
+        for (tool, min, required) in doctor_tools(pm) {
+            checks.push(check_tool(tool, min, required).await);
+        }
+        let dirs = CacheDirs::resolve(self.cache_dir.as_deref())?;
+        checks.push(check_writable("data directory", &dirs.builds).await);
+        checks.push(check_writable("cache directory", &dirs.downloads).await);
+        if !self.offline {
+            checks.push(check_network().await);
+        }
+
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&checks).into_diagnostic()?
+            );
+        } else if !self.quiet {
+            for check in &checks {
+                let mark = match (check.ok, check.required) {
+                    (true, _) => "✔".green().to_string(),
+                    (false, true) => "✘".red().to_string(),
+                    (false, false) => "!".yellow().to_string(),
+                };
+                println!("{} {}: {}", mark, check.name, check.detail);
+            }
+        }
+        let failed = checks
+            .iter()
+            .filter(|check| check.required && !check.ok)
+            .map(|check| check.name.clone())
+            .collect::<Vec<_>>();
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(InfoError::DoctorFailed(failed).into())
+        }
+    }
+}
+
+/// Checks that `tool` is on PATH, and at least version `min`.
+async fn check_tool(tool: &str, min: &str, required: bool) -> Check {
+    let check = |ok, detail| Check {
+        name: tool.into(),
+        required,
+        ok,
+        detail,
+    };
+    let path = match which::which(tool) {
+        Ok(path) => path,
+        Err(_) => return check(false, "not found on PATH".into()),
+    };
+    let output = tool_command(&path).arg("--version").output().await;
+    let stdout = match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        Ok(output) => {
+            return check(
+                false,
+                format!(
+                    "`{} --version` failed at {} ({})",
+                    tool,
+                    path.display(),
+                    output.status
+                ),
+            )
+        }
+        Err(e) => return check(false, format!("couldn't run {}: {}", path.display(), e)),
+    };
+    let min: Version = min.parse().expect("BUG: minimum versions are valid");
+    match parse_tool_version(&stdout) {
+        Some(version) if version >= min => {
+            check(true, format!("{} at {}", version, path.display()))
+        }
+        Some(version) => check(
+            false,
+            format!(
+                "{} at {}, but {} or newer is needed",
+                version,
+                path.display(),
+                min
+            ),
+        ),
+        None => check(
+            false,
+            format!(
+                "couldn't tell the version of {} from {:?}",
+                path.display(),
+                stdout.trim()
+            ),
+        ),
+    }
+}
+
+/// Picks the version number out of a tool's `--version` output, like
+/// `8.1.0`, `git version 2.30.1 (Apple Git-130)`, or
+/// `git version 2.33.0.windows.2`.
+fn parse_tool_version(output: &str) -> Option<Version> {
+    let word = output
+        .split_whitespace()
+        .find(|word| {
+            word.trim_start_matches('v')
+                .starts_with(|c: char| c.is_ascii_digit())
+        })?
+        .trim_start_matches('v');
+    let mut parts = word
+        .split('.')
+        .map_while(|part| part.parse::<u64>().ok())
+        .take(3)
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        return None;
+    }
+    parts.resize(3, "0".into());
+    parts.join(".").parse().ok()
+}
+
+/// Checks that collider can create and write files in `dir`.
+async fn check_writable(name: &str, dir: &Path) -> Check {
+    let dir = dir.to_owned();
+    let result = smol::unblock({
+        let dir = dir.clone();
+        move || {
+            std::fs::create_dir_all(&dir)?;
+            let probe = dir.join(".collider-doctor");
+            std::fs::write(&probe, "")?;
+            std::fs::remove_file(&probe)
+        }
+    })
+    .await;
+    Check {
+        name: name.into(),
+        required: true,
+        ok: result.is_ok(),
+        detail: match result {
+            Ok(()) => format!("{} is writable", dir.display()),
+            Err(e) => format!("can't write to {}: {}", dir.display(), e),
+        },
+    }
+}
+
+/// Checks that Electron's release feed can be reached.
+async fn check_network() -> Check {
+    // Everything, `send` included, runs inside the Tokio context, since
+    // that's where reqwest's timeout timer is set up.
+    let result = async {
+        Ok::<_, ElectronError>(
+            build_http_client(None, Some(NETWORK_TIMEOUT))?
+                .head(RELEASES_URL)
+                .send()
+                .await?
+                .error_for_status()?,
+        )
+    }
+    .compat()
+    .await;
+    Check {
+        name: "network".into(),
+        required: true,
+        ok: result.is_ok(),
+        detail: match result {
+            Ok(_) => format!("{} is reachable", RELEASES_URL),
+            Err(e) => format!("can't reach {}: {}", RELEASES_URL, e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_versions() {
+        let parse = |output| parse_tool_version(output).map(|v| v.to_string());
+        assert_eq!(parse("8.1.0\n"), Some("8.1.0".into()));
+        assert_eq!(parse("v16.13.0\n"), Some("16.13.0".into()));
+        assert_eq!(
+            parse("git version 2.30.1 (Apple Git-130)\n"),
+            Some("2.30.1".into())
+        );
+        assert_eq!(
+            parse("git version 2.33.0.windows.2\n"),
+            Some("2.33.0".into())
+        );
+        assert_eq!(parse("git version 2\n"), Some("2.0.0".into()));
+        assert_eq!(parse("no version here"), None);
+    }
+
+    #[test]
+    fn checks_the_project_package_manager() {
+        let tools = |pm| {
+            doctor_tools(pm)
+                .into_iter()
+                .map(|(tool, _, _)| tool)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tools(PackageManager::Npm), vec!["npm", "npx", "git"]);
+        assert_eq!(tools(PackageManager::Yarn), vec!["yarn", "npx", "git"]);
+        assert_eq!(tools(PackageManager::Pnpm), vec!["pnpm", "git"]);
+    }
+
+    #[test]
+    fn writable_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = smol::block_on(check_writable("test", &dir.path().join("new")));
+        assert!(check.ok, "{}", check.detail);
+        assert!(dir.path().join("new").is_dir());
+        assert!(!dir.path().join("new").join(".collider-doctor").exists());
+    }
+}
@@ -0,0 +1,14 @@
+use collider_common::{
+    miette::{self, Diagnostic},
+    thiserror::Error,
+};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum InfoError {
+    #[error("Some required checks failed: {}.", .0.join(", "))]
+    #[diagnostic(
+        code(collider::info::doctor_failed),
+        help("Fix the problems marked above, then run `collider doctor` again.")
+    )]
+    DoctorFailed(Vec<String>),
+}
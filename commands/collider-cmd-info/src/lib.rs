@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use collider_command::{
+    async_trait::async_trait,
+    clap::{self, Clap},
+    collider_config::{self, ColliderConfigLayer},
+    ColliderCommand,
+};
+use collider_common::{
+    miette::{IntoDiagnostic, Result},
+    serde::Serialize,
+    serde_json,
+};
+use collider_electron::{current_collider_version, host_arch, host_platform, CacheDirs, Electron};
+
+pub use doctor::{Check, DoctorCmd};
+pub use errors::InfoError;
+
+mod doctor;
+mod errors;
+
+/// External tools whose whereabouts are worth knowing when something goes
+/// wrong.
+pub const TOOLS: &[&str] = &["git", "node", "npm", "npx"];
+
+#[derive(Debug, Clap, ColliderConfigLayer)]
+pub struct InfoCmd {
+    #[clap(from_global)]
+    quiet: bool,
+
+    #[clap(from_global)]
+    json: bool,
+
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+}
+
+/// Everything `collider info` reports.
+#[derive(Debug, Serialize)]
+pub struct Info {
+    /// collider's own version, if it was installed from npm.
+    pub collider_version: Option<String>,
+    /// Electron's name for this platform, or `None` if Electron doesn't
+    /// ship for it.
+    pub os: Option<&'static str>,
+    /// Electron's name for this architecture, or `None` if Electron doesn't
+    /// ship for it.
+    pub arch: Option<&'static str>,
+    /// Where downloaded Electron builds are unpacked.
+    pub data_local_dir: PathBuf,
+    /// Where Electron zips are downloaded to.
+    pub cache_dir: PathBuf,
+    /// Every cached Electron build, as `v<version>-<os>-<arch>`, newest first.
+    pub cached: Vec<String>,
+    /// Each of [`TOOLS`], and where it was found on PATH.
+    pub tools: BTreeMap<&'static str, Option<PathBuf>>,
+}
+
+impl Info {
+    /// Looks everything up, with Electron's cache in `cache_dir` if given
+    /// (see [`CacheDirs::resolve`]).
+    pub async fn gather(cache_dir: Option<&Path>) -> Result<Info> {
+        let dirs = CacheDirs::resolve(cache_dir)?;
+        Ok(Info {
+            collider_version: current_collider_version()
+                .await?
+                .map(|version| version.to_string()),
+            os: host_platform().ok(),
+            arch: host_arch().ok(),
+            data_local_dir: dirs.builds,
+            cache_dir: dirs.downloads,
+            cached: Electron::list_cached(cache_dir)?
+                .iter()
+                .map(Electron::triple)
+                .collect(),
+            tools: TOOLS
+                .iter()
+                .map(|tool| (*tool, which::which(tool).ok()))
+                .collect(),
+        })
+    }
+
+    /// A plain-text report, one `name: value` line each, for pasting into
+    /// bug reports.
+    pub fn report(&self) -> String {
+        let or_unknown = |value: Option<&str>| value.unwrap_or("unknown").to_owned();
+        let mut lines = vec![
+            ("collider", or_unknown(self.collider_version.as_deref())),
+            ("os", or_unknown(self.os)),
+            ("arch", or_unknown(self.arch)),
+            ("data dir", self.data_local_dir.display().to_string()),
+            ("cache dir", self.cache_dir.display().to_string()),
+            (
+                "cached",
+                if self.cached.is_empty() {
+                    "none".into()
+                } else {
+                    self.cached.join(", ")
+                },
+            ),
+        ];
+        for (tool, path) in &self.tools {
+            lines.push((
+                tool,
+                path.as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|| "not found".into()),
+            ));
+        }
+        let width = lines.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        lines
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "{:width$}  {}",
+                    format!("{}:", name),
+                    value,
+                    width = width + 1
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[async_trait]
+impl ColliderCommand for InfoCmd {
+    async fn execute(self) -> Result<()> {
+        let info = Info::gather(self.cache_dir.as_deref()).await?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&info).into_diagnostic()?);
+        } else if !self.quiet {
+            println!("{}", info.report());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lines_up() {
+        let cache = tempfile::tempdir().unwrap();
+        let build = CacheDirs::resolve(Some(cache.path()))
+            .unwrap()
+            .builds
+            .join("v13.1.7-linux-x64");
+        std::fs::create_dir_all(&build).unwrap();
+        std::fs::write(build.join("electron"), "").unwrap();
+        let info = Info {
+            collider_version: None,
+            os: Some("linux"),
+            arch: Some("x64"),
+            data_local_dir: "/data".into(),
+            cache_dir: "/cache".into(),
+            cached: Electron::list_cached(Some(cache.path()))
+                .unwrap()
+                .iter()
+                .map(Electron::triple)
+                .collect(),
+            tools: vec![("git", Some("/usr/bin/git".into())), ("npm", None)]
+                .into_iter()
+                .collect(),
+        };
+        assert_eq!(
+            info.report(),
+            [
+                "collider:   unknown",
+                "os:         linux",
+                "arch:       x64",
+                "data dir:   /data",
+                "cache dir:  /cache",
+                "cached:     v13.1.7-linux-x64",
+                "git:        /usr/bin/git",
+                "npm:        not found",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use collider_common::{
+    miette::{self, Diagnostic},
+    thiserror::{self, Error},
+};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum NewError {
+    #[error(transparent)]
+    #[diagnostic(code(collider::new::io_error))]
+    IoError(#[from] std::io::Error),
+
+    #[error("Unknown template: {template}")]
+    #[diagnostic(
+        code(collider::new::unknown_template),
+        help("Available templates are: {}.", .available.join(", "))
+    )]
+    UnknownTemplate {
+        template: String,
+        available: Vec<String>,
+    },
+
+    #[error("No name given for the new app, and there's no terminal to ask for one on.")]
+    #[diagnostic(
+        code(collider::new::missing_name),
+        help("Pass `--name <name>` or the directory to create it in, e.g. `collider new my-app`.")
+    )]
+    MissingName,
+
+    #[error("{} already exists and isn't empty.", .0.display())]
+    #[diagnostic(
+        code(collider::new::dir_not_empty),
+        help("Pick a new directory for the app, or empty this one out first.")
+    )]
+    DirNotEmpty(PathBuf),
+
+    #[error("`{name}` can't be used as the app's name: {reason}.")]
+    #[diagnostic(
+        code(collider::new::invalid_name),
+        help("The name goes in package.json, so it has to be one npm accepts: lowercase and URL-safe, like `my-app` or `@scope/my-app`.")
+    )]
+    InvalidName { name: String, reason: &'static str },
+
+    #[error("Failed to parse {}", .0.display())]
+    #[diagnostic(code(collider::new::bad_package_json))]
+    BadPackageJson(PathBuf, #[source] serde_json::Error),
+
+    #[error("Both a template and a template repository were given.")]
+    #[diagnostic(
+        code(collider::new::template_conflict),
+        help("Pick one. If one of them comes from a config file, remove it there.")
+    )]
+    TemplateConflict,
+}
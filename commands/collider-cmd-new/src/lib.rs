@@ -1,24 +1,100 @@
-use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 
 use collider_command::{
     async_trait::async_trait,
     clap::{self, Clap},
     collider_config::{self, ColliderConfigLayer},
-    tracing, ColliderCommand,
+    format_command_line, run_tool, tracing, which_tool, ChildOutput, ColliderCommand,
+    PackageManager, PACKAGE_MANAGERS,
 };
-use collider_common::miette::{IntoDiagnostic, Result};
+use collider_common::{
+    miette::{IntoDiagnostic, Result},
+    smol,
+};
+use dialoguer::{console, theme::ColorfulTheme, Input, Select};
+use include_dir::{include_dir, Dir};
+
+pub use errors::NewError;
+
+mod errors;
+
+/// Every template `new` knows, one directory each, baked into the binary so
+/// it works wherever collider is installed. Text in them can use `__NAME__`
+/// for the new app's name.
+static TEMPLATES: Dir = include_dir!("templates");
+
+/// The template used when none is picked.
+const DEFAULT_TEMPLATE: &str = "quick-start";
+
+/// Names of every template `--template` accepts, sorted.
+pub fn available_templates() -> Vec<&'static str> {
+    let mut names = TEMPLATES
+        .dirs()
+        .iter()
+        .filter_map(|dir| dir.path().to_str())
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+}
 
 #[derive(Debug, Clap, ColliderConfigLayer)]
 pub struct NewCmd {
-    #[clap(about = "Path to create new Electron application in.")]
-    path: PathBuf,
+    #[clap(
+        about = "Path to create new Electron application in. Defaults to --name, which is asked for if left out and there's a terminal to ask on."
+    )]
+    path: Option<PathBuf>,
+    #[clap(
+        long,
+        about = "Name of the new application, used in its package.json, so it has to be one npm accepts. Defaults to the name of its directory, lowercased and with anything npm doesn't allow turned into `-`."
+    )]
+    name: Option<String>,
+    #[clap(
+        long,
+        short = 'y',
+        about = "Don't ask anything: use --name or the path as given, and defaults for everything else."
+    )]
+    yes: bool,
     #[clap(
         long,
         short = 't',
-        default_value = "vanilla",
-        about = "Template to use when scaffolding a new application."
+        about = "Template to use when scaffolding a new application: `quick-start`, `typescript`, or `react`. Picked from a list if left out and there's a terminal to ask on, and `quick-start` otherwise."
+    )]
+    template: Option<String>,
+    #[clap(
+        long,
+        value_name = "GIT-URL",
+        conflicts_with = "template",
+        about = "Git repository to use as the template instead of a built-in one, e.g. your team's boilerplate. Its history isn't kept."
+    )]
+    template_repo: Option<String>,
+    #[clap(
+        long,
+        value_name = "REF",
+        requires = "template-repo",
+        about = "Branch or tag of --template-repo to use. Defaults to the repository's default branch."
+    )]
+    template_ref: Option<String>,
+    #[clap(long, about = "List the available templates, and exit.")]
+    list_templates: bool,
+    #[clap(
+        long,
+        about = "Package manager to install the new app's dependencies with. Defaults to the first of npm, yarn, or pnpm that's installed.",
+        possible_values = PACKAGE_MANAGERS
+    )]
+    package_manager: Option<String>,
+    #[clap(
+        long,
+        alias = "skip-git",
+        about = "Don't run `git init` in the new app. Handy when it's going into an existing repository."
+    )]
+    no_git: bool,
+    #[clap(
+        long,
+        alias = "skip-install",
+        about = "Don't install the new app's dependencies. You'll need to install them yourself before starting it."
     )]
-    template: String,
+    no_install: bool,
     #[clap(from_global)]
     verbosity: tracing::Level,
     #[clap(from_global)]
@@ -30,25 +106,569 @@ pub struct NewCmd {
 #[async_trait]
 impl ColliderCommand for NewCmd {
     async fn execute(self) -> Result<()> {
+        if self.list_templates {
+            if self.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&available_templates()).into_diagnostic()?
+                );
+            } else {
+                for name in available_templates() {
+                    println!("{}", name);
+                }
+            }
+            return Ok(());
+        }
         let current_dir = std::env::current_dir().into_diagnostic()?;
-        match self.template.as_ref() {
-            "react" => println!(
-                "Making a new React-based Electron app at {}",
-                current_dir.join(self.path).display(),
-            ),
-            "vue" => println!(
-                "Making a new Vue-based Electron app at {}",
-                current_dir.join(self.path).display(),
-            ),
-            "vanilla" => println!(
-                "Making a new VanillaJS-based Electron app at {}",
-                current_dir.join(self.path).display(),
-            ),
-            template => panic!(
-                "Unknown workload: {}, possible workloads are: react, vue, vanilla",
-                template
+        self.create_in(&current_dir).await
+    }
+}
+
+impl NewCmd {
+    /// Makes the new app, with its path taken relative to `current_dir`.
+    async fn create_in(self, current_dir: &Path) -> Result<()> {
+        // clap catches this on the command line, but config can set either.
+        if self.template.is_some() && self.template_repo.is_some() {
+            return Err(NewError::TemplateConflict.into());
+        }
+        // Catch a bad --template before asking anything else.
+        if let Some(template) = &self.template {
+            find_template(template)?;
+        }
+        let interactive = self.can_prompt();
+        let name = match (&self.name, &self.path) {
+            (Some(name), _) => Some(name.clone()),
+            (None, Some(_)) => None,
+            (None, None) if interactive => Some(
+                Input::<String>::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Project name")
+                    .interact_text()
+                    .into_diagnostic()?,
             ),
+            (None, None) => return Err(NewError::MissingName.into()),
+        };
+        let path = match (&self.path, &name) {
+            (Some(path), _) => path.clone(),
+            (None, Some(name)) => name.into(),
+            (None, None) => unreachable!("BUG: a name is required without a path"),
+        };
+        let template_name = match (&self.template, &self.template_repo) {
+            (_, Some(repo)) => repo.clone(),
+            (Some(template), None) => template.clone(),
+            (None, None) if interactive => pick_template()?,
+            (None, None) => DEFAULT_TEMPLATE.into(),
+        };
+        let dest = current_dir.join(&path);
+        if !self.quiet && !self.json {
+            println!(
+                "Making a new {} Electron app at {}",
+                template_name,
+                dest.display(),
+            );
+        }
+        // A name that was asked for has to work as is, but one that comes
+        // from the directory is just made to fit.
+        let name = match name {
+            Some(name) => {
+                if let Some(reason) = invalid_package_name(&name) {
+                    return Err(NewError::InvalidName { name, reason }.into());
+                }
+                name
+            }
+            None => package_name_from_dir(&dest),
+        };
+        if let Some(repo) = &self.template_repo {
+            self.clone_template(repo, current_dir, &dest).await?;
+            set_package_name(&dest.join("package.json"), &name)?;
+        } else {
+            let template = find_template(&template_name)?;
+            let dest_clone = dest.clone();
+            let name = name.clone();
+            smol::unblock(move || create_new_dir(&template, &dest_clone, &name)).await?;
+        }
+        // A cloned template might come with a lockfile, so look after it's
+        // written out.
+        let pm = match &self.package_manager {
+            Some(pm) => pm.parse()?,
+            None => PackageManager::detect(&dest)?
+                .or_else(PackageManager::on_path)
+                .unwrap_or(PackageManager::Npm),
+        };
+        tracing::debug!("Using {} as the package manager", pm);
+        let say = |msg: &str| {
+            if !self.quiet && !self.json {
+                println!("{}", msg);
+            }
+        };
+        if self.no_git {
+            say("Skipping `git init`.");
+        } else {
+            self.run_in(&dest, "git", &["init"], &[]).await?;
+        }
+        if self.no_install {
+            say("Skipping dependency install.");
+        } else {
+            // collider provides Electron itself, so templates that depend on
+            // the package (for its typings) don't need its own download.
+            let skip_download = [("ELECTRON_SKIP_BINARY_DOWNLOAD", OsStr::new("1"))];
+            self.run_in(&dest, pm.as_str(), &["install"], &skip_download)
+                .await?;
+        }
+        if self.json {
+            let summary = serde_json::json!({
+                "path": dest,
+                "name": name,
+                "template": template_name,
+                "package_manager": pm.as_str(),
+                "git": !self.no_git,
+                "installed": !self.no_install,
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&summary).into_diagnostic()?
+            );
+        } else if !self.quiet {
+            let mut steps = vec![format!("cd {}", path.display())];
+            if self.no_install {
+                steps.push(format!("{} install", pm));
+            }
+            steps.push(format!("{} run start", pm));
+            println!("Done! To start your app, run: {}", steps.join(" && "));
+        }
+        Ok(())
+    }
+
+    /// Whether there's someone around to answer prompts. Without a terminal
+    /// on both ends, prompting would just hang (e.g. in CI).
+    fn can_prompt(&self) -> bool {
+        !self.yes
+            && !self.quiet
+            && !self.json
+            && atty::is(atty::Stream::Stdin)
+            && console::user_attended()
+    }
+
+    /// Clones `repo` (at `--template-ref`, if given) into `dest` as the new
+    /// app, without its history. If the clone fails, whatever it left behind
+    /// is removed again.
+    async fn clone_template(&self, repo: &str, cwd: &Path, dest: &Path) -> Result<()> {
+        let existed = dest.exists();
+        if existed && std::fs::read_dir(dest).into_diagnostic()?.next().is_some() {
+            return Err(NewError::DirNotEmpty(dest.to_owned()).into());
         }
+        let mut args = vec![OsStr::new("clone"), OsStr::new("--depth"), OsStr::new("1")];
+        if let Some(git_ref) = &self.template_ref {
+            args.push(OsStr::new("--branch"));
+            args.push(OsStr::new(git_ref));
+        }
+        args.extend([OsStr::new("--"), OsStr::new(repo), dest.as_os_str()]);
+        if let Err(err) = self.run_in(cwd, "git", &args, &[]).await {
+            tracing::debug!("Cleaning up {} after failed clone", dest.display());
+            if existed {
+                clear_dir(dest)?;
+            } else if dest.exists() {
+                std::fs::remove_dir_all(dest).into_diagnostic()?;
+            }
+            return Err(err);
+        }
+        std::fs::remove_dir_all(dest.join(".git")).into_diagnostic()?;
         Ok(())
     }
+
+    /// Runs an external tool in the new app's directory, with `env` added to
+    /// its environment.
+    async fn run_in<A: AsRef<OsStr>>(
+        &self,
+        dir: &Path,
+        tool: &str,
+        args: &[A],
+        env: &[(&str, &OsStr)],
+    ) -> Result<()> {
+        let command_line = format_command_line(Some(dir), env, OsStr::new(tool), args);
+        tracing::info!("Running {}", command_line);
+        let mut cmd = which_tool(tool)?;
+        cmd.args(args).envs(env.iter().copied()).current_dir(dir);
+        let output = ChildOutput::from_flags(false, self.quiet || self.json);
+        run_tool(cmd, &command_line, output).await?;
+        Ok(())
+    }
+}
+
+fn pick_template() -> Result<String> {
+    let templates = available_templates();
+    let default = templates
+        .iter()
+        .position(|name| *name == DEFAULT_TEMPLATE)
+        .unwrap_or(0);
+    let picked = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Template")
+        .items(&templates)
+        .default(default)
+        .interact()
+        .into_diagnostic()?;
+    Ok(templates[picked].into())
+}
+
+fn find_template(name: &str) -> Result<Dir<'static>, NewError> {
+    TEMPLATES
+        .dirs()
+        .iter()
+        .find(|dir| dir.path() == Path::new(name))
+        .copied()
+        .ok_or_else(|| NewError::UnknownTemplate {
+            template: name.into(),
+            available: available_templates()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        })
+}
+
+/// Writes `template` out into `dest`, which has to be empty (or not exist
+/// yet), filling in `name` wherever the template says `__NAME__`.
+fn create_new_dir(template: &Dir, dest: &Path, name: &str) -> Result<(), NewError> {
+    if dest.exists() && std::fs::read_dir(dest)?.next().is_some() {
+        return Err(NewError::DirNotEmpty(dest.to_owned()));
+    }
+    write_dir(template, template.path(), dest, name)
+}
+
+/// Sets the `name` in the package.json at `path`, if there is one. Keys stay
+/// in the order they were in.
+fn set_package_name(path: &Path, name: &str) -> Result<(), NewError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut pkg: serde_json::Value =
+        serde_json::from_str(&text).map_err(|e| NewError::BadPackageJson(path.to_owned(), e))?;
+    match pkg.as_object_mut() {
+        Some(pkg) => {
+            pkg.insert("name".into(), name.into());
+        }
+        None => {
+            tracing::warn!(
+                "{} isn't an object, so its name can't be set.",
+                path.display()
+            );
+            return Ok(());
+        }
+    }
+    let mut text = serde_json::to_string_pretty(&pkg).expect("BUG: JSON values always serialize");
+    text.push('\n');
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+/// Why npm wouldn't accept `name` for a new package, if it wouldn't.
+fn invalid_package_name(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        return Some("it's empty");
+    }
+    if name.len() > 214 {
+        return Some("it's longer than 214 characters");
+    }
+    if matches!(name, "node_modules" | "favicon.ico") {
+        return Some("npm reserves it");
+    }
+    let bare = match name.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, bare)) if !scope.is_empty() && package_name_chars(scope) => bare,
+            _ => return Some("its scope has to look like `@scope/name`"),
+        },
+        None => name,
+    };
+    if bare.is_empty() || bare.starts_with('.') || bare.starts_with('_') {
+        return Some("it has to start with a letter, digit or `-`");
+    }
+    if !package_name_chars(bare) {
+        return Some("it can only have lowercase letters, digits, `-`, `.` and `_`");
+    }
+    None
+}
+
+fn package_name_chars(name: &str) -> bool {
+    name.chars().all(is_package_name_char)
+}
+
+fn is_package_name_char(c: char) -> bool {
+    matches!(c, 'a'..='z' | '0'..='9' | '-' | '.' | '_')
+}
+
+/// A package name npm will take, made from the name of the directory `dest`:
+/// lowercased, with anything else npm doesn't allow turned into `-`.
+fn package_name_from_dir(dest: &Path) -> String {
+    let dir_name = dest
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name = dir_name
+        .chars()
+        .map(|c| if is_package_name_char(c) { c } else { '-' })
+        .collect::<String>();
+    let name = name
+        .trim_start_matches(&['.', '_'][..])
+        .trim_end_matches('-');
+    match name.get(..214).unwrap_or(name) {
+        "" | "node_modules" | "favicon.ico" => "electron-app".into(),
+        name => name.into(),
+    }
+}
+
+/// Empties out `dir`, but leaves it in place.
+fn clear_dir(dir: &Path) -> Result<(), NewError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        } else {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_dir(dir: &Dir, root: &Path, dest: &Path, name: &str) -> Result<(), NewError> {
+    // Embedded paths are relative to `templates/`, not to the template.
+    let relative = |path: &Path| {
+        dest.join(
+            path.strip_prefix(root)
+                .expect("BUG: template entries live under the template"),
+        )
+    };
+    std::fs::create_dir_all(relative(dir.path()))?;
+    for file in dir.files() {
+        let path = relative(file.path());
+        tracing::debug!("Writing {}", path.display());
+        match file.contents_utf8() {
+            Some(text) => std::fs::write(path, text.replace("__NAME__", name))?,
+            None => std::fs::write(path, file.contents())?,
+        }
+    }
+    for subdir in dir.dirs() {
+        write_dir(subdir, root, dest, name)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `NewCmd` that doesn't ask anything, or run git or an install.
+    fn new_cmd() -> NewCmd {
+        NewCmd {
+            path: None,
+            name: None,
+            yes: true,
+            template: None,
+            template_repo: None,
+            template_ref: None,
+            list_templates: false,
+            package_manager: None,
+            no_git: true,
+            no_install: true,
+            verbosity: tracing::Level::WARN,
+            quiet: true,
+            json: false,
+        }
+    }
+
+    #[test]
+    fn writes_out_the_whole_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("my-app");
+        let cmd = NewCmd {
+            path: Some(dest.clone()),
+            ..new_cmd()
+        };
+        smol::block_on(cmd.execute()).unwrap();
+
+        let pkg = std::fs::read_to_string(dest.join("package.json")).unwrap();
+        assert!(pkg.contains(r#""name": "my-app""#));
+        assert!(dest.join("index.js").is_file());
+        assert!(dest.join("src").join("index.html").is_file());
+
+        assert!(matches!(
+            create_new_dir(&find_template("quick-start").unwrap(), &dest, "my-app"),
+            Err(NewError::DirNotEmpty(_))
+        ));
+        assert!(matches!(
+            find_template("nope"),
+            Err(NewError::UnknownTemplate { .. })
+        ));
+    }
+
+    #[test]
+    fn every_template_has_an_app() {
+        assert_eq!(
+            available_templates(),
+            vec!["quick-start", "react", "typescript"]
+        );
+        for name in available_templates() {
+            let dir = tempfile::tempdir().unwrap();
+            create_new_dir(&find_template(name).unwrap(), dir.path(), "my-app").unwrap();
+            let pkg = std::fs::read_to_string(dir.path().join("package.json")).unwrap();
+            let pkg: collider_common::serde_json::Value =
+                collider_common::serde_json::from_str(&pkg).unwrap();
+            assert_eq!(pkg["name"], "my-app", "{}", name);
+            assert!(pkg["main"].is_string(), "{}", name);
+            // Scripts get run by whichever package manager the app uses.
+            for script in pkg["scripts"].as_object().unwrap().values() {
+                let script = script.as_str().unwrap();
+                assert!(!script.contains("npm run"), "{}: {}", name, script);
+            }
+        }
+    }
+
+    #[test]
+    fn typescript_typings_match_the_electron_it_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        create_new_dir(&find_template("typescript").unwrap(), dir.path(), "my-app").unwrap();
+        let pkg: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join("package.json")).unwrap(),
+        )
+        .unwrap();
+        let tool_versions = std::fs::read_to_string(dir.path().join(".tool-versions")).unwrap();
+        assert_eq!(
+            pkg["devDependencies"]["electron"].as_str(),
+            tool_versions.trim().strip_prefix("electron ")
+        );
+    }
+
+    #[test]
+    fn name_stands_in_for_the_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let cmd = NewCmd {
+            name: Some("named-app".into()),
+            ..new_cmd()
+        };
+        smol::block_on(cmd.create_in(dir.path())).unwrap();
+        let pkg =
+            std::fs::read_to_string(dir.path().join("named-app").join("package.json")).unwrap();
+        assert!(pkg.contains(r#""name": "named-app""#));
+
+        let err = smol::block_on(new_cmd().create_in(dir.path())).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<NewError>(),
+            Some(NewError::MissingName)
+        ));
+    }
+
+    #[test]
+    fn only_the_top_level_name_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("package.json");
+        std::fs::write(
+            &path,
+            r#"{"version": "1.0.0", "author": {"name": "Kat"}, "name": "boilerplate", "main": "index.js"}"#,
+        )
+        .unwrap();
+        set_package_name(&path, "my-app").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\n  \"version\": \"1.0.0\",\n  \"author\": {\n    \"name\": \"Kat\"\n  },\n  \"name\": \"my-app\",\n  \"main\": \"index.js\"\n}\n"
+        );
+
+        std::fs::write(&path, "{ nope").unwrap();
+        assert!(matches!(
+            set_package_name(&path, "my-app"),
+            Err(NewError::BadPackageJson(..))
+        ));
+    }
+
+    #[test]
+    fn names_follow_npm_rules() {
+        for name in &["my-app", "electron.app", "@me/my-app", "a1_b2"] {
+            assert_eq!(invalid_package_name(name), None, "{}", name);
+        }
+        for name in &[
+            "",
+            "Named App",
+            "MyApp",
+            ".hidden",
+            "_private",
+            "@/app",
+            "@me/",
+            "node_modules",
+            "app!",
+        ] {
+            assert!(invalid_package_name(name).is_some(), "{}", name);
+        }
+        assert_eq!(
+            package_name_from_dir(Path::new("/apps/My Cool App!")),
+            "my-cool-app"
+        );
+        assert_eq!(package_name_from_dir(Path::new("/apps/.dotted")), "dotted");
+        assert_eq!(package_name_from_dir(Path::new("/")), "electron-app");
+
+        let dir = tempfile::tempdir().unwrap();
+        let err = smol::block_on(
+            NewCmd {
+                name: Some("Named App".into()),
+                ..new_cmd()
+            }
+            .create_in(dir.path()),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<NewError>(),
+            Some(NewError::InvalidName { .. })
+        ));
+        assert!(!dir.path().join("Named App").exists());
+    }
+
+    #[test]
+    fn clones_template_repos() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("boilerplate");
+        std::fs::create_dir(&repo).unwrap();
+        std::fs::write(
+            repo.join("package.json"),
+            r#"{"name": "boilerplate", "version": "1.0.0"}"#,
+        )
+        .unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(&repo)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "init"]);
+        let new = |template_repo: &Path, dest: &Path| NewCmd {
+            path: Some(dest.to_owned()),
+            template_repo: Some(template_repo.display().to_string()),
+            ..new_cmd()
+        };
+
+        let dest = dir.path().join("my-app");
+        smol::block_on(new(&repo, &dest).execute()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dest.join("package.json")).unwrap(),
+            "{\n  \"name\": \"my-app\",\n  \"version\": \"1.0.0\"\n}\n"
+        );
+        assert!(!dest.join(".git").exists());
+
+        let dest = dir.path().join("named-app");
+        let cmd = NewCmd {
+            name: Some("@me/named-app".into()),
+            ..new(&repo, &dest)
+        };
+        smol::block_on(cmd.execute()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dest.join("package.json")).unwrap(),
+            "{\n  \"name\": \"@me/named-app\",\n  \"version\": \"1.0.0\"\n}\n"
+        );
+
+        let dest = dir.path().join("broken-app");
+        assert!(smol::block_on(new(&dir.path().join("nope"), &dest).execute()).is_err());
+        assert!(!dest.exists());
+    }
 }
@@ -0,0 +1,3 @@
+window.addEventListener('DOMContentLoaded', () => {
+  document.getElementById('electron-version').innerText = process.versions.electron
+})
@@ -0,0 +1,8 @@
+import React from 'react'
+import ReactDOM from 'react-dom'
+
+function App () {
+  return <h1>Hello from __NAME__!</h1>
+}
+
+ReactDOM.render(<App />, document.getElementById('root'))
@@ -0,0 +1,4 @@
+window.addEventListener('DOMContentLoaded', () => {
+  const version = document.getElementById('electron-version')
+  if (version) version.innerText = process.versions.electron
+})
@@ -0,0 +1,179 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use collider_common::tracing;
+use flate2::{write::GzEncoder, Compression};
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::errors::PackError;
+
+/// Every format `--target` accepts.
+pub const ARCHIVE_FORMATS: &[&str] = &["zip", "tar.gz"];
+
+/// A distributable archive of a packed app, written next to its build
+/// directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// Works everywhere, but can't hold symlinks, so they're stored as
+    /// copies of what they point to.
+    Zip,
+    /// Keeps symlinks and permissions intact, which macOS bundles need.
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::TarGz => "tar.gz",
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = PackError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zip" => Ok(ArchiveFormat::Zip),
+            "tar.gz" | "tgz" => Ok(ArchiveFormat::TarGz),
+            _ => Err(PackError::UnknownArchiveFormat {
+                format: s.into(),
+                available: ARCHIVE_FORMATS.iter().map(|f| f.to_string()).collect(),
+            }),
+        }
+    }
+}
+
+/// Archives everything inside `dir` (but not `dir` itself) into `dest`.
+pub(crate) fn write_archive(
+    format: ArchiveFormat,
+    dir: &Path,
+    dest: &Path,
+) -> Result<(), PackError> {
+    let out = BufWriter::new(File::create(dest)?);
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = ZipWriter::new(out);
+            let copied_links = add_to_zip(&mut zip, dir, "")?;
+            zip.finish()?.flush()?;
+            if copied_links > 0 {
+                tracing::warn!(
+                    "{} has {} symlink(s), stored as copies since zip can't hold links. Use `--target tar.gz` to keep them.",
+                    dir.display(),
+                    copied_links
+                );
+            }
+        }
+        ArchiveFormat::TarGz => {
+            let mut tar = tar::Builder::new(GzEncoder::new(out, Compression::default()));
+            tar.follow_symlinks(false);
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let name = entry.file_name();
+                if entry.file_type()?.is_dir() {
+                    tar.append_dir_all(&name, entry.path())?;
+                } else {
+                    tar.append_path_with_name(entry.path(), &name)?;
+                }
+            }
+            tar.into_inner()?.finish()?.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Adds everything in `dir` to `zip`, under `prefix`. Returns how many
+/// symlinks had to be copied instead.
+fn add_to_zip<W: Write + io::Seek>(
+    zip: &mut ZipWriter<W>,
+    dir: &Path,
+    prefix: &str,
+) -> Result<usize, PackError> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.sort();
+    let mut copied_links = 0;
+    for path in paths {
+        let name = format!(
+            "{}{}",
+            prefix,
+            path.file_name()
+                .expect("BUG: directory entries always have names")
+                .to_string_lossy()
+        );
+        if fs::symlink_metadata(&path)?.file_type().is_symlink() {
+            copied_links += 1;
+        }
+        let meta = fs::metadata(&path)?;
+        let options = FileOptions::default().unix_permissions(unix_mode(&meta));
+        if meta.is_dir() {
+            zip.add_directory(format!("{}/", name), options)?;
+            copied_links += add_to_zip(zip, &path, &format!("{}/", name))?;
+        } else {
+            zip.start_file(name, options.large_file(meta.len() >= u32::MAX as u64))?;
+            io::copy(&mut File::open(&path)?, zip)?;
+        }
+    }
+    Ok(copied_links)
+}
+
+#[cfg(unix)]
+fn unix_mode(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode()
+}
+
+#[cfg(not(unix))]
+fn unix_mode(meta: &fs::Metadata) -> u32 {
+    if meta.is_dir() {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_hold_the_release_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let release = dir.path().join("release");
+        fs::create_dir_all(release.join("resources")).unwrap();
+        fs::write(release.join("electron"), "exe").unwrap();
+        fs::write(release.join("resources").join("app.asar"), "asar").unwrap();
+
+        let zip_path = dir.path().join("app.zip");
+        write_archive(ArchiveFormat::Zip, &release, &zip_path).unwrap();
+        let mut zip = zip::ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        let mut names = zip.file_names().map(String::from).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["electron", "resources/", "resources/app.asar"]);
+        let mut asar = String::new();
+        io::Read::read_to_string(&mut zip.by_name("resources/app.asar").unwrap(), &mut asar)
+            .unwrap();
+        assert_eq!(asar, "asar");
+
+        let tar_path = dir.path().join("app.tar.gz");
+        write_archive(ArchiveFormat::TarGz, &release, &tar_path).unwrap();
+        let mut tar =
+            tar::Archive::new(flate2::read::GzDecoder::new(File::open(&tar_path).unwrap()));
+        let names = tar
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"electron".to_owned()));
+        assert!(names.contains(&"resources/app.asar".to_owned()));
+
+        assert!(matches!(
+            "dmg".parse::<ArchiveFormat>(),
+            Err(PackError::UnknownArchiveFormat { .. })
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use collider_common::serde_json;
+use plist::{Dictionary, Value};
+
+use crate::errors::PackError;
+
+/// What a packed macOS app calls itself, in place of the stock Electron
+/// `Info.plist` values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BundleInfo {
+    /// `CFBundleName` and `CFBundleDisplayName`.
+    pub name: String,
+    /// `CFBundleShortVersionString`.
+    pub version: String,
+    /// `CFBundleIdentifier`.
+    pub id: String,
+}
+
+impl BundleInfo {
+    /// Reads the app's name and version from its package.json. Without an
+    /// `app_id`, the bundle identifier is `com.collider.<name>`.
+    pub fn from_package_json(proj_dir: &Path, app_id: Option<&str>) -> Result<Self, PackError> {
+        let pkg_path = proj_dir.join("package.json");
+        let pkg: serde_json::Value = serde_json::from_str(&fs::read_to_string(&pkg_path)?)?;
+        match (pkg["name"].as_str(), pkg["version"].as_str()) {
+            (Some(name), Some(version)) => {
+                // `@scope/app` shows up in the Dock as just `app`.
+                let name = name.rsplit('/').next().unwrap_or(name).to_owned();
+                Ok(BundleInfo {
+                    id: app_id
+                        .map(String::from)
+                        .unwrap_or_else(|| default_app_id(&name)),
+                    name,
+                    version: version.into(),
+                })
+            }
+            _ => Err(PackError::BundleInfoUnknown(pkg_path)),
+        }
+    }
+}
+
+/// `com.collider.<name>`, with anything a bundle identifier can't hold
+/// (only letters, digits, `-` and `.` are allowed) replaced by `-`.
+fn default_app_id(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    format!("com.collider.{}", name)
+}
+
+/// Sets `info`'s keys in the `Info.plist` at `path`, keeping everything
+/// else, and writing it back in the format (binary or XML) it was in.
+pub(crate) fn write_info_plist(path: &Path, info: &BundleInfo) -> Result<(), PackError> {
+    let bytes = fs::read(path)?;
+    let binary = bytes.starts_with(b"bplist");
+    let mut plist = Value::from_reader(Cursor::new(bytes))?;
+    let dict: &mut Dictionary = plist
+        .as_dictionary_mut()
+        .ok_or_else(|| PackError::BadInfoPlist(path.to_owned()))?;
+    for (key, value) in &[
+        ("CFBundleName", &info.name),
+        ("CFBundleDisplayName", &info.name),
+        ("CFBundleShortVersionString", &info.version),
+        ("CFBundleIdentifier", &info.id),
+    ] {
+        dict.insert((*key).into(), Value::String((*value).clone()));
+    }
+    if binary {
+        plist.to_file_binary(path)?;
+    } else {
+        plist.to_file_xml(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_plist_keeps_its_format() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "@me/my app", "version": "1.2.3"}"#,
+        )
+        .unwrap();
+        let info = BundleInfo::from_package_json(dir.path(), None).unwrap();
+        assert_eq!(info.name, "my app");
+        assert_eq!(info.id, "com.collider.my-app");
+
+        let mut stock = Dictionary::new();
+        stock.insert("CFBundleName".into(), Value::String("Electron".into()));
+        stock.insert(
+            "CFBundleExecutable".into(),
+            Value::String("Electron".into()),
+        );
+        let stock = Value::Dictionary(stock);
+        for binary in &[false, true] {
+            let path = dir.path().join("Info.plist");
+            if *binary {
+                stock.to_file_binary(&path).unwrap();
+            } else {
+                stock.to_file_xml(&path).unwrap();
+            }
+            write_info_plist(&path, &info).unwrap();
+
+            assert_eq!(fs::read(&path).unwrap().starts_with(b"bplist"), *binary);
+            let plist = Value::from_file(&path).unwrap();
+            let dict = plist.as_dictionary().unwrap();
+            let get = |key| dict.get(key).and_then(Value::as_string);
+            assert_eq!(get("CFBundleName"), Some("my app"));
+            assert_eq!(get("CFBundleDisplayName"), Some("my app"));
+            assert_eq!(get("CFBundleShortVersionString"), Some("1.2.3"));
+            assert_eq!(get("CFBundleIdentifier"), Some("com.collider.my-app"));
+            assert_eq!(get("CFBundleExecutable"), Some("Electron"));
+        }
+    }
+}
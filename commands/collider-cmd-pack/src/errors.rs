@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use collider_common::{
+    miette::{self, Diagnostic},
+    thiserror::{self, Error},
+};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum PackError {
+    #[error(transparent)]
+    #[diagnostic(code(collider::pack::io_error))]
+    IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    #[diagnostic(code(collider::pack::json_error))]
+    JsonError(#[from] collider_common::serde_json::Error),
+
+    #[error("Couldn't tell where `{0} pack` put the tarball it made.")]
+    #[diagnostic(
+        code(collider::pack::packed_tarball_missing),
+        help("Run `{0} pack` in the app's directory yourself to see what it printed. Passing a different --package-manager may help.")
+    )]
+    PackedTarballMissing(String),
+
+    #[error(transparent)]
+    #[diagnostic(code(collider::pack::zip_error))]
+    ZipError(#[from] zip::result::ZipError),
+
+    #[error("Failed to read {}.", .path.display())]
+    #[diagnostic(
+        code(collider::pack::bad_ignore_file),
+        help("This file uses gitignore syntax. Check it for malformed patterns.")
+    )]
+    BadIgnoreFile {
+        path: PathBuf,
+        #[source]
+        source: ignore::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(code(collider::pack::plist_error))]
+    PlistError(#[from] plist::Error),
+
+    #[error("{} isn't a dictionary, so collider can't set the app's name in it.", .0.display())]
+    #[diagnostic(
+        code(collider::pack::bad_info_plist),
+        help("This Electron build's Info.plist looks broken. Try `collider pack --force` to download it again.")
+    )]
+    BadInfoPlist(PathBuf),
+
+    #[error("Can't name the macOS app: {} needs a `name` and a `version`.", .0.display())]
+    #[diagnostic(
+        code(collider::pack::bundle_info_unknown),
+        help("macOS apps get their CFBundleName and CFBundleShortVersionString from the app's package.json.")
+    )]
+    BundleInfoUnknown(PathBuf),
+
+    #[error("Unknown archive format: {format}")]
+    #[diagnostic(
+        code(collider::pack::unknown_archive_format),
+        help("Available formats are: {}.", .available.join(", "))
+    )]
+    UnknownArchiveFormat {
+        format: String,
+        available: Vec<String>,
+    },
+
+    #[error("Can't name the app's archives: {} needs a `name` and a `version`.", .0.display())]
+    #[diagnostic(
+        code(collider::pack::archive_name_unknown),
+        help("Archives are named `<name>-<version>-<os>-<arch>`, from the app's package.json.")
+    )]
+    ArchiveNameUnknown(PathBuf),
+
+    #[error("Packing failed for {} targets: {}.", .0.len(), .0.join(", "))]
+    #[diagnostic(
+        code(collider::pack::targets_failed),
+        help("Each target's error was logged above. Bundles for the other targets were still packed.")
+    )]
+    TargetsFailed(Vec<String>),
+
+    #[error("`npm pack` output is missing package.json (looked in {}).", .0.display())]
+    #[diagnostic(
+        code(collider::pack::packed_package_json_missing),
+        help("This usually means `npm pack` failed to produce a usable tarball. Try running `npm pack` yourself and inspecting what it includes.")
+    )]
+    PackedPackageJsonMissing(PathBuf),
+
+    #[error("`npm pack` output is missing the app's entrypoint, `{main}` (looked in {}).", .dir.display())]
+    #[diagnostic(
+        code(collider::pack::packed_main_missing),
+        help("npm left `main` out of the tarball. Check the `files` field in package.json and any .npmignore file: the entrypoint (and everything it loads) needs to be included. `npm pack --dry-run` shows what gets packed.")
+    )]
+    PackedMainMissing { main: String, dir: PathBuf },
+
+    #[error("Can't rebuild native modules for {platform} on this machine: {}.", .modules.join(", "))]
+    #[diagnostic(
+        code(collider::pack::foreign_native_modules),
+        help("Native modules have to be compiled on the platform they're for. Pack for {platform} on a {platform} machine (e.g. in CI), or pass a prebuilt --asar built there.")
+    )]
+    ForeignNativeModules {
+        platform: String,
+        modules: Vec<String>,
+    },
+
+    #[error("{} has symlinks that won't resolve once it's moved: {}.", .app.display(), .links.join(", "))]
+    #[diagnostic(
+        code(collider::pack::broken_bundle_symlinks),
+        help("macOS apps need every symlink inside the .app to be relative and to point somewhere inside the bundle, or dyld can't load the frameworks. Try `collider pack --force` to start from a fresh copy of Electron.")
+    )]
+    BrokenBundleSymlinks { app: PathBuf, links: Vec<String> },
+}
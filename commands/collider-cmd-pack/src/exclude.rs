@@ -0,0 +1,113 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::errors::PackError;
+
+/// Optional file in the app's root directory listing paths to leave out of
+/// app.asar, in gitignore syntax. It's applied on top of whatever npm's own
+/// `files` field and .npmignore let through.
+pub const IGNORE_FILE: &str = ".colliderignore";
+
+/// Deletes everything in the staged project at `proj` that matches the
+/// patterns in `ignore_file`. Returns how many files were removed.
+pub(crate) fn remove_ignored(proj: &Path, ignore_file: &Path) -> Result<usize, PackError> {
+    let bad_file = |source| PackError::BadIgnoreFile {
+        path: ignore_file.to_owned(),
+        source,
+    };
+    let mut builder = GitignoreBuilder::new(proj);
+    if let Some(err) = builder.add(ignore_file) {
+        return Err(bad_file(err));
+    }
+    let ignore = builder.build().map_err(bad_file)?;
+    Ok(remove_matches(&ignore, proj)?)
+}
+
+fn remove_matches(ignore: &Gitignore, dir: &Path) -> io::Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_dir = entry.file_type()?.is_dir();
+        let ignored = ignore.matched(&path, is_dir).is_ignore();
+        match (is_dir, ignored) {
+            (true, true) => {
+                removed += count_files(&path)?;
+                fs::remove_dir_all(&path)?;
+            }
+            (true, false) => removed += remove_matches(ignore, &path)?,
+            (false, true) => {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+            (false, false) => {}
+        }
+    }
+    Ok(removed)
+}
+
+fn count_files(dir: &Path) -> io::Result<usize> {
+    let mut count = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            count += count_files(&entry.path())?;
+        } else {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_ignored_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let proj = dir.path().join("package");
+        for file in &[
+            "package.json",
+            "index.js",
+            "index.js.map",
+            "keep.js.map",
+            "test/fixtures/big.bin",
+            "test/index.js",
+            "docs/guide.md",
+            "node_modules/dep/index.js",
+            "node_modules/dep/index.js.map",
+        ] {
+            let path = proj.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let ignore_file = dir.path().join(IGNORE_FILE);
+        fs::write(&ignore_file, "*.map\n!keep.js.map\n/test/\ndocs\n").unwrap();
+
+        assert_eq!(remove_ignored(&proj, &ignore_file).unwrap(), 5);
+        for kept in &[
+            "package.json",
+            "index.js",
+            "keep.js.map",
+            "node_modules/dep/index.js",
+        ] {
+            assert!(proj.join(kept).exists(), "{} should be kept", kept);
+        }
+        for removed in &[
+            "index.js.map",
+            "test",
+            "docs",
+            "node_modules/dep/index.js.map",
+        ] {
+            assert!(
+                !proj.join(removed).exists(),
+                "{} should be removed",
+                removed
+            );
+        }
+    }
+}
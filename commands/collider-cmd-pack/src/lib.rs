@@ -1,23 +1,61 @@
-use std::path::{Path, PathBuf};
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
+use std::process::Output;
+use std::sync::Arc;
 
+use collider_asar::unpacked_dir;
 use collider_command::{
     async_trait::async_trait,
     clap::{self, Clap},
     collider_config::{self, ColliderConfigLayer},
-    tracing, ColliderCommand,
+    format_command_line, package_main, resolve_main, run_tool, tracing, which_tool, Artifact,
+    ChildOutput, ColliderCommand, ColliderEvent, ConfirmRetry, PackageManager, Webhook,
+    PACKAGE_MANAGERS,
 };
 use collider_common::{
-    miette::{self, Context, IntoDiagnostic, Result},
-    smol::{self, fs, process::Command},
+    miette::{Context, IntoDiagnostic, Result},
+    serde::Serialize,
+    serde_json,
+    smol::{self, fs, stream::StreamExt},
+};
+use collider_electron::{
+    copy_tree, dir_size, expand_targets, host_arch, host_platform, unshare, Electron,
+    ElectronError, ElectronOpts, Platform, ARCHS,
 };
-use collider_electron::{Electron, ElectronOpts};
 use flate2::read::GzDecoder;
 use tar::Archive;
 
+pub use archive::{ArchiveFormat, ARCHIVE_FORMATS};
+pub use errors::PackError;
+pub use exclude::IGNORE_FILE;
+
+use bundle::BundleInfo;
+
+mod archive;
+mod bundle;
+mod errors;
+mod exclude;
+
+/// Version of `@electron/rebuild` that `pack` runs through npx unless told
+/// otherwise. Pinned to a known-good release so packing doesn't change
+/// underneath users whenever a new version gets published.
+pub const DEFAULT_REBUILD_VERSION: &str = "3.2.13";
+
+/// What `--asar-unpack` defaults to: native modules, which Electron would
+/// otherwise have to copy out to a temporary file whenever the app runs.
+pub const DEFAULT_ASAR_UNPACK: &str = "*.node";
+
+/// Version of the `--emit-artifacts-json` format. Bump it on any change that
+/// could break something reading the file.
+pub const ARTIFACTS_SCHEMA_VERSION: u32 = 1;
+
+/// An external command pack runs, as `(tool, args, cwd)`.
+type ToolStep = (&'static str, Vec<OsString>, Option<PathBuf>);
+
 #[derive(Debug, Clap, ColliderConfigLayer)]
 pub struct PackCmd {
     #[clap(
-        about = "Path to the root of an Electron app. Must be a directory containing a package.json and any files you want to bundle into the app.",
+        about = "Path to the root of an Electron app. Must be a directory containing a package.json and any files you want to bundle into the app. Paths matching a `.colliderignore` file there (gitignore syntax) are left out of app.asar.",
         default_value = "."
     )]
     path: PathBuf,
@@ -37,6 +75,52 @@ pub struct PackCmd {
     )]
     asar: Option<PathBuf>,
 
+    #[clap(
+        long,
+        number_of_values = 1,
+        multiple_occurrences = true,
+        value_name = "GLOB",
+        about = "Keep files matching this glob out of app.asar, putting them in app.asar.unpacked next to it instead. Can be given multiple times. A glob without a `/` matches file names anywhere, e.g. `*.node`; others match paths inside the app, e.g. `assets/**`. Defaults to `*.node`, so native modules load straight from disk. These are added after any `asar_unpack` entries from config files."
+    )]
+    #[collider_config(append)]
+    asar_unpack: Vec<String>,
+
+    #[clap(
+        long,
+        about = "Content-addressed store to hard-link Electron's runtime files from, instead of copying them into every build. Builds that share an Electron version then share its files on disk. Falls back to copying where linking isn't possible."
+    )]
+    cas_store: Option<PathBuf>,
+
+    #[clap(
+        long,
+        number_of_values = 1,
+        multiple_occurrences = true,
+        about = "Platform(s) to pack for, instead of the one collider is running on. Can be given multiple times, or as a comma-separated list, e.g. `win32,darwin`, or `all`. Each platform is packed for each --arch, all at once."
+    )]
+    platform: Vec<String>,
+
+    #[clap(
+        long,
+        number_of_values = 1,
+        multiple_occurrences = true,
+        about = "Architecture(s) to pack for, instead of the one collider is running on. Can be given multiple times, or as a comma-separated list, e.g. `x64,arm64`, or `all`. Combinations Electron doesn't ship are skipped."
+    )]
+    arch: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        about = "Also archive each packed app into a single file to distribute, named `<name>-<version>-<os>-<arch>` after package.json. Takes a comma-separated list of `zip` and `tar.gz`. Zip can't hold symlinks, so use tar.gz for macOS apps."
+    )]
+    target: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "ID",
+        about = "Bundle identifier (CFBundleIdentifier) for macOS apps, e.g. `com.example.my-app`. Defaults to `com.collider.<name>`, after package.json."
+    )]
+    app_id: Option<String>,
+
     #[clap(long, short, about = "Force download of the Electron binary.")]
     force: bool,
 
@@ -47,86 +131,552 @@ pub struct PackCmd {
     )]
     include_prerelease: bool,
 
-    #[clap(long, short, about = "GitHub API Token (no permissions needed)")]
+    #[clap(
+        long,
+        about = "Always look up the newest Electron release matching the requested version, instead of reusing a cached one that already matches. Slower, but picks up new releases right away."
+    )]
+    always_resolve: bool,
+
+    #[clap(
+        long,
+        about = "Don't use the network at all. Only Electron versions that are already downloaded are considered."
+    )]
+    offline: bool,
+
+    #[clap(
+        long,
+        short,
+        about = "GitHub API Token (no permissions needed). Defaults to the `GH_TOKEN` environment variable, then `GITHUB_TOKEN`."
+    )]
     github_token: Option<String>,
 
+    #[clap(
+        long,
+        about = "Version of the `@electron/rebuild` npm package to use when rebuilding native modules.",
+        default_value = DEFAULT_REBUILD_VERSION
+    )]
+    rebuild_version: String,
+
+    #[clap(
+        long,
+        about = "Don't rebuild native modules against Electron. Pack already skips this when node_modules has no .node files or binding.gyp."
+    )]
+    skip_rebuild: bool,
+
+    #[clap(
+        long,
+        about = "Comma-separated list of Chromium locales to keep in the packaged Electron, e.g. `en-US,fr`. All other `locales/*.pak` files (`*.lproj` directories on macOS) are removed. `en-US` is always kept as a fallback."
+    )]
+    locales: Option<String>,
+
+    #[clap(
+        long,
+        about = "Package manager to pack and install the app with. By default, this is detected from the lockfile in the app's directory, falling back to npm.",
+        possible_values = PACKAGE_MANAGERS
+    )]
+    package_manager: Option<String>,
+
+    #[clap(
+        long,
+        about = "Strip debug symbols from the packaged Electron binary and its shared libraries (.so and .node files), using `strip`. Linux builds only."
+    )]
+    strip: bool,
+
+    #[clap(
+        long,
+        about = "Print each external command (npm, yarn, npx, etc) before running it, quoted so it can be pasted into a shell."
+    )]
+    print_command: bool,
+
+    #[clap(
+        long,
+        about = "Print the external commands pack would run, without running anything. Paths that depend on the selected Electron version are shown as placeholders."
+    )]
+    dry_run: bool,
+
+    #[clap(
+        long,
+        about = "Stream output from external commands (npm, yarn, npx, etc) live. By default, their output is only shown if they fail. Ignored with --quiet."
+    )]
+    verbose_child: bool,
+
+    #[clap(
+        long,
+        about = "Write a JSON list of everything pack produced, with each file's size, SHA-256, and target os/arch, to this path. Meant for release jobs that need to know what to upload. Unlike --json output, the format is versioned by its `schemaVersion` field."
+    )]
+    emit_artifacts_json: Option<PathBuf>,
+
     #[clap(from_global)]
     quiet: bool,
 
     #[clap(from_global)]
     json: bool,
+
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+
+    #[clap(from_global)]
+    webhook: Option<String>,
+
+    #[clap(from_global)]
+    webhook_header: Option<String>,
 }
 
 #[async_trait]
 impl ColliderCommand for PackCmd {
     async fn execute(self) -> Result<()> {
+        if self.dry_run {
+            return self.print_dry_run();
+        }
         let out = self.output.clone();
-        // Make sure we've downloaded & cached an electron version
-        let electron = self.ensure_electron().await?;
+        let webhook = Webhook::from_opts(self.webhook.as_deref(), self.webhook_header.as_deref())?;
+        let targets = self.targets()?;
+        let formats = self.archive_formats()?;
+        let archive_stem = if formats.is_empty() {
+            None
+        } else {
+            Some(archive_stem(&self.path)?)
+        };
+        let bundle_info = if targets
+            .iter()
+            .any(|(platform, _)| matches!(platform.as_str(), "darwin" | "mas"))
+        {
+            Some(BundleInfo::from_package_json(
+                &self.path,
+                self.app_id.as_deref(),
+            )?)
+        } else {
+            None
+        };
         fs::create_dir_all(&out)
             .await
             .into_diagnostic()
             .context("Failed to create output directory")?;
-        let (build_dir, rel_electron) = self.ensure_build_dir(&electron, &out).await?;
-        let asar = self.ensure_asar(&rel_electron, &build_dir).await?;
-        self.place_asar(
-            &rel_electron,
-            &asar,
-            &build_dir.join("release").join("resources").join("app.asar"),
-        )
-        .await?;
-        println!("{:#?}", rel_electron);
+        // The project only needs to be packed up once. Unless it has native
+        // modules to rebuild for each target, so does app.asar.
+        let staged = match &self.asar {
+            Some(_) => None,
+            None => Some(self.stage_proj(&out).await?),
+        };
+        let skip_rebuild = match &staged {
+            Some(_) if self.skip_rebuild => {
+                tracing::info!("Skipping native module rebuild: --skip-rebuild was given.");
+                true
+            }
+            Some(proj) if !has_native_modules(proj).await? => {
+                tracing::info!(
+                    "Skipping native module rebuild: node_modules has no .node files or binding.gyp."
+                );
+                true
+            }
+            _ => false,
+        };
+        let shared_asar = match (&self.asar, &staged) {
+            (Some(asar), _) => Some(asar.clone()),
+            (None, Some(proj)) if skip_rebuild => {
+                let asar = out.join("staging").join("app.asar");
+                self.pack_asar(proj, &asar).await?;
+                Some(asar)
+            }
+            _ => None,
+        };
+        let shared = Arc::new(SharedPack {
+            // Several bars redrawing over each other would just be noise.
+            progress_bar: !self.quiet && !self.json && targets.len() == 1,
+            out,
+            webhook,
+            staged,
+            asar: shared_asar,
+            formats,
+            archive_stem,
+            bundle_info,
+        });
+        let cmd = Arc::new(self);
+        let tasks = targets
+            .iter()
+            .cloned()
+            .map(|(platform, arch)| {
+                let (cmd, shared) = (cmd.clone(), shared.clone());
+                smol::spawn(async move { cmd.pack_target(&shared, &platform, &arch).await })
+            })
+            .collect::<Vec<_>>();
+        // Every target gets to finish (or fail) before reporting, so one
+        // failure doesn't leave the others' child processes running.
+        let mut bundles = Vec::new();
+        let mut artifacts = Vec::new();
+        let mut failures = Vec::new();
+        for ((platform, arch), task) in targets.iter().zip(tasks) {
+            match task.await {
+                Ok((bundle, target_artifacts)) => {
+                    bundles.push(bundle);
+                    artifacts.extend(target_artifacts);
+                }
+                Err(e) => failures.push((format!("{}-{}", platform, arch), e)),
+            }
+        }
+        if let Some(dest) = &cmd.emit_artifacts_json {
+            write_artifacts_json(dest, artifacts).await?;
+        }
+        if cmd.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&bundles).into_diagnostic()?
+            );
+        } else if !cmd.quiet && !bundles.is_empty() {
+            println!("Packed {} bundle(s):", bundles.len());
+            for bundle in &bundles {
+                println!("  {}\t{}", bundle.target, bundle.output.display());
+                for archive in &bundle.archives {
+                    println!("    {}", archive.display());
+                }
+            }
+        }
+        if failures.len() == 1 {
+            let (_, e) = failures.remove(0);
+            return Err(e);
+        }
+        if !failures.is_empty() {
+            for (target, e) in &failures {
+                tracing::error!("Packing for {} failed: {:?}", target, e);
+            }
+            return Err(PackError::TargetsFailed(
+                failures.into_iter().map(|(target, _)| target).collect(),
+            )
+            .into());
+        }
         Ok(())
     }
 }
 
 impl PackCmd {
-    async fn ensure_asar(&self, electron: &Electron, build_dir: &Path) -> Result<PathBuf> {
-        if let Some(asar) = &self.asar {
-            return Ok(asar.clone());
-        }
-        // TODO: npm pack the project up, extract it into the build dir, `npm
-        // i --production` it, then continue with the rest here.
-        let tarball = self.npm_pack_proj(&self.path).await?;
-        let proj_dest = self.extract_to_build_dir(&tarball, build_dir).await?;
-        self.prune_proj(&proj_dest).await?;
-        self.rebuild_proj(&proj_dest, electron).await?;
-        let asar_dest = build_dir.join("app.asar");
-        self.pack_asar(&proj_dest, &asar_dest).await?;
-        Ok(asar_dest)
+    /// Everything pack does for one target, once the project's been staged.
+    /// Runs concurrently with the other targets' pipelines.
+    async fn pack_target(
+        &self,
+        shared: &SharedPack,
+        platform: &str,
+        arch: &str,
+    ) -> Result<(PackedBundle, Vec<PackArtifact>)> {
+        // Make sure we've downloaded & cached an electron version
+        let electron = self
+            .ensure_electron(platform, arch, shared.progress_bar, shared.webhook.as_ref())
+            .await?;
+        if let Some(webhook) = &shared.webhook {
+            webhook
+                .send(&ColliderEvent::Resolved {
+                    command: "pack".into(),
+                    electron: electron.info(),
+                })
+                .await;
+        }
+        let (build_dir, rel_electron) = self.ensure_build_dir(&electron, &shared.out).await?;
+        if let Some(info) = &shared.bundle_info {
+            self.set_bundle_info(&rel_electron, info).await?;
+        }
+        if let Some(locales) = &self.locales {
+            self.prune_electron_locales(&rel_electron, locales).await?;
+        }
+        if self.strip {
+            self.strip_binaries(&rel_electron).await?;
+        }
+        let asar = match (&shared.asar, &shared.staged) {
+            (Some(asar), _) => asar.clone(),
+            (None, Some(proj)) => self.target_asar(proj, &rel_electron, &build_dir).await?,
+            (None, None) => {
+                unreachable!("BUG: either there's a prebuilt asar, or the project got staged")
+            }
+        };
+        let app_asar = resources_dir(rel_electron.os(), rel_electron.dist_dir()).join("app.asar");
+        self.place_asar(&rel_electron, &asar, &app_asar).await?;
+        check_bundle_symlinks(&rel_electron).await?;
+        let mut archives = Vec::new();
+        for format in &shared.formats {
+            let archive = shared.out.join(format!(
+                "{}-{}-{}.{}",
+                shared
+                    .archive_stem
+                    .as_deref()
+                    .expect("BUG: archives need a name"),
+                rel_electron.os(),
+                rel_electron.arch(),
+                format.extension()
+            ));
+            tracing::info!("Archiving app into {}.", archive.display());
+            let (format, release, dest) = (*format, build_dir.join("release"), archive.clone());
+            smol::unblock(move || archive::write_archive(format, &release, &dest)).await?;
+            archives.push(archive);
+        }
+        // The webhook reports the same hashes `--emit-artifacts-json` does.
+        let artifacts = if self.emit_artifacts_json.is_some() || shared.webhook.is_some() {
+            let hashed = pack_artifacts(
+                rel_electron.dist_dir(),
+                rel_electron.os(),
+                rel_electron.arch(),
+                &app_asar,
+                &archives,
+            )
+            .await;
+            match hashed {
+                Ok(artifacts) => artifacts,
+                // Like delivery itself, the webhook alone shouldn't fail an
+                // otherwise-good pack.
+                Err(e) if self.emit_artifacts_json.is_none() => {
+                    tracing::warn!("{:?}", e);
+                    Vec::new()
+                }
+                Err(e) => return Err(e),
+            }
+        } else {
+            Vec::new()
+        };
+        if let Some(webhook) = &shared.webhook {
+            webhook
+                .send(&ColliderEvent::PackComplete {
+                    electron: rel_electron.info(),
+                    output: build_dir.clone(),
+                    artifacts: artifacts.iter().filter_map(PackArtifact::hashed).collect(),
+                })
+                .await;
+        }
+        let bundle = PackedBundle {
+            output: build_dir,
+            electron_version: rel_electron.version().to_string(),
+            target: rel_electron.triple(),
+            archives,
+        };
+        Ok((bundle, artifacts))
     }
 
-    async fn npm_pack_proj(&self, proj_dir: &Path) -> Result<PathBuf> {
-        let npm_path = which::which("npm").into_diagnostic().context(
-            "Failed to find npm command while packaging project. NPM/npx are required by collider.",
-        )?;
+    fn print_dry_run(&self) -> Result<()> {
+        // Nothing gets resolved or built here, so paths under the build dir
+        // are shown relative to a placeholder for the Electron target.
+        let staging = self.output.join("staging");
+        let proj = staging.join("package");
+        let mut steps: Vec<ToolStep> = Vec::new();
+        // With a prebuilt asar, no package manager ever gets run.
+        let pm = match self.asar {
+            Some(_) => None,
+            None => Some(self.package_manager()?),
+        };
+        if let Some(pm) = pm {
+            steps.push((pm.as_str(), pm.pack_args(), Some(self.path.clone())));
+            steps.push((
+                pm.as_str(),
+                pm.install_production_args(),
+                Some(proj.clone()),
+            ));
+        }
+        for (platform, arch) in self.targets()? {
+            let build_dir = self
+                .output
+                .join(format!("<electron-version>-{}-{}", platform, arch));
+            if self.strip && platform == "linux" {
+                // The real run also strips any .so and .node files it finds.
+                steps.push((
+                    "strip",
+                    self.strip_args(&[build_dir.join("release").join("electron")]),
+                    None,
+                ));
+            }
+            if let (Some(pm), false) = (pm, self.skip_rebuild) {
+                // The real run only rebuilds if the project has native
+                // modules. app.asar gets written without any external tools.
+                let (tool, args) = pm.dlx(self.rebuild_args(&platform, &arch));
+                steps.push((tool, args, Some(build_dir.join("package"))));
+            }
+        }
+        if steps.is_empty() && !self.quiet {
+            println!("# Using prebuilt asar; no external commands to run.");
+        }
+        for (tool, args, cwd) in &steps {
+            println!(
+                "{}",
+                format_command_line(cwd.as_deref(), &[], OsStr::new(tool), args)
+            );
+        }
+        Ok(())
+    }
 
-        // TODO: pnpm and Yarn support. See https://github.com/zkochan/which-pm. For now, just use NPM :)
-        let mut cmd = if cfg!(target_os = "windows") {
-            let mut cmd = Command::new("cmd");
-            cmd.arg("/c");
-            cmd.arg(npm_path);
-            cmd
-        } else {
-            Command::new(npm_path)
+    /// Every `(platform, arch)` to pack for. Without `--platform` or
+    /// `--arch`, that's just the host's.
+    fn targets(&self) -> Result<Vec<(String, String)>> {
+        let split = |lists: &[String], host: &str| {
+            let items = lists
+                .iter()
+                .flat_map(|list| list.split(','))
+                .map(|item| item.trim().to_owned())
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>();
+            if items.is_empty() {
+                vec![host.to_owned()]
+            } else {
+                items
+            }
         };
+        let platforms = split(&self.platform, host_platform()?);
+        let archs = split(&self.arch, host_arch()?);
+        let several = platforms.len() * archs.len() > 1;
+        let mut targets = Vec::new();
+        for platform in &platforms {
+            for arch in &archs {
+                match expand_targets(platform, arch) {
+                    Ok(expanded) => {
+                        for target in expanded {
+                            if !targets.contains(&target) {
+                                targets.push(target);
+                            }
+                        }
+                    }
+                    // With a list, some combinations not existing is expected.
+                    Err(ElectronError::UnsupportedArch(_))
+                        if several && ARCHS.contains(&arch.as_str()) =>
+                    {
+                        tracing::warn!(
+                            "Electron doesn't ship for {}-{}. Skipping it.",
+                            platform,
+                            arch
+                        );
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        if targets.is_empty() {
+            return Err(ElectronError::UnsupportedArch(format!(
+                "{}-{}",
+                platforms.join(","),
+                archs.join(",")
+            ))
+            .into());
+        }
+        Ok(targets)
+    }
 
-        let output = cmd
-            .arg("pack")
-            .output()
-            .await
-            .into_diagnostic()
-            .context("Failed to spawn NPM")?;
+    /// Runs an external tool to completion, printing it first if asked to.
+    async fn run_tool(&self, tool: &str, args: &[OsString], cwd: Option<&Path>) -> Result<Output> {
+        let command_line = format_command_line(cwd, &[], OsStr::new(tool), args);
+        if self.print_command {
+            println!("{}", command_line);
+        }
+        let mut cmd = which_tool(tool)?;
+        cmd.args(args);
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+        let output = ChildOutput::from_flags(self.verbose_child, self.quiet || self.json);
+        Ok(run_tool(cmd, &command_line, output).await?)
+    }
 
-        if !output.status.success() {
-            miette::bail!("NPM pack failed")
+    /// Every `--target` archive format asked for.
+    fn archive_formats(&self) -> Result<Vec<ArchiveFormat>, PackError> {
+        let mut formats = Vec::new();
+        for format in self.target.iter().flat_map(|list| list.split(',')) {
+            let format = format.trim();
+            if format.is_empty() {
+                continue;
+            }
+            let format = format.parse()?;
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
         }
+        Ok(formats)
+    }
 
-        let package_file = String::from_utf8(output.stdout)
+    fn package_manager(&self) -> Result<PackageManager> {
+        Ok(PackageManager::resolve(
+            self.package_manager.as_deref(),
+            &self.path,
+        )?)
+    }
+
+    fn strip_args(&self, files: &[PathBuf]) -> Vec<OsString> {
+        // Only debug info: dynamic symbols have to stay, since native modules
+        // link against the ones Electron exports.
+        let mut args = vec![OsString::from("--strip-debug")];
+        args.extend(files.iter().map(OsString::from));
+        args
+    }
+
+    fn rebuild_args(&self, platform: &str, arch: &str) -> Vec<OsString> {
+        vec![
+            format!("@electron/rebuild@{}", self.rebuild_version).into(),
+            "--arch".into(),
+            arch.into(),
+            "--platform".into(),
+            platform.into(),
+        ]
+    }
+
+    /// Packs up the project with its package manager and installs its
+    /// production dependencies, once, in a staging directory under `out`.
+    async fn stage_proj(&self, out: &Path) -> Result<PathBuf> {
+        let pm = self.package_manager()?;
+        tracing::debug!("Using {} to pack {}", pm, self.path.display());
+        let tarball = self.pack_proj(pm, &self.path).await?;
+        let proj = self
+            .extract_to_build_dir(&tarball, &out.join("staging"))
+            .await?;
+        validate_packed_proj(&proj)?;
+        self.prune_proj(pm, &proj).await?;
+        let ignore_file = self.path.join(IGNORE_FILE);
+        if ignore_file.is_file() {
+            let (from, staged) = (ignore_file.clone(), proj.clone());
+            let excluded = smol::unblock(move || exclude::remove_ignored(&staged, &from)).await?;
+            tracing::debug!(
+                "Excluded {} file(s) matching {}",
+                excluded,
+                ignore_file.display()
+            );
+            // In case the patterns caught something the app can't do without.
+            validate_packed_proj(&proj)?;
+        }
+        Ok(proj)
+    }
+
+    /// Builds an app.asar just for `electron`'s target, from a copy of the
+    /// staged project with its native modules rebuilt for that target.
+    async fn target_asar(
+        &self,
+        staged: &Path,
+        electron: &Electron,
+        build_dir: &Path,
+    ) -> Result<PathBuf> {
+        let proj_dest = build_dir.join("package");
+        if proj_dest.exists() {
+            fs::remove_dir_all(&proj_dest)
+                .await
+                .into_diagnostic()
+                .context("Failed to clear out the previous build's project")?;
+        }
+        fs::create_dir_all(&proj_dest).await.into_diagnostic()?;
+        let (from, to) = (staged.to_owned(), proj_dest.clone());
+        smol::unblock(move || {
+            let mut opts = fs_extra::dir::CopyOptions::new();
+            opts.content_only = true;
+            fs_extra::dir::copy(from, to, &opts)
+        })
+        .await
+        .into_diagnostic()
+        .context("Failed to copy the staged project into the build directory")?;
+        self.rebuild_proj(&proj_dest, electron).await?;
+        let asar_dest = build_dir.join("app.asar");
+        self.pack_asar(&proj_dest, &asar_dest).await?;
+        Ok(asar_dest)
+    }
+
+    async fn pack_proj(&self, pm: PackageManager, proj_dir: &Path) -> Result<PathBuf> {
+        let output = self
+            .run_tool(pm.as_str(), &pm.pack_args(), Some(proj_dir))
+            .await?;
+        let stdout = String::from_utf8(output.stdout)
             .into_diagnostic()
             .context("Package name is invalid utf8")?;
-
-        Ok(proj_dir.join(&package_file.trim()))
+        let package_file = pm
+            .packed_tarball(&stdout)
+            .ok_or_else(|| PackError::PackedTarballMissing(pm.to_string()))?;
+        // An absolute path (from yarn) just replaces proj_dir here.
+        Ok(proj_dir.join(package_file))
     }
 
     async fn extract_to_build_dir(&self, tarball: &Path, build_dir: &Path) -> Result<PathBuf> {
@@ -144,10 +694,32 @@ impl PackCmd {
         Ok(build_dir.join("package"))
     }
 
-    async fn ensure_electron(&self) -> Result<Electron> {
-        let opts = ElectronOpts::new()
+    async fn ensure_electron(
+        &self,
+        platform: &str,
+        arch: &str,
+        progress_bar: bool,
+        webhook: Option<&Webhook>,
+    ) -> Result<Electron> {
+        let mut opts = ElectronOpts::new()
             .force(self.force)
-            .include_prerelease(self.include_prerelease);
+            .include_prerelease(self.include_prerelease)
+            .progress_bar(progress_bar)
+            .always_resolve(self.always_resolve)
+            .offline(self.offline);
+        if let Some(token) = &self.github_token {
+            opts = opts.github_token(token);
+        }
+        if let Some(prompt) = ConfirmRetry::if_interactive(self.quiet, self.json) {
+            opts = opts.retry_prompt(prompt);
+        }
+        if let Some(cache_dir) = &self.cache_dir {
+            opts = opts.cache_dir(cache_dir);
+        }
+        if let Some(webhook) = webhook {
+            opts = opts.on_event(webhook.download_milestones("pack"));
+        }
+        opts = opts.platform(platform.parse::<Platform>()?).arch(arch);
 
         let electron = opts.ensure_electron().await?;
         Ok(electron)
@@ -158,17 +730,124 @@ impl PackCmd {
         electron: &Electron,
         out: &Path,
     ) -> Result<(PathBuf, Electron)> {
-        let electron_dir = electron
+        // Named for the full target, so builds for different targets don't
+        // clobber each other.
+        let build_dir = out.join(electron.triple());
+        let new_electron = electron
+            .copy_files(&build_dir.join("release"), self.cas_store.as_deref())
+            .await?;
+        // Catch an interrupted or short copy here, before the asar steps
+        // spend time on a runtime that can't work.
+        new_electron.verify_version().await?;
+        Ok((build_dir, new_electron))
+    }
+
+    /// Makes a macOS app call itself by the project's name, instead of
+    /// "Electron", in the Dock and About panel.
+    async fn set_bundle_info(&self, electron: &Electron, info: &BundleInfo) -> Result<()> {
+        if !matches!(electron.os(), "darwin" | "mas") {
+            return Ok(());
+        }
+        // Electron.app/Contents/MacOS/Electron
+        let plist = electron
             .exe()
             .parent()
-            .expect("BUG: This should definitely have a parent directory.")
-            .to_owned();
-        let dirname = electron_dir
-            .file_name()
-            .expect("BUG: This should have a file name.");
-        let build_dir = out.join(dirname);
-        let new_electron = electron.copy_files(&build_dir.join("release")).await?;
-        Ok((build_dir, new_electron))
+            .and_then(Path::parent)
+            .expect("BUG: The exe should be inside Electron.app/Contents.")
+            .join("Info.plist");
+        if self.cas_store.is_some() {
+            unshare(&plist)
+                .into_diagnostic()
+                .with_context(|| format!("Failed to unshare {}", plist.display()))?;
+        }
+        tracing::debug!("Setting bundle info in {}", plist.display());
+        let info = info.clone();
+        smol::unblock(move || bundle::write_info_plist(&plist, &info)).await?;
+        Ok(())
+    }
+
+    async fn prune_electron_locales(&self, electron: &Electron, locales: &str) -> Result<()> {
+        let (dirs, kind) = if matches!(electron.os(), "darwin" | "mas") {
+            // Chromium's own live in the framework, and the app lists the
+            // ones it supports as empty dirs of its own.
+            let contents = electron.dist_dir().join("Electron.app").join("Contents");
+            let framework = contents
+                .join("Frameworks")
+                .join("Electron Framework.framework")
+                .join("Resources");
+            (
+                vec![resources_dir(electron.os(), electron.dist_dir()), framework],
+                LocaleFiles::Lproj,
+            )
+        } else {
+            let locales_dir = electron
+                .exe()
+                .parent()
+                .expect("BUG: This should have a parent directory.")
+                .join("locales");
+            (vec![locales_dir], LocaleFiles::Pak)
+        };
+        let mut existing = Vec::new();
+        for dir in dirs {
+            if fs::metadata(&dir).await.is_ok() {
+                existing.push(dir);
+            } else {
+                tracing::warn!(
+                    "No locales directory found at {}. Skipping it.",
+                    dir.display()
+                );
+            }
+        }
+        let keep = locales
+            .split(',')
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>();
+        let (removed, saved) = prune_locales(&existing, kind, &keep).await?;
+        if !self.quiet && !self.json {
+            println!(
+                "Pruned {} locale files, saving {:.1} MB.",
+                removed,
+                saved as f64 / 1_000_000.0
+            );
+        }
+        Ok(())
+    }
+
+    async fn strip_binaries(&self, electron: &Electron) -> Result<()> {
+        if electron.os() != "linux" {
+            tracing::warn!(
+                "--strip only applies to Linux builds, not {}. Skipping.",
+                electron.os()
+            );
+            return Ok(());
+        }
+        let dist_dir = electron.dist_dir().to_owned();
+        let files = smol::unblock(move || strippable_files(&dist_dir))
+            .await
+            .into_diagnostic()
+            .context("Failed to look for binaries to strip")?;
+        if self.cas_store.is_some() {
+            // strip rewrites files in place when they're hard-linked, which
+            // would change them in the store, too.
+            for file in &files {
+                unshare(file)
+                    .into_diagnostic()
+                    .with_context(|| format!("Failed to unshare {}", file.display()))?;
+            }
+        }
+        let before = total_size(&files).await?;
+        self.run_tool("strip", &self.strip_args(&files), None)
+            .await?;
+        let after = total_size(&files).await?;
+        if !self.quiet && !self.json {
+            println!(
+                "Stripped {} binaries, saving {:.1} MB.",
+                files.len(),
+                before.saturating_sub(after) as f64 / 1_000_000.0
+            );
+        }
+        Ok(())
     }
 
     async fn place_asar(&self, electron: &Electron, asar: &Path, dest: &Path) -> Result<()> {
@@ -179,125 +858,857 @@ impl PackCmd {
             dest.display()
         );
         let opts = fs_extra::file::CopyOptions::new();
-        fs_extra::file::copy(asar, &dest, &opts).into_diagnostic()?;
+        fs_extra::file::copy(asar, dest, &opts).into_diagnostic()?;
+        let (unpacked, unpacked_dest) = (unpacked_dir(asar), unpacked_dir(dest));
+        if unpacked.is_dir() {
+            tracing::debug!(
+                "Copying unpacked files from {} to {}",
+                unpacked.display(),
+                unpacked_dest.display()
+            );
+            if unpacked_dest.exists() {
+                fs::remove_dir_all(&unpacked_dest).await.into_diagnostic()?;
+            }
+            smol::unblock(move || copy_tree(&unpacked, &unpacked_dest))
+                .await
+                .into_diagnostic()
+                .context("Failed to copy app.asar.unpacked into the build")?;
+        }
         Ok(())
     }
 
     async fn remove_default_app_asar(&self, electron: &Electron) -> Result<()> {
-        let default_app = electron
-            .exe()
-            .parent()
-            .expect("BUG: This should have a parent directory.")
-            .join("resources")
-            .join("default_app.asar");
+        let default_app =
+            resources_dir(electron.os(), electron.dist_dir()).join("default_app.asar");
         fs::remove_file(&default_app).await.into_diagnostic()?;
         Ok(())
     }
 
-    async fn prune_proj(&self, proj_dir: &Path) -> Result<()> {
+    async fn prune_proj(&self, pm: PackageManager, proj_dir: &Path) -> Result<()> {
         tracing::info!("Pruning current node_modules down to only production dependencies.");
-        // TODO: Instead of doing this, get a direct path to the npm-cli.js
-        // file. This will help bypass the Terminate Batch Job b.s. on
-        // Windows.
-        let npm_path = which::which("npm").into_diagnostic().context(
-            "Failed to find npm command while packaging project. NPM/npx are required by collider.",
-        )?;
-
-        // TODO: pnpm and Yarn support. See https://github.com/zkochan/which-pm. For now, just use NPM :)
-        let mut cmd = if cfg!(target_os = "windows") {
-            let mut cmd = Command::new("cmd");
-            cmd.arg("/c");
-            cmd.arg(npm_path);
-            cmd
+        self.run_tool(pm.as_str(), &pm.install_production_args(), Some(proj_dir))
+            .await?;
+        Ok(())
+    }
+
+    async fn rebuild_proj(&self, proj_dir: &Path, electron: &Electron) -> Result<()> {
+        if electron.os() != host_platform()? {
+            // @electron/rebuild can't compile for another OS. That's fine as
+            // long as there's nothing to compile.
+            let node_modules = proj_dir.join("node_modules");
+            let modules = smol::unblock(move || native_modules(&node_modules))
+                .await
+                .into_diagnostic()
+                .context("Failed to look for native modules")?;
+            if !modules.is_empty() {
+                return Err(PackError::ForeignNativeModules {
+                    platform: electron.os().into(),
+                    modules,
+                }
+                .into());
+            }
+            tracing::info!("No native modules to rebuild for {}.", electron.os());
+            return Ok(());
+        }
+        tracing::info!(
+            "Rebuilding native modules for {}-{}.",
+            electron.os(),
+            electron.arch()
+        );
+        let (tool, args) = self
+            .package_manager()?
+            .dlx(self.rebuild_args(electron.os(), electron.arch()));
+        self.run_tool(tool, &args, Some(proj_dir)).await?;
+        Ok(())
+    }
+
+    async fn pack_asar(&self, proj_dir: &Path, dest: &Path) -> Result<()> {
+        tracing::info!("Packing app into {}.", dest.display());
+        let (proj_dir, dest) = (proj_dir.to_owned(), dest.to_owned());
+        let unpack = if self.asar_unpack.is_empty() {
+            vec![DEFAULT_ASAR_UNPACK.to_owned()]
         } else {
-            Command::new(npm_path)
+            self.asar_unpack.clone()
         };
+        smol::unblock(move || collider_asar::pack(&proj_dir, &dest, &unpack)).await?;
+        Ok(())
+    }
+}
+
+/// What every target's pipeline shares, worked out once up front.
+struct SharedPack {
+    out: PathBuf,
+    progress_bar: bool,
+    webhook: Option<Webhook>,
+    /// The project, packed and extracted, unless there's a prebuilt asar.
+    staged: Option<PathBuf>,
+    /// An app.asar every target can use, if no target needs its own.
+    asar: Option<PathBuf>,
+    formats: Vec<ArchiveFormat>,
+    /// What archive names start with, if there are any archives.
+    archive_stem: Option<String>,
+    bundle_info: Option<BundleInfo>,
+}
+
+/// One bundle in pack's `--json` output.
+#[derive(Debug, Serialize)]
+struct PackedBundle {
+    output: PathBuf,
+    electron_version: String,
+    target: String,
+    /// Anything `--target` made from it.
+    archives: Vec<PathBuf>,
+}
 
-        let status = cmd
-            .arg("install")
-            .arg("--production")
-            .current_dir(proj_dir)
-            .status()
+/// What `--emit-artifacts-json` writes.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactsManifest {
+    schema_version: u32,
+    artifacts: Vec<PackArtifact>,
+}
+
+/// Something pack produced.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PackArtifact {
+    /// `app-dir` for the packaged app's directory, `asar` for its
+    /// app.asar, or `archive` for a `--target` archive of it.
+    kind: &'static str,
+    path: PathBuf,
+    /// Total size in bytes. For directories, that's everything inside.
+    size: u64,
+    /// Only files get one.
+    sha256: Option<String>,
+    os: String,
+    arch: String,
+}
+
+impl PackArtifact {
+    /// This artifact as a webhook reports it, if it's a file.
+    fn hashed(&self) -> Option<Artifact> {
+        Some(Artifact {
+            path: self.path.clone(),
+            size: self.size,
+            sha256: self.sha256.clone()?,
+        })
+    }
+}
+
+/// Describes the packaged app in `app_dir`, and the app.asar inside it.
+async fn pack_artifacts(
+    app_dir: &Path,
+    os: &str,
+    arch: &str,
+    app_asar: &Path,
+    archives: &[PathBuf],
+) -> Result<Vec<PackArtifact>> {
+    let dir = app_dir.to_owned();
+    let dir_size = smol::unblock(move || dir_size(&dir))
+        .await
+        .into_diagnostic()
+        .with_context(|| format!("Failed to measure {}", app_dir.display()))?;
+    let asar = Artifact::from_path(app_asar)
+        .await
+        .into_diagnostic()
+        .with_context(|| format!("Failed to hash {}", app_asar.display()))?;
+    let mut artifacts = vec![
+        PackArtifact {
+            kind: "app-dir",
+            path: app_dir.to_owned(),
+            size: dir_size,
+            sha256: None,
+            os: os.into(),
+            arch: arch.into(),
+        },
+        PackArtifact {
+            kind: "asar",
+            path: asar.path,
+            size: asar.size,
+            sha256: Some(asar.sha256),
+            os: os.into(),
+            arch: arch.into(),
+        },
+    ];
+    for archive in archives {
+        let archive = Artifact::from_path(archive)
             .await
             .into_diagnostic()
-            .context("Failed to spawn NPM itself.")?;
+            .with_context(|| format!("Failed to hash {}", archive.display()))?;
+        artifacts.push(PackArtifact {
+            kind: "archive",
+            path: archive.path,
+            size: archive.size,
+            sha256: Some(archive.sha256),
+            os: os.into(),
+            arch: arch.into(),
+        });
+    }
+    Ok(artifacts)
+}
 
-        if !status.success() {
-            miette::bail!("node_modules pruning failed.")
-        }
+async fn write_artifacts_json(dest: &Path, artifacts: Vec<PackArtifact>) -> Result<()> {
+    let manifest = ArtifactsManifest {
+        schema_version: ARTIFACTS_SCHEMA_VERSION,
+        artifacts,
+    };
+    fs::write(
+        dest,
+        serde_json::to_vec_pretty(&manifest).into_diagnostic()?,
+    )
+    .await
+    .into_diagnostic()
+    .with_context(|| format!("Failed to write artifact list to {}", dest.display()))
+}
 
+/// Where an Electron distribution keeps its `.asar` files: inside the app
+/// bundle on macOS, and in `resources/` next to the exe everywhere else.
+fn resources_dir(os: &str, dist_dir: &Path) -> PathBuf {
+    if matches!(os, "darwin" | "mas") {
+        dist_dir
+            .join("Electron.app")
+            .join("Contents")
+            .join("Resources")
+    } else {
+        dist_dir.join("resources")
+    }
+}
+
+/// Makes sure a macOS build's .app bundle will still launch wherever it
+/// ends up: every symlink inside it has to be relative and resolve within
+/// the bundle. Other platforms have no bundle to check.
+async fn check_bundle_symlinks(electron: &Electron) -> Result<()> {
+    let app = match electron
+        .exe()
+        .ancestors()
+        .find(|dir| dir.extension() == Some(OsStr::new("app")))
+    {
+        Some(app) => app.to_owned(),
+        None => return Ok(()),
+    };
+    let app_clone = app.clone();
+    let links = smol::unblock(move || bundle_symlink_problems(&app_clone))
+        .await
+        .into_diagnostic()
+        .context("Failed to check the app bundle's symlinks")?;
+    if links.is_empty() {
         Ok(())
+    } else {
+        Err(PackError::BrokenBundleSymlinks { app, links }.into())
     }
+}
 
-    async fn rebuild_proj(&self, proj_dir: &Path, electron: &Electron) -> Result<()> {
-        tracing::info!("Rebuilding node_modules for target platform.");
-        let npx_path = which::which("npx").into_diagnostic().context(
-            "Failed to find npx command while packaging project. NPM/npx are required by collider.",
-        )?;
-
-        let mut cmd = if cfg!(target_os = "windows") {
-            let mut cmd = Command::new("cmd");
-            cmd.arg("/c");
-            cmd.arg(npx_path);
-            cmd
+/// Every symlink under `app` that's absolute, points outside `app`, or
+/// doesn't resolve, with why.
+fn bundle_symlink_problems(app: &Path) -> std::io::Result<Vec<String>> {
+    let mut problems = Vec::new();
+    let mut dirs = vec![app.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            if !file_type.is_symlink() {
+                continue;
+            }
+            let target = std::fs::read_link(&path)?;
+            let name = path.strip_prefix(app).unwrap_or(&path).display();
+            if target.is_absolute() {
+                problems.push(format!("{} (absolute)", name));
+                continue;
+            }
+            // Resolve lexically, so a `..` can't sneak out of the bundle by
+            // way of another link.
+            let mut resolved = dir.clone();
+            for component in target.components() {
+                match component {
+                    Component::ParentDir => {
+                        resolved.pop();
+                    }
+                    Component::CurDir => {}
+                    other => resolved.push(other),
+                }
+            }
+            if !resolved.starts_with(app) {
+                problems.push(format!("{} (points outside the bundle)", name));
+            } else if std::fs::metadata(&path).is_err() {
+                problems.push(format!("{} (dangling)", name));
+            }
+        }
+    }
+    problems.sort();
+    Ok(problems)
+}
+
+/// Whether anything in `proj`'s node_modules needs rebuilding against
+/// Electron: packages with native code to compile, or prebuilt `.node`
+/// binaries, which were most likely built for Node instead.
+async fn has_native_modules(proj: &Path) -> Result<bool> {
+    let node_modules = proj.join("node_modules");
+    smol::unblock(move || -> std::io::Result<bool> {
+        Ok(!native_modules(&node_modules)?.is_empty() || has_node_files(&node_modules)?)
+    })
+    .await
+    .into_diagnostic()
+    .context("Failed to look for native modules")
+}
+
+/// Whether there are any `.node` files anywhere under `dir`.
+fn has_node_files(dir: &Path) -> std::io::Result<bool> {
+    if !dir.is_dir() {
+        return Ok(false);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if has_node_files(&path)? {
+                return Ok(true);
+            }
+        } else if file_type.is_file() && path.extension() == Some(OsStr::new("node")) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Names of the packages under `node_modules` with native code to compile,
+/// going by whether they have a `binding.gyp`. Scoped and nested packages
+/// are included.
+fn native_modules(node_modules: &Path) -> std::io::Result<Vec<String>> {
+    let mut found = Vec::new();
+    if node_modules.is_dir() {
+        find_native_modules(node_modules, "", &mut found)?;
+    }
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+fn find_native_modules(
+    node_modules: &Path,
+    scope: &str,
+    found: &mut Vec<String>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(node_modules)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if scope.is_empty() && name.starts_with('@') {
+            find_native_modules(&path, &name, found)?;
+            continue;
+        }
+        let name = if scope.is_empty() {
+            name
         } else {
-            Command::new(npx_path)
+            format!("{}/{}", scope, name)
         };
+        if path.join("binding.gyp").is_file() {
+            found.push(name);
+        }
+        let nested = path.join("node_modules");
+        if nested.is_dir() {
+            find_native_modules(&nested, "", found)?;
+        }
+    }
+    Ok(())
+}
+
+/// `<name>-<version>` from the package.json in `proj_dir`, to start archive
+/// names with. Scoped names lose their `@` and `/`.
+fn archive_stem(proj_dir: &Path) -> Result<String, PackError> {
+    let pkg_path = proj_dir.join("package.json");
+    let pkg: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&pkg_path)?)?;
+    match (pkg["name"].as_str(), pkg["version"].as_str()) {
+        (Some(name), Some(version)) => Ok(format!(
+            "{}-{}",
+            name.trim_start_matches('@').replace('/', "-"),
+            version
+        )),
+        _ => Err(PackError::ArchiveNameUnknown(pkg_path)),
+    }
+}
+
+/// Makes sure what `npm pack` gave us is actually an app: it needs a
+/// package.json, and whatever its `main` points to. Misconfigured `files`
+/// fields or .npmignores can leave those out, and it's much nicer to catch
+/// that here than when the packed app fails to start.
+fn validate_packed_proj(proj_dir: &Path) -> Result<(), PackError> {
+    let pkg_path = proj_dir.join("package.json");
+    if !pkg_path.is_file() {
+        return Err(PackError::PackedPackageJsonMissing(proj_dir.to_owned()));
+    }
+    let pkg: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&pkg_path)?)?;
+    let main = package_main(&pkg);
+    if resolve_main(proj_dir, main).is_some() {
+        Ok(())
+    } else {
+        Err(PackError::PackedMainMissing {
+            main: main.into(),
+            dir: proj_dir.to_owned(),
+        })
+    }
+}
+
+/// The ELF binaries in an Electron distribution worth stripping: the
+/// `electron` executable itself, and any shared libraries or native modules.
+/// Files with those names that aren't actually ELF are skipped.
+fn strippable_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            files.extend(strippable_files(&path)?);
+            continue;
+        }
+        if !file_type.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let wanted = name == "electron"
+            || name.ends_with(".so")
+            || name.contains(".so.")
+            || name.ends_with(".node");
+        if wanted && is_elf(&path)? {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn is_elf(path: &Path) -> std::io::Result<bool> {
+    use std::io::Read;
+    let mut magic = [0u8; 4];
+    let read = std::fs::File::open(path)?.read(&mut magic)?;
+    Ok(read == 4 && &magic == b"\x7fELF")
+}
+
+async fn total_size(files: &[PathBuf]) -> Result<u64> {
+    let mut total = 0;
+    for file in files {
+        total += fs::metadata(file).await.into_diagnostic()?.len();
+    }
+    Ok(total)
+}
+
+/// How an Electron build stores Chromium's locales.
+#[derive(Debug, Clone, Copy)]
+enum LocaleFiles {
+    /// `<locale>.pak` files, on Linux and Windows.
+    Pak,
+    /// `<locale>.lproj` directories on macOS, named with underscores
+    /// (`pt_BR.lproj`), and with `en.lproj` for `en-US`.
+    Lproj,
+}
 
-        let status = cmd
-            .arg("electron-rebuild")
-            .arg("--arch")
-            .arg(electron.arch())
-            .arg("--platform")
-            .arg(electron.os())
-            .current_dir(proj_dir)
-            .status()
+impl LocaleFiles {
+    /// The Chromium locale `file_name` is for, if it's for one at all.
+    fn locale_of(self, file_name: &str) -> Option<String> {
+        match self {
+            LocaleFiles::Pak => file_name.strip_suffix(".pak").map(String::from),
+            LocaleFiles::Lproj => match file_name.strip_suffix(".lproj")? {
+                "en" => Some("en-US".into()),
+                // Not a language, just where unlocalized resources go.
+                "Base" => None,
+                locale => Some(locale.replace('_', "-")),
+            },
+        }
+    }
+}
+
+/// Removes every locale in `dirs` that isn't in `keep` (or `en-US`, which
+/// Chromium falls back to). Warns about any requested locales that Electron
+/// doesn't ship. Returns how many files were removed and how many bytes that
+/// freed up.
+async fn prune_locales(dirs: &[PathBuf], kind: LocaleFiles, keep: &[&str]) -> Result<(usize, u64)> {
+    let mut found = Vec::new();
+    let mut removed = 0;
+    let mut saved = 0;
+    for dir in dirs {
+        let mut entries = fs::read_dir(dir)
             .await
             .into_diagnostic()
-            .context("Failed to spawn npx itself.")?;
+            .context("Failed to read Electron locales directory")?;
+        while let Some(entry) = entries.next().await {
+            let path = entry.into_diagnostic()?.path();
+            let locale = match path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| kind.locale_of(name))
+            {
+                Some(locale) => locale,
+                None => continue,
+            };
+            if locale == "en-US" || keep.contains(&locale.as_str()) {
+                found.push(locale);
+                continue;
+            }
+            let failed = || format!("Failed to remove locale at {}", path.display());
+            match kind {
+                LocaleFiles::Pak => {
+                    saved += fs::metadata(&path).await.into_diagnostic()?.len();
+                    fs::remove_file(&path)
+                        .await
+                        .into_diagnostic()
+                        .with_context(failed)?;
+                }
+                LocaleFiles::Lproj => {
+                    let dir = path.clone();
+                    saved += smol::unblock(move || dir_size(&dir))
+                        .await
+                        .into_diagnostic()
+                        .with_context(failed)?;
+                    fs::remove_dir_all(&path)
+                        .await
+                        .into_diagnostic()
+                        .with_context(failed)?;
+                }
+            }
+            removed += 1;
+        }
+    }
+    for locale in keep {
+        if !found.iter().any(|l| l == locale) {
+            tracing::warn!(
+                "Locale `{}` is not one Electron ships. Ignoring it.",
+                locale
+            );
+        }
+    }
+    Ok((removed, saved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use collider_command::{clap::IntoApp, collider_config::ColliderConfig};
+
+    #[test]
+    fn packed_proj_missing_main() {
+        // What `npm pack` produces for `"files": ["lib"]` with a main outside
+        // of lib/.
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "my-app", "main": "main.js", "files": ["lib"]}"#,
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("lib")).unwrap();
+        std::fs::write(dir.path().join("lib").join("util.js"), "").unwrap();
+        assert!(matches!(
+            validate_packed_proj(dir.path()),
+            Err(PackError::PackedMainMissing { ref main, .. }) if main == "main.js"
+        ));
+
+        std::fs::write(dir.path().join("main.js"), "").unwrap();
+        assert!(validate_packed_proj(dir.path()).is_ok());
 
-        if !status.success() {
-            miette::bail!("node_modules rebuild failed.")
+        let empty = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            validate_packed_proj(empty.path()),
+            Err(PackError::PackedPackageJsonMissing(_))
+        ));
+    }
+
+    #[test]
+    fn prunes_unrequested_locales() {
+        let dir = tempfile::tempdir().unwrap();
+        for locale in &["en-US", "fr", "de", "ja"] {
+            std::fs::write(dir.path().join(format!("{}.pak", locale)), b"pak").unwrap();
         }
+        let dirs = [dir.path().to_owned()];
+        let (removed, saved) =
+            smol::block_on(prune_locales(&dirs, LocaleFiles::Pak, &["fr", "xx-YY"])).unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(saved, 6);
+        let mut remaining = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        remaining.sort();
+        assert_eq!(remaining, vec!["en-US.pak", "fr.pak"]);
+    }
 
-        Ok(())
+    #[test]
+    fn prunes_mac_lproj_dirs() {
+        let app = tempfile::tempdir().unwrap();
+        let framework = tempfile::tempdir().unwrap();
+        for dir in &["en", "en_GB", "pt_BR", "de", "Base"] {
+            let lproj = framework.path().join(format!("{}.lproj", dir));
+            std::fs::create_dir(&lproj).unwrap();
+            std::fs::write(lproj.join("locale.pak"), b"pak").unwrap();
+            std::fs::create_dir(app.path().join(format!("{}.lproj", dir))).unwrap();
+        }
+        std::fs::write(framework.path().join("resources.pak"), b"pak").unwrap();
+
+        let dirs = [app.path().to_owned(), framework.path().to_owned()];
+        let (removed, saved) =
+            smol::block_on(prune_locales(&dirs, LocaleFiles::Lproj, &["pt-BR"])).unwrap();
+        assert_eq!(removed, 4);
+        assert_eq!(saved, 6);
+        for dir in &dirs {
+            let mut remaining = std::fs::read_dir(dir)
+                .unwrap()
+                .map(|e| e.unwrap().file_name().into_string().unwrap())
+                .filter(|name| name.ends_with(".lproj"))
+                .collect::<Vec<_>>();
+            remaining.sort();
+            assert_eq!(remaining, vec!["Base.lproj", "en.lproj", "pt_BR.lproj"]);
+        }
+        assert!(framework.path().join("resources.pak").exists());
     }
 
-    async fn pack_asar(&self, proj_dir: &Path, dest: &Path) -> Result<()> {
-        self.run_asar_pack(proj_dir, dest).await?;
-        Ok(())
+    #[test]
+    fn finds_strippable_binaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let elf = b"\x7fELF and then some";
+        std::fs::create_dir_all(dir.path().join("resources/app.asar.unpacked")).unwrap();
+        std::fs::write(dir.path().join("electron"), elf).unwrap();
+        std::fs::write(dir.path().join("libffmpeg.so"), elf).unwrap();
+        std::fs::write(dir.path().join("libvulkan.so.1"), elf).unwrap();
+        std::fs::write(
+            dir.path().join("resources/app.asar.unpacked/addon.node"),
+            elf,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("not-really.so"), b"text").unwrap();
+        std::fs::write(dir.path().join("chrome_100_percent.pak"), elf).unwrap();
+        let found = strippable_files(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|p| p.strip_prefix(dir.path()).unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                PathBuf::from("electron"),
+                PathBuf::from("libffmpeg.so"),
+                PathBuf::from("libvulkan.so.1"),
+                PathBuf::from("resources/app.asar.unpacked/addon.node"),
+            ]
+        );
     }
 
-    async fn run_asar_pack(&self, proj_dir: &Path, dest: &Path) -> Result<()> {
-        tracing::info!("Rebuilding node_modules for target platform.");
-        let npx_path = which::which("npx").into_diagnostic().context(
-            "Failed to find npx command while packaging project. NPM/npx are required by collider.",
-        )?;
+    #[test]
+    fn artifacts_json_lists_app_and_asar() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_dir = dir.path().join("release");
+        let resources = app_dir.join("resources");
+        std::fs::create_dir_all(&resources).unwrap();
+        std::fs::write(app_dir.join("electron"), "runtime").unwrap();
+        std::fs::write(resources.join("app.asar"), "asar").unwrap();
 
-        let mut cmd = if cfg!(target_os = "windows") {
-            let mut cmd = Command::new("cmd");
-            cmd.arg("/c");
-            cmd.arg(npx_path);
-            cmd
-        } else {
-            Command::new(npx_path)
-        };
+        let dest = dir.path().join("artifacts.json");
+        smol::block_on(async {
+            let artifacts =
+                pack_artifacts(&app_dir, "linux", "x64", &resources.join("app.asar"), &[])
+                    .await
+                    .unwrap();
+            write_artifacts_json(&dest, artifacts).await.unwrap();
+        });
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&dest).unwrap()).unwrap();
+        assert_eq!(json["schemaVersion"], ARTIFACTS_SCHEMA_VERSION);
+        let artifacts = json["artifacts"].as_array().unwrap();
+        assert_eq!(artifacts[0]["kind"], "app-dir");
+        assert_eq!(artifacts[0]["size"], "runtime".len() + "asar".len());
+        assert!(artifacts[0]["sha256"].is_null());
+        assert_eq!(artifacts[1]["kind"], "asar");
+        assert_eq!(artifacts[1]["os"], "linux");
+        assert_eq!(artifacts[1]["arch"], "x64");
+        // sha256("asar")
+        assert_eq!(
+            artifacts[1]["sha256"],
+            "20f539dde638d97fcfd24d3b72ac23e8341d108190cbaecdb3e1ce7735514bbd"
+        );
+    }
 
-        let status = cmd
-            .arg("asar")
-            .arg("pack")
-            .arg(proj_dir)
-            .arg(dest)
-            .current_dir(&self.path)
-            .status()
-            .await
-            .into_diagnostic()
-            .context("Failed to spawn npx itself.")?;
+    #[test]
+    fn webhook_gets_every_hashed_artifact() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_dir = dir.path().join("release");
+        std::fs::create_dir_all(&app_dir).unwrap();
+        let app_asar = app_dir.join("app.asar");
+        std::fs::write(&app_asar, "asar").unwrap();
+        let archives = vec![dir.path().join("app-linux-x64.zip")];
+        std::fs::write(&archives[0], "zip").unwrap();
+
+        let artifacts = smol::block_on(pack_artifacts(
+            &app_dir, "linux", "x64", &app_asar, &archives,
+        ))
+        .unwrap();
+        let hashed = artifacts
+            .iter()
+            .filter_map(PackArtifact::hashed)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hashed.iter().map(|a| &a.path).collect::<Vec<_>>(),
+            vec![&app_asar, &archives[0]]
+        );
+        assert_eq!(hashed[1].sha256, artifacts[2].sha256.clone().unwrap());
+    }
 
-        if !status.success() {
-            miette::bail!("Packaging up .asar failed.")
+    #[test]
+    fn finds_native_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        let node_modules = dir.path().join("node_modules");
+        for pkg in &[
+            "plain",
+            "sqlite3",
+            "@scope/keytar",
+            "plain/node_modules/fsevents",
+        ] {
+            std::fs::create_dir_all(node_modules.join(pkg)).unwrap();
+        }
+        for native in &["sqlite3", "@scope/keytar", "plain/node_modules/fsevents"] {
+            std::fs::write(node_modules.join(native).join("binding.gyp"), "{}").unwrap();
         }
+        assert_eq!(
+            native_modules(&node_modules).unwrap(),
+            vec!["@scope/keytar", "fsevents", "sqlite3"]
+        );
+        assert!(native_modules(&dir.path().join("nope")).unwrap().is_empty());
 
-        Ok(())
+        assert!(!has_node_files(&node_modules).unwrap());
+        let prebuilds = node_modules
+            .join("plain")
+            .join("prebuilds")
+            .join("linux-x64");
+        std::fs::create_dir_all(&prebuilds).unwrap();
+        std::fs::write(prebuilds.join("plain.node"), "").unwrap();
+        assert!(has_node_files(&node_modules).unwrap());
+    }
+
+    #[test]
+    fn targets_cross_platforms_and_archs() {
+        let cmd = PackCmd::try_parse_from(["pack"]).unwrap();
+        assert_eq!(
+            cmd.targets().unwrap(),
+            vec![(
+                host_platform().unwrap().to_string(),
+                host_arch().unwrap().to_string()
+            )]
+        );
+
+        // Lists and repeated flags both work, and mix.
+        let cmd = PackCmd::try_parse_from([
+            "pack",
+            "--platform",
+            "darwin,win32",
+            "--arch",
+            "ia32",
+            "--arch",
+            " x64",
+        ])
+        .unwrap();
+        let targets = cmd.targets().unwrap();
+        let targets = targets
+            .iter()
+            .map(|(platform, arch)| format!("{}-{}", platform, arch))
+            .collect::<Vec<_>>();
+        // No ia32 build for macOS, so that one's skipped.
+        assert_eq!(targets, vec!["darwin-x64", "win32-ia32", "win32-x64"]);
+
+        let mut cmd = cmd;
+        cmd.platform = vec!["darwin".into()];
+        cmd.arch = vec!["ia32".into()];
+        assert!(cmd.targets().is_err());
+        cmd.platform = vec!["beos,linux".into()];
+        cmd.arch = Vec::new();
+        assert!(cmd.targets().is_err());
+    }
+
+    #[test]
+    fn targets_from_config_are_replaced_by_flags() {
+        let mut config = ColliderConfig::new();
+        config.set("platform", vec!["linux", "win32"]).unwrap();
+        config.set("arch", vec!["x64"]).unwrap();
+
+        let args = ["pack"];
+        let matches = PackCmd::into_app().get_matches_from(args);
+        let mut cmd = PackCmd::try_parse_from(args).unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+        assert_eq!(cmd.platform, vec!["linux", "win32"]);
+        assert_eq!(cmd.arch, vec!["x64"]);
+
+        let args = ["pack", "--platform", "darwin"];
+        let matches = PackCmd::into_app().get_matches_from(args);
+        let mut cmd = PackCmd::try_parse_from(args).unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+        assert_eq!(cmd.platform, vec!["darwin"]);
+        assert_eq!(cmd.arch, vec!["x64"]);
+    }
+
+    #[test]
+    fn asar_goes_inside_the_mac_bundle() {
+        let release = Path::new("release");
+        for os in ["darwin", "mas"] {
+            assert_eq!(
+                resources_dir(os, release),
+                Path::new("release/Electron.app/Contents/Resources")
+            );
+        }
+        for os in ["linux", "win32"] {
+            assert_eq!(resources_dir(os, release), Path::new("release/resources"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn bundle_symlinks_must_stay_inside() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("Electron.app");
+        let framework = app
+            .join("Contents")
+            .join("Frameworks")
+            .join("Electron Framework.framework");
+        std::fs::create_dir_all(framework.join("Versions").join("A")).unwrap();
+        std::fs::write(
+            framework
+                .join("Versions")
+                .join("A")
+                .join("Electron Framework"),
+            "",
+        )
+        .unwrap();
+        symlink("A", framework.join("Versions").join("Current")).unwrap();
+        symlink(
+            "Versions/Current/Electron Framework",
+            framework.join("Electron Framework"),
+        )
+        .unwrap();
+        assert!(bundle_symlink_problems(&app).unwrap().is_empty());
+
+        symlink("/usr/lib", framework.join("Absolute")).unwrap();
+        symlink("../../../../outside", framework.join("Escapes")).unwrap();
+        symlink("Versions/B", framework.join("Dangling")).unwrap();
+        let name = |link: &str| {
+            Path::new("Contents/Frameworks/Electron Framework.framework")
+                .join(link)
+                .display()
+                .to_string()
+        };
+        assert_eq!(
+            bundle_symlink_problems(&app).unwrap(),
+            vec![
+                format!("{} (absolute)", name("Absolute")),
+                format!("{} (dangling)", name("Dangling")),
+                format!("{} (points outside the bundle)", name("Escapes")),
+            ]
+        );
     }
 }
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use collider_common::{
+    miette::{self, Diagnostic},
+    thiserror::{self, Error},
+};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum SelfUpdateError {
+    #[error(transparent)]
+    #[diagnostic(code(collider::self_update::electron_error))]
+    ElectronError(#[from] collider_electron::ElectronError),
+
+    #[error(transparent)]
+    #[diagnostic(code(collider::self_update::zip_error))]
+    ZipError(#[from] zip::result::ZipError),
+
+    #[error("{0}")]
+    #[diagnostic(code(collider::self_update::io_error))]
+    IoError(String, #[source] std::io::Error),
+
+    #[error("collider's latest release is tagged `{0}`, which isn't a version.")]
+    #[diagnostic(code(collider::self_update::bad_release_tag))]
+    BadReleaseTag(String),
+
+    #[error("collider {version} doesn't have a download for this platform. Looked for {asset}.")]
+    #[diagnostic(
+        code(collider::self_update::no_release_asset),
+        help("Check https://github.com/zkat/electron-collider/releases for a build that works here, or build collider from source.")
+    )]
+    NoReleaseAsset { version: String, asset: String },
+
+    #[error("collider's {0} release doesn't publish checksums, so it can't be installed safely.")]
+    #[diagnostic(
+        code(collider::self_update::no_checksums),
+        help("Download it from https://github.com/zkat/electron-collider/releases yourself, or build collider from source.")
+    )]
+    NoChecksums(String),
+
+    #[error("{0} isn't listed in the release's checksums, so it can't be installed safely.")]
+    #[diagnostic(
+        code(collider::self_update::checksum_missing),
+        help("Download it from https://github.com/zkat/electron-collider/releases yourself, or build collider from source.")
+    )]
+    ChecksumMissing(String),
+
+    #[error("Checksum mismatch for {asset}: expected {expected}, got {actual}.")]
+    #[diagnostic(
+        code(collider::self_update::checksum_mismatch),
+        help(
+            "The download was corrupted or tampered with. Nothing was replaced. Try again later."
+        )
+    )]
+    ChecksumMismatch {
+        asset: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("{asset} doesn't contain {exe}.")]
+    #[diagnostic(code(collider::self_update::exe_missing))]
+    ExeMissing { asset: String, exe: String },
+
+    #[error("Failed to replace {} with the new version.", .path.display())]
+    #[diagnostic(
+        code(collider::self_update::replace_failed),
+        help("If collider is installed somewhere you can't write to, run this again with permission to write there, or update collider the same way you installed it.")
+    )]
+    ReplaceFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
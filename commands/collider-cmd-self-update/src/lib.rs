@@ -0,0 +1,445 @@
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use async_compat::CompatExt;
+use collider_command::{
+    async_trait::async_trait,
+    clap::{self, Clap},
+    collider_config::{self, ColliderConfigLayer},
+    tracing, ColliderCommand,
+};
+use collider_common::{
+    miette::{IntoDiagnostic, Result},
+    serde::Deserialize,
+    serde_json, smol,
+};
+use collider_electron::{
+    build_http_client, check_throttled, expected_checksum, github_auth_header, host_arch,
+    host_platform, retry_async, ElectronError, RetryPolicy, DEFAULT_MAX_RETRIES,
+    DEFAULT_RETRY_DELAY,
+};
+use node_semver::Version;
+use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use sha2::{Digest, Sha256};
+
+pub use errors::SelfUpdateError;
+
+mod errors;
+
+/// Where collider's own releases are published.
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/zkat/electron-collider/releases/latest";
+
+/// The release asset listing every other asset's SHA256, in the same format
+/// as Electron's.
+const SHASUMS_ASSET: &str = "SHASUMS256.txt";
+
+/// Retries for everything self-update fetches, the same as Electron
+/// downloads get by default.
+const RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_retries: DEFAULT_MAX_RETRIES,
+    base_delay: DEFAULT_RETRY_DELAY,
+};
+
+#[derive(Debug, Clap, ColliderConfigLayer)]
+pub struct SelfUpdateCmd {
+    #[clap(
+        long,
+        about = "Only check whether a newer collider is available, without installing it."
+    )]
+    check: bool,
+
+    #[clap(
+        long,
+        short,
+        about = "GitHub API Token (no permissions needed). Defaults to the `GH_TOKEN` environment variable, then `GITHUB_TOKEN`."
+    )]
+    github_token: Option<String>,
+
+    #[clap(from_global)]
+    quiet: bool,
+
+    #[clap(from_global)]
+    json: bool,
+
+    #[clap(skip)]
+    #[collider_config(ignore)]
+    current_version: Option<String>,
+}
+
+impl SelfUpdateCmd {
+    /// The version of collider that's running. Defaults to this crate's own
+    /// version, so the `collider` binary should pass in its own.
+    pub fn current_version(mut self, version: &str) -> Self {
+        self.current_version = Some(version.into());
+        self
+    }
+
+    async fn latest_release(&self, client: &reqwest::Client) -> Result<Release> {
+        let auth = github_auth_header(LATEST_RELEASE_URL, self.github_token.as_deref());
+        let json = retry_async(RETRY_POLICY, || {
+            let mut req = client
+                .get(LATEST_RELEASE_URL)
+                // GitHub's API turns away requests without one.
+                .header(USER_AGENT, concat!("collider/", env!("CARGO_PKG_VERSION")))
+                .header(ACCEPT, "application/vnd.github.v3+json");
+            if let Some(auth) = &auth {
+                req = req.header(AUTHORIZATION, auth);
+            }
+            async move {
+                // Sent inside the Tokio context, like everything else reqwest.
+                let res = check_throttled(req.send().await?)?;
+                Ok::<_, ElectronError>(res.error_for_status()?.text().await?)
+            }
+            .compat()
+        })
+        .await?;
+        Ok(serde_json::from_str(&json).map_err(|e| {
+            ElectronError::from_json_err(e, LATEST_RELEASE_URL.into(), json.clone())
+        })?)
+    }
+}
+
+/// The parts of a GitHub release `collider self-update` cares about.
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn version(&self) -> Result<Version, SelfUpdateError> {
+        self.tag_name
+            .trim_start_matches('v')
+            .parse()
+            .map_err(|_| SelfUpdateError::BadReleaseTag(self.tag_name.clone()))
+    }
+
+    /// The zip with collider for `platform` and `arch`, named the same way as
+    /// Electron's own: `collider-v<version>-<platform>-<arch>.zip`.
+    fn asset_for(&self, platform: &str, arch: &str) -> Result<&ReleaseAsset, SelfUpdateError> {
+        let version = self.version()?;
+        let name = format!("collider-v{}-{}-{}.zip", version, platform, arch);
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| SelfUpdateError::NoReleaseAsset {
+                version: version.to_string(),
+                asset: name,
+            })
+    }
+
+    /// The release's [`SHASUMS_ASSET`]. Nothing gets installed without it.
+    fn shasums(&self) -> Result<&ReleaseAsset, SelfUpdateError> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == SHASUMS_ASSET)
+            .ok_or_else(|| SelfUpdateError::NoChecksums(self.tag_name.clone()))
+    }
+}
+
+#[async_trait]
+impl ColliderCommand for SelfUpdateCmd {
+    async fn execute(self) -> Result<()> {
+        let current: Version = self
+            .current_version
+            .as_deref()
+            .unwrap_or(env!("CARGO_PKG_VERSION"))
+            .parse()
+            .into_diagnostic()?;
+        let exe = std::env::current_exe().map_err(|e| {
+            SelfUpdateError::IoError("Failed to find the running collider executable".into(), e)
+        })?;
+        #[cfg(windows)]
+        remove_old_exe(&exe).await;
+
+        let client = build_http_client(None, None)?;
+        let release = self.latest_release(&client).await?;
+        let latest = release.version()?;
+        let newer = latest > current;
+        let mut updated = false;
+        if newer && !self.check {
+            let platform = host_platform()?;
+            let asset = release.asset_for(platform, host_arch()?)?;
+            tracing::info!("Downloading {}", asset.browser_download_url);
+            let zip = download(&client, &asset.browser_download_url).await?;
+            let shasums = download(&client, &release.shasums()?.browser_download_url).await?;
+            verify_asset(&zip, &asset.name, &String::from_utf8_lossy(&shasums))?;
+            let exe_name = if platform == "win32" {
+                "collider.exe"
+            } else {
+                "collider"
+            };
+            let new_exe = exe_from_zip(zip, &asset.name, exe_name)?;
+            let path = exe.clone();
+            smol::unblock(move || replace_exe(&path, &new_exe)).await?;
+            updated = true;
+        }
+
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "current": current.to_string(),
+                    "latest": latest.to_string(),
+                    "update_available": newer,
+                    "updated": updated,
+                }))
+                .into_diagnostic()?
+            );
+        } else if !self.quiet {
+            if updated {
+                println!("Updated collider from {} to {}.", current, latest);
+            } else if newer {
+                println!(
+                    "collider {} is available (you have {}). Run `collider self-update` to install it.",
+                    latest, current
+                );
+            } else {
+                println!("collider {} is the latest version.", current);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Downloads `url` into memory, retrying transient failures.
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, SelfUpdateError> {
+    let bytes = retry_async(RETRY_POLICY, || async move {
+        async {
+            let res = check_throttled(client.get(url).send().await?)?;
+            Ok::<_, ElectronError>(res.error_for_status()?.bytes().await?)
+        }
+        .compat()
+        .await
+    })
+    .await?;
+    Ok(bytes.to_vec())
+}
+
+/// Checks the downloaded `zip` against its entry in the release's
+/// [`SHASUMS_ASSET`], before anything in it gets near the running executable.
+fn verify_asset(zip: &[u8], asset: &str, shasums: &str) -> Result<(), SelfUpdateError> {
+    let expected = expected_checksum(shasums, asset)
+        .ok_or_else(|| SelfUpdateError::ChecksumMissing(asset.into()))?;
+    let actual = hex::encode(Sha256::digest(zip));
+    if expected.eq_ignore_ascii_case(&actual) {
+        Ok(())
+    } else {
+        Err(SelfUpdateError::ChecksumMismatch {
+            asset: asset.into(),
+            expected: expected.into(),
+            actual,
+        })
+    }
+}
+
+/// Reads the executable called `exe_name` out of the zip `asset`.
+fn exe_from_zip(zip: Vec<u8>, asset: &str, exe_name: &str) -> Result<Vec<u8>, SelfUpdateError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(zip))?;
+    let mut file = match archive.by_name(exe_name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => {
+            return Err(SelfUpdateError::ExeMissing {
+                asset: asset.into(),
+                exe: exe_name.into(),
+            })
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut exe = Vec::new();
+    file.read_to_end(&mut exe).map_err(|e| {
+        SelfUpdateError::IoError(format!("Failed to read {} from {}", exe_name, asset), e)
+    })?;
+    Ok(exe)
+}
+
+/// Where the new executable is written before it's renamed over `exe`.
+fn new_exe_path(exe: &Path) -> PathBuf {
+    let mut name = exe.file_name().expect("BUG: exe has a name").to_owned();
+    name.push(".new");
+    exe.with_file_name(name)
+}
+
+/// Where a running executable is moved to on Windows, so the new one can
+/// take its place. It can't be deleted until it exits.
+#[cfg(windows)]
+fn old_exe_path(exe: &Path) -> PathBuf {
+    let mut name = exe.file_name().expect("BUG: exe has a name").to_owned();
+    name.push(".old");
+    exe.with_file_name(name)
+}
+
+/// Cleans up after an earlier update on Windows. Whatever's there is from a
+/// collider that's since exited.
+#[cfg(windows)]
+async fn remove_old_exe(exe: &Path) {
+    let old = old_exe_path(exe);
+    if smol::fs::remove_file(&old).await.is_ok() {
+        tracing::debug!("Removed {} from an earlier update.", old.display());
+    }
+}
+
+/// Swaps `new_exe` in for the executable at `exe`. The new one is written
+/// next to it first, so it's only ever replaced by a complete file.
+fn replace_exe(exe: &Path, new_exe: &[u8]) -> Result<(), SelfUpdateError> {
+    let replace_failed = |source| SelfUpdateError::ReplaceFailed {
+        path: exe.to_owned(),
+        source,
+    };
+    let tmp = new_exe_path(exe);
+    let written = std::fs::write(&tmp, new_exe).and_then(|_| {
+        // Keeps the executable bit, along with whatever else was set.
+        let perms = std::fs::metadata(exe)?.permissions();
+        std::fs::set_permissions(&tmp, perms)
+    });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(replace_failed(e));
+    }
+    swap_exe(exe, &tmp).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        replace_failed(e)
+    })
+}
+
+#[cfg(not(windows))]
+fn swap_exe(exe: &Path, new_exe: &Path) -> std::io::Result<()> {
+    // The running process keeps its own copy open, so this is safe.
+    std::fs::rename(new_exe, exe)
+}
+
+#[cfg(windows)]
+fn swap_exe(exe: &Path, new_exe: &Path) -> std::io::Result<()> {
+    // A running executable can't be overwritten or deleted, but it can be
+    // renamed out of the way.
+    let old = old_exe_path(exe);
+    std::fs::rename(exe, &old)?;
+    std::fs::rename(new_exe, exe).map_err(|e| {
+        let _ = std::fs::rename(&old, exe);
+        e
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn release(tag: &str, assets: &[&str]) -> Release {
+        Release {
+            tag_name: tag.into(),
+            assets: assets
+                .iter()
+                .map(|name| ReleaseAsset {
+                    name: (*name).into(),
+                    browser_download_url: format!("https://example.com/{}", name),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn assets_are_checked_against_shasums() {
+        let zip = b"not really a zip";
+        let sum = hex::encode(Sha256::digest(zip));
+        let asset = "collider-v0.2.0-linux-x64.zip";
+        let shasums = format!(
+            "{} *collider-v0.2.0-win32-x64.zip\n{} *{}\n",
+            "0".repeat(64),
+            sum.to_uppercase(),
+            asset
+        );
+        verify_asset(zip, asset, &shasums).unwrap();
+        assert!(matches!(
+            verify_asset(b"tampered", asset, &shasums),
+            Err(SelfUpdateError::ChecksumMismatch { ref expected, .. }) if *expected == sum.to_uppercase()
+        ));
+        assert!(matches!(
+            verify_asset(zip, "collider-v0.2.0-darwin-x64.zip", &shasums),
+            Err(SelfUpdateError::ChecksumMissing(_))
+        ));
+
+        assert!(matches!(
+            release("v0.2.0", &[asset]).shasums(),
+            Err(SelfUpdateError::NoChecksums(_))
+        ));
+        assert_eq!(
+            release("v0.2.0", &[asset, SHASUMS_ASSET])
+                .shasums()
+                .unwrap()
+                .name,
+            SHASUMS_ASSET
+        );
+    }
+
+    #[test]
+    fn picks_asset_for_platform() {
+        let release = release(
+            "v0.2.0",
+            &[
+                "collider-v0.2.0-darwin-arm64.zip",
+                "collider-v0.2.0-linux-x64.zip",
+                "collider-v0.2.0-win32-x64.zip",
+            ],
+        );
+        assert_eq!(release.version().unwrap().to_string(), "0.2.0");
+        assert_eq!(
+            release.asset_for("linux", "x64").unwrap().name,
+            "collider-v0.2.0-linux-x64.zip"
+        );
+        assert!(matches!(
+            release.asset_for("linux", "arm64"),
+            Err(SelfUpdateError::NoReleaseAsset { ref asset, .. })
+                if asset == "collider-v0.2.0-linux-arm64.zip"
+        ));
+        assert!(matches!(
+            self::release("nightly", &[]).version(),
+            Err(SelfUpdateError::BadReleaseTag(_))
+        ));
+    }
+
+    #[test]
+    fn reads_exe_from_zip() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("collider", zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(b"new collider").unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+
+        assert_eq!(
+            exe_from_zip(zip.clone(), "collider.zip", "collider").unwrap(),
+            b"new collider"
+        );
+        assert!(matches!(
+            exe_from_zip(zip, "collider.zip", "collider.exe"),
+            Err(SelfUpdateError::ExeMissing { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replaces_exe_keeping_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("collider");
+        std::fs::write(&exe, "old collider").unwrap();
+        std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        replace_exe(&exe, b"new collider").unwrap();
+        assert_eq!(std::fs::read(&exe).unwrap(), b"new collider");
+        assert_eq!(
+            std::fs::metadata(&exe).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        assert!(!new_exe_path(&exe).exists());
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use collider_common::{
     miette::{self, Diagnostic},
     thiserror::{self, Error},
@@ -13,7 +15,43 @@ pub enum StartError {
     #[diagnostic(code(collider::start::semver_error))]
     SemverError(#[from] node_semver::SemverError),
 
-    #[error("Electron process exited with an error")]
-    #[diagnostic(code(collider::start::electron_error))]
-    ElectronFailed,
+    #[error(transparent)]
+    #[diagnostic(code(collider::start::json_error))]
+    JsonError(#[from] collider_common::serde_json::Error),
+
+    #[error("`--base-href` can only be used when launching an .html file, but `{0}` isn't one.")]
+    #[diagnostic(code(collider::start::base_href_requires_html))]
+    BaseHrefRequiresHtml(String),
+
+    #[error("`--base-href` must point to an existing directory: {}", .0.display())]
+    #[diagnostic(code(collider::start::invalid_base_href))]
+    InvalidBaseHref(PathBuf),
+
+    #[error("Could not find an entrypoint for the app in {}. Looked for: {}", .dir.display(), .tried.join(", "))]
+    #[diagnostic(
+        code(collider::start::missing_entrypoint),
+        help("Electron needs a package.json whose `main` field points at an existing file, or an index.js/index.html in the app directory.")
+    )]
+    MissingEntrypoint { dir: PathBuf, tried: Vec<String> },
+
+    #[error("The app's entrypoint, {}, does not exist yet. It looks like build output: try running `npm run {script}` first.", .entry.display())]
+    #[diagnostic(
+        code(collider::start::unbuilt_entrypoint),
+        help("package.json's `main` field should point at the file Electron starts from. If that's generated by a build step, build the app before starting it.")
+    )]
+    UnbuiltEntrypoint { entry: PathBuf, script: String },
+
+    #[error("`{0}` is not a valid environment variable setting.")]
+    #[diagnostic(
+        code(collider::start::invalid_env_var),
+        help("Environment variables are given as `KEY=VALUE`, both with `--env` and in the `env` list in config files.")
+    )]
+    InvalidEnvVar(String),
+
+    #[error("`--inspect` and `--inspect-brk` can't both be used.")]
+    #[diagnostic(
+        code(collider::start::inspect_conflict),
+        help("Pick one. If one of them comes from a config file, remove it there.")
+    )]
+    InspectConflict,
 }
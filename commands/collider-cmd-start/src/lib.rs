@@ -1,23 +1,38 @@
-use std::path::Path;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
 
 use collider_command::{
     async_trait::async_trait,
     clap::{self, Clap},
     collider_config::{self, ColliderConfigLayer},
-    tracing, ColliderCommand,
+    format_command_line, package_main, resolve_main, tracing, ColliderCommand, ColliderEvent,
+    ConfirmRetry, Webhook,
 };
 use collider_common::{
     miette::{Context, Result},
-    smol::process::Command,
+    serde_json,
+    smol::fs,
+};
+use collider_electron::{
+    launch_exe, launch_exe_captured, ElectronError, ElectronOpts, OutputStream,
 };
-use collider_electron::ElectronOpts;
 use node_semver::Range;
+use tempfile::TempDir;
 
 pub use errors::StartError;
 
 mod errors;
 
+/// Node's usual debugger port, for `--inspect` and `--inspect-brk` without
+/// one.
+const DEFAULT_INSPECT_PORT: &str = "5858";
+
+/// Everything here can also be set in a `[start]` section of a
+/// `.colliderrc`, which takes precedence over top-level config keys.
 #[derive(Debug, Clap, ColliderConfigLayer)]
+#[collider_config(section = "start")]
 pub struct StartCmd {
     #[clap(
         about = "Path to Electron app. Must be an index.js file, a folder containing a package.json file, a folder containing an index.json file, and .html/.htm file, or an http/https/file URL.",
@@ -28,16 +43,50 @@ pub struct StartCmd {
     #[clap(long, short, about = "Force download of the Electron binary.")]
     force: bool,
 
-    #[clap(long, short, about = "Electron version to use.", default_value = "*")]
+    #[clap(
+        long,
+        short,
+        about = "Electron version to use. Defaults to the `electron` entry in the app's .tool-versions file, if there is one, and otherwise the latest version.",
+        default_value = "*"
+    )]
     using: String,
 
     #[clap(long, short, about = "Open a REPL to the main process.")]
     interactive: bool,
 
-    #[clap(long, short, about = "Print the Electron version being used.")]
+    #[clap(
+        long,
+        value_name = "PORT",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = DEFAULT_INSPECT_PORT,
+        conflicts_with = "inspect-brk",
+        about = "Let a debugger (VS Code, Chrome DevTools, etc) attach to the main process on this port, 5858 if not given. Use `--inspect=PORT` to pick one."
+    )]
+    inspect: Option<u16>,
+
+    #[clap(
+        long,
+        value_name = "PORT",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = DEFAULT_INSPECT_PORT,
+        about = "Like --inspect, but pause before the app's first line of code runs, until a debugger attaches."
+    )]
+    inspect_brk: Option<u16>,
+
+    #[clap(
+        long,
+        short,
+        about = "Print the Electron version being used. The app isn't launched, so any other arguments for it are ignored."
+    )]
     electron_version: bool,
 
-    #[clap(long, short, about = "Print the Node ABI version.")]
+    #[clap(
+        long,
+        short,
+        about = "Print the Node ABI version. The app isn't launched, so any other arguments for it are ignored."
+    )]
     abi: bool,
 
     #[clap(
@@ -47,68 +96,692 @@ pub struct StartCmd {
     )]
     include_prerelease: bool,
 
+    #[clap(
+        long,
+        about = "Always look up the newest Electron release matching the requested version, instead of reusing a cached one that already matches. Slower, but picks up new releases right away."
+    )]
+    always_resolve: bool,
+
+    #[clap(
+        long,
+        about = "Don't use the network at all. Only Electron versions that are already downloaded are considered."
+    )]
+    offline: bool,
+
     #[clap(long, about = "Trace warnings")]
     trace_warnings: bool,
 
+    #[clap(
+        long,
+        about = "Don't connect collider's stdin to the app. By default, anything piped into `collider start` is passed through to Electron."
+    )]
+    no_stdin: bool,
+
+    #[clap(
+        long = "electron-arg",
+        number_of_values = 1,
+        allow_hyphen_values = true,
+        about = "Extra argument to pass to Electron itself, e.g. `--electron-arg=--disable-gpu`. Can be given multiple times. These are added after any `electron_args` from config files, rather than replacing them."
+    )]
+    #[collider_config(append)]
+    electron_args: Vec<String>,
+
+    #[clap(
+        long = "env",
+        number_of_values = 1,
+        about = "Environment variable to set for Electron, as `KEY=VALUE`. Can be given multiple times. These are added after any `env` entries from config files."
+    )]
+    #[collider_config(append)]
+    env: Vec<EnvVar>,
+
+    #[clap(
+        last = true,
+        about = "Arguments to pass to the app itself, after `--`. These are added after any `app_args` from config files."
+    )]
+    #[collider_config(append)]
+    app_args: Vec<String>,
+
+    #[clap(
+        long,
+        about = "When launching an .html file, serve absolute asset paths (`/assets/app.js`) out of this directory instead of the filesystem root."
+    )]
+    base_href: Option<PathBuf>,
+
+    #[clap(
+        long,
+        about = "Print the full Electron command line before running it, quoted so it can be pasted into a shell."
+    )]
+    print_command: bool,
+
+    #[clap(
+        long,
+        about = "Print the Electron command line that would be run, without running it. Electron is still downloaded if needed, so the printed path is real. The launcher generated for `--base-href` is removed again afterwards, though."
+    )]
+    dry_run: bool,
+
     #[clap(from_global)]
     quiet: bool,
 
     #[clap(from_global)]
     json: bool,
+
+    #[clap(from_global)]
+    cache_dir: Option<PathBuf>,
+
+    #[clap(from_global)]
+    webhook: Option<String>,
+
+    #[clap(from_global)]
+    webhook_header: Option<String>,
 }
 
 #[async_trait]
 impl ColliderCommand for StartCmd {
     async fn execute(self) -> Result<()> {
-        let range = self
-            .using
-            .parse::<Range>()
-            .map_err(StartError::SemverError)?;
+        // clap catches both on the command line, but config can set either.
+        if self.inspect.is_some() && self.inspect_brk.is_some() {
+            return Err(StartError::InspectConflict.into());
+        }
+        // Catch a missing entrypoint before spending time on downloads, since
+        // Electron's own error for it is pretty confusing.
+        let path = Path::new(&self.path);
+        if !self.abi && !self.electron_version && path.is_dir() {
+            check_entrypoint(path)?;
+        }
+        let webhook = Webhook::from_opts(self.webhook.as_deref(), self.webhook_header.as_deref())?;
 
-        let opts = ElectronOpts::new()
-            .range(range)
+        let mut opts = ElectronOpts::new()
             .force(self.force)
-            .include_prerelease(self.include_prerelease);
+            .include_prerelease(self.include_prerelease)
+            .progress_bar(!self.quiet && !self.json)
+            .always_resolve(self.always_resolve)
+            .offline(self.offline);
+        if let Some(prompt) = ConfirmRetry::if_interactive(self.quiet, self.json) {
+            opts = opts.retry_prompt(prompt);
+        }
+        if let Some(webhook) = &webhook {
+            opts = opts.on_event(webhook.download_milestones("start"));
+        }
+        // An explicit --using wins over anything the project pins.
+        if self.using != "*" {
+            opts = opts.range(
+                self.using
+                    .parse::<Range>()
+                    .map_err(StartError::SemverError)?,
+            );
+        }
+        if let Some(cache_dir) = &self.cache_dir {
+            opts = opts.cache_dir(cache_dir);
+        }
+        if path.is_dir() {
+            opts = opts.project_dir(path);
+        } else if let Some(parent) = path.parent() {
+            opts = opts.project_dir(parent);
+        }
 
         let electron = opts.ensure_electron().await?;
+        if let Some(webhook) = &webhook {
+            webhook
+                .send(&ColliderEvent::Resolved {
+                    command: "start".into(),
+                    electron: electron.info(),
+                })
+                .await;
+        }
 
-        tracing::debug!("Launching executable at {}", electron.exe().display());
-        if !self.quiet && !self.json {
+        if !self.quiet && !self.json && !self.dry_run {
             println!(
                 "Starting application. Debug information will be printed here. Press Ctrl+C to exit."
             );
         }
-        self.exec_electron(electron.exe()).await.with_context(|| {
-            format!(
-                "Failed to execute Electron binary at {}",
-                electron.exe().display()
-            )
-        })?;
+        // Any launcher is deleted once Electron exits, or right away on a
+        // dry run.
+        let (app, _launcher) = self.app_path().await?;
+        let stdin = if self.no_stdin {
+            Stdio::null()
+        } else {
+            Stdio::inherit()
+        };
+        self.exec_electron(electron.exe(), app, stdin)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to execute Electron binary at {}",
+                    electron.exe().display()
+                )
+            })?;
         Ok(())
     }
 }
 
 impl StartCmd {
-    async fn exec_electron(&self, exe: &Path) -> Result<(), StartError> {
-        let mut cmd = Command::new(exe);
+    /// Figures out what to actually hand to Electron. Usually that's just
+    /// `path`, but HTML files launched with `--base-href` go through a
+    /// generated main script instead. That lives in a directory of its own
+    /// for this run, which is deleted when it's dropped.
+    async fn app_path(&self) -> Result<(OsString, Option<TempDir>), StartError> {
+        let base = match &self.base_href {
+            Some(base) => base,
+            None => return Ok((self.path.clone().into(), None)),
+        };
+        let entry = Path::new(&self.path);
+        let is_html = matches!(
+            entry.extension().and_then(|ext| ext.to_str()),
+            Some("html") | Some("htm")
+        );
+        if !is_html || !entry.is_file() {
+            return Err(StartError::BaseHrefRequiresHtml(self.path.clone()));
+        }
+        if !base.is_dir() {
+            return Err(StartError::InvalidBaseHref(base.clone()));
+        }
+        let entry = entry.canonicalize()?;
+        let base = base.canonicalize()?;
+        let launcher = tempfile::Builder::new()
+            .prefix("collider-html-launcher-")
+            .tempdir()?;
+        fs::write(
+            launcher.path().join("package.json"),
+            r#"{"name": "collider-html-launcher", "main": "main.js"}"#,
+        )
+        .await?;
+        fs::write(
+            launcher.path().join("main.js"),
+            HTML_LAUNCHER
+                .replace("__ENTRY__", &serde_json::to_string(&entry)?)
+                .replace("__BASE__", &serde_json::to_string(&base)?),
+        )
+        .await?;
+        tracing::debug!(
+            "Launching {} through {}, with assets served from {}",
+            entry.display(),
+            launcher.path().display(),
+            base.display()
+        );
+        Ok((launcher.path().into(), Some(launcher)))
+    }
+
+    /// Electron's full argv. `--abi` and `--electron-version` only ask
+    /// Electron about itself, so they short-circuit everything else.
+    fn electron_argv(&self, app: OsString) -> Vec<OsString> {
+        let mut args = Vec::new();
         if self.abi {
-            cmd.arg("--abi");
+            args.push("--abi".into());
         } else if self.electron_version {
-            cmd.arg("--version");
+            args.push("--version".into());
         } else {
             if self.trace_warnings {
-                cmd.arg("--trace-warnings");
+                args.push("--trace-warnings".into());
             }
             if self.interactive {
-                cmd.arg("--interactive");
+                args.push("--interactive".into());
+            }
+            if let Some(port) = self.inspect {
+                args.push(format!("--inspect={}", port).into());
+            } else if let Some(port) = self.inspect_brk {
+                args.push(format!("--inspect-brk={}", port).into());
             }
-            cmd.arg(&self.path);
+            args.extend(self.electron_args.iter().map(OsString::from));
+            args.push(app);
+            args.extend(self.app_args.iter().map(OsString::from));
         }
-        let status = cmd.status().await?;
-        if status.success() {
-            Ok(())
+        args
+    }
+
+    async fn exec_electron(
+        &self,
+        exe: &Path,
+        app: OsString,
+        stdin: Stdio,
+    ) -> Result<(), ElectronError> {
+        let args = self.electron_argv(app);
+        let env = self
+            .env
+            .iter()
+            .map(|var| (var.key.as_str(), OsStr::new(&var.value)))
+            .collect::<Vec<_>>();
+        if self.print_command || self.dry_run {
+            println!(
+                "{}",
+                format_command_line(None, &env, exe.as_os_str(), &args)
+            );
+        }
+        if self.dry_run {
+            return Ok(());
+        }
+        if self.json {
+            // Electron's raw output would corrupt a JSON stream, so it's
+            // wrapped up into records of its own instead.
+            launch_exe_captured(exe, &args, &env, stdin, |stream, line| {
+                println!("{}", electron_log_record(stream, line));
+            })
+            .await?;
         } else {
-            Err(StartError::ElectronFailed)
+            launch_exe(exe, &args, &env, stdin).await?;
+        }
+        Ok(())
+    }
+}
+
+/// A line of Electron's output, as a JSON log record. stdout lines are
+/// logged at info, and stderr lines at warn.
+fn electron_log_record(stream: OutputStream, line: &str) -> serde_json::Value {
+    let level = match stream {
+        OutputStream::Stdout => "INFO",
+        OutputStream::Stderr => "WARN",
+    };
+    serde_json::json!({
+        "level": level,
+        "source": "electron",
+        "stream": stream.as_str(),
+        "message": line,
+    })
+}
+
+/// An environment variable for Electron, given as `KEY=VALUE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EnvVar {
+    key: String,
+    value: String,
+}
+
+impl FromStr for EnvVar {
+    type Err = StartError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok(EnvVar {
+                key: key.into(),
+                value: value.into(),
+            }),
+            _ => Err(StartError::InvalidEnvVar(s.into())),
+        }
+    }
+}
+
+/// Makes sure an app directory has something for Electron to start: either
+/// whatever package.json's `main` points to, or a conventional index.js or
+/// index.html.
+fn check_entrypoint(dir: &Path) -> Result<(), StartError> {
+    let pkg_path = dir.join("package.json");
+    if pkg_path.is_file() {
+        let pkg: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&pkg_path)?)?;
+        let main = package_main(&pkg);
+        if resolve_main(dir, main).is_some() {
+            return Ok(());
+        }
+        if pkg["scripts"]["build"].is_string() {
+            return Err(StartError::UnbuiltEntrypoint {
+                entry: dir.join(main),
+                script: "build".into(),
+            });
+        }
+        return Err(StartError::MissingEntrypoint {
+            dir: dir.to_owned(),
+            tried: vec![format!("package.json main ({})", main)],
+        });
+    }
+    let conventional = ["index.js", "index.html"];
+    if conventional.iter().any(|name| dir.join(name).is_file()) {
+        Ok(())
+    } else {
+        Err(StartError::MissingEntrypoint {
+            dir: dir.to_owned(),
+            tried: vec![
+                "package.json".into(),
+                "index.js".into(),
+                "index.html".into(),
+            ],
+        })
+    }
+}
+
+/// Main script used to launch a bare HTML file with `--base-href`. Requests
+/// for files that don't exist are rerouted into the base directory, so
+/// absolute asset paths resolve the way they would when served from a web
+/// server rooted there. The entry and anything else that's really on disk
+/// load as-is.
+const HTML_LAUNCHER: &str = r#"const { app, BrowserWindow, protocol } = require("electron");
+const fs = require("fs");
+const path = require("path");
+const { fileURLToPath } = require("url");
+
+const entry = __ENTRY__;
+const base = __BASE__;
+
+app.whenReady().then(() => {
+  protocol.interceptFileProtocol("file", (request, callback) => {
+    const requested = fileURLToPath(request.url);
+    if (
+      requested === base ||
+      requested.startsWith(base + path.sep) ||
+      fs.existsSync(requested)
+    ) {
+      callback({ path: requested });
+    } else {
+      const root = path.parse(requested).root;
+      callback({ path: path.join(base, path.relative(root, requested)) });
+    }
+  });
+  new BrowserWindow({ width: 800, height: 600 }).loadFile(entry);
+});
+
+app.on("window-all-closed", () => app.quit());
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use collider_command::{clap::IntoApp, collider_config::ColliderConfig};
+
+    #[test]
+    fn main_points_at_missing_build_output() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"main": "dist/main.js", "scripts": {"build": "tsc"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            check_entrypoint(dir.path()),
+            Err(StartError::UnbuiltEntrypoint { ref script, .. }) if script == "build"
+        ));
+
+        std::fs::create_dir(dir.path().join("dist")).unwrap();
+        std::fs::write(dir.path().join("dist").join("main.js"), "").unwrap();
+        assert!(check_entrypoint(dir.path()).is_ok());
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            check_entrypoint(empty.path()),
+            Err(StartError::MissingEntrypoint { .. })
+        ));
+    }
+
+    fn html_start_cmd(dir: &Path) -> StartCmd {
+        let entry = dir.join("index.html");
+        std::fs::write(&entry, "").unwrap();
+        let base = dir.join("public");
+        std::fs::create_dir_all(&base).unwrap();
+        StartCmd::try_parse_from([
+            OsStr::new("start"),
+            entry.as_os_str(),
+            OsStr::new("--base-href"),
+            base.as_os_str(),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn html_launcher_is_generated_per_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let cmd = html_start_cmd(dir.path());
+        let (app, launcher) = collider_common::smol::block_on(cmd.app_path()).unwrap();
+        let launcher = launcher.unwrap();
+        assert_eq!(app, launcher.path().as_os_str());
+        let pkg: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(launcher.path().join("package.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(pkg["main"], "main.js");
+        assert!(launcher.path().join("main.js").is_file());
+
+        // Concurrent runs don't share one.
+        let (_, other) = collider_common::smol::block_on(cmd.app_path()).unwrap();
+        assert_ne!(other.unwrap().path(), launcher.path());
+
+        let path = launcher.path().to_owned();
+        drop(launcher);
+        assert!(!path.exists());
+    }
+
+    /// Runs the generated main.js under Node, with a stand-in `electron`
+    /// module that prints where each file request gets sent.
+    #[cfg(unix)]
+    #[test]
+    fn html_launcher_reroutes_missing_files_into_base() {
+        if std::process::Command::new("node")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            return;
         }
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().canonicalize().unwrap();
+        let cmd = html_start_cmd(&dir);
+        let (_, launcher) = collider_common::smol::block_on(cmd.app_path()).unwrap();
+        let launcher = launcher.unwrap();
+        let stub = launcher.path().join("node_modules").join("electron");
+        std::fs::create_dir_all(&stub).unwrap();
+        std::fs::write(
+            stub.join("index.js"),
+            r#"const { pathToFileURL } = require("url");
+let handler;
+module.exports = {
+  app: { whenReady: () => Promise.resolve(), on() {} },
+  protocol: { interceptFileProtocol: (_scheme, h) => { handler = h; } },
+  BrowserWindow: class {
+    loadFile() {
+      for (const p of JSON.parse(process.env.REQUESTS)) {
+        handler({ url: pathToFileURL(p).href }, (res) => console.log(res.path));
+      }
+    }
+  },
+};
+"#,
+        )
+        .unwrap();
+
+        let base = dir.join("public");
+        let sibling = dir.join("public2").join("app.js");
+        let entry = dir.join("index.html");
+        let next_to_entry = dir.join("style.css");
+        std::fs::write(&next_to_entry, "").unwrap();
+        let requests = vec![
+            entry.clone(),
+            next_to_entry.clone(),
+            base.join("app.js"),
+            sibling.clone(),
+            PathBuf::from("/assets/app.js"),
+        ];
+        let output = std::process::Command::new("node")
+            .arg("main.js")
+            .current_dir(launcher.path())
+            .env("REQUESTS", serde_json::to_string(&requests).unwrap())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let routed = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(
+            routed.lines().map(PathBuf::from).collect::<Vec<_>>(),
+            vec![
+                entry,
+                next_to_entry,
+                base.join("app.js"),
+                base.join(sibling.strip_prefix("/").unwrap()),
+                base.join("assets").join("app.js"),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stdin_is_forwarded() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("electron");
+        let received = dir.path().join("received");
+        std::fs::write(&exe, format!("#!/bin/sh\ncat > '{}'\n", received.display())).unwrap();
+        std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let input = dir.path().join("input");
+        std::fs::write(&input, "hello from stdin\n").unwrap();
+
+        let cmd = StartCmd::try_parse_from(["start"]).unwrap();
+        let stdin = Stdio::from(std::fs::File::open(&input).unwrap());
+        collider_common::smol::block_on(cmd.exec_electron(&exe, ".".into(), stdin)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&received).unwrap(),
+            "hello from stdin\n"
+        );
+    }
+
+    #[test]
+    fn electron_args_append_to_config() {
+        let args = ["start", "--electron-arg", "--from-cli"];
+        let matches = StartCmd::into_app().get_matches_from(args);
+        let mut cmd = StartCmd::try_parse_from(args).unwrap();
+        let mut config = ColliderConfig::new();
+        config
+            .set("electron_args", vec!["--from-config", "--also-from-config"])
+            .unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+        assert_eq!(
+            cmd.electron_args,
+            vec!["--from-config", "--also-from-config", "--from-cli"]
+        );
+    }
+
+    #[test]
+    fn bool_flags_default_from_config() {
+        let args = ["start"];
+        let matches = StartCmd::into_app().get_matches_from(args);
+        let mut cmd = StartCmd::try_parse_from(args).unwrap();
+        let mut config = ColliderConfig::new();
+        config.set("no_stdin", true).unwrap();
+        config.set("start.print_command", "yes").unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+        assert!(cmd.no_stdin);
+        assert!(cmd.print_command);
+
+        config.set("dry_run", "nope").unwrap();
+        let mut cmd = StartCmd::try_parse_from(args).unwrap();
+        assert!(cmd.layer_config(&matches, &config).is_err());
+    }
+
+    #[test]
+    fn multi_word_flags_beat_config() {
+        let mut config = ColliderConfig::new();
+        config.set("include_prerelease", false).unwrap();
+        config.set("base_href", "from-config").unwrap();
+
+        let args = ["start", "--include-prerelease", "--base-href", "cli"];
+        let matches = StartCmd::into_app().get_matches_from(args);
+        let mut cmd = StartCmd::try_parse_from(args).unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+        assert!(cmd.include_prerelease);
+        assert_eq!(cmd.base_href, Some("cli".into()));
+
+        let args = ["start"];
+        let matches = StartCmd::into_app().get_matches_from(args);
+        let mut cmd = StartCmd::try_parse_from(args).unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+        assert_eq!(cmd.base_href, Some("from-config".into()));
+    }
+
+    #[test]
+    fn inspect_flags() {
+        let argv = |args: &[&str]| {
+            StartCmd::try_parse_from(args)
+                .map(|cmd| cmd.electron_argv("app".into()))
+                .map_err(|_| ())
+        };
+        assert_eq!(
+            argv(&["start", "--inspect", "app"]),
+            Ok(vec!["--inspect=5858".into(), "app".into()])
+        );
+        assert_eq!(
+            argv(&["start", "--inspect-brk=9229", "app"]),
+            Ok(vec!["--inspect-brk=9229".into(), "app".into()])
+        );
+        assert!(argv(&["start", "--inspect", "--inspect-brk", "app"]).is_err());
+        assert!(argv(&["start", "--inspect=nope", "app"]).is_err());
+    }
+
+    #[test]
+    fn trailing_args_follow_the_app_path() {
+        let cmd = StartCmd::try_parse_from([
+            "start",
+            "--trace-warnings",
+            "app",
+            "--",
+            "--enable-logging",
+            "--my-flag=foo",
+        ])
+        .unwrap();
+        assert_eq!(
+            cmd.electron_argv("app".into()),
+            vec![
+                "--trace-warnings",
+                "app",
+                "--enable-logging",
+                "--my-flag=foo"
+            ]
+        );
+
+        let cmd = StartCmd::try_parse_from(["start", "-i", "app", "--", "--my-flag"]).unwrap();
+        assert_eq!(
+            cmd.electron_argv("app".into()),
+            vec!["--interactive", "app", "--my-flag"]
+        );
+        let cmd = StartCmd::try_parse_from(["start", "--abi", "app", "--", "--my-flag"]).unwrap();
+        assert_eq!(cmd.electron_argv("app".into()), vec!["--abi"]);
+    }
+
+    #[test]
+    fn start_section_beats_top_level_config() {
+        let args = ["start"];
+        let matches = StartCmd::into_app().get_matches_from(args);
+        let mut cmd = StartCmd::try_parse_from(args).unwrap();
+        let mut config = ColliderConfig::new();
+        config.set("using", "^13").unwrap();
+        config.set("start.using", "^15").unwrap();
+        config.set("start.path", "app").unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+        assert_eq!(cmd.using, "^15");
+        assert_eq!(cmd.path, "app");
+
+        let args = ["start", "--using", "^16", "other-app"];
+        let matches = StartCmd::into_app().get_matches_from(args);
+        let mut cmd = StartCmd::try_parse_from(args).unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+        assert_eq!(cmd.using, "^16");
+        assert_eq!(cmd.path, "other-app");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn configured_app_args_and_env_reach_electron() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("electron");
+        let received = dir.path().join("received");
+        std::fs::write(
+            &exe,
+            format!(
+                "#!/bin/sh\nprintf '%s\\n' \"$GREETING\" \"$@\" > '{}'\n",
+                received.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let args = ["start", "--", "--from-cli"];
+        let matches = StartCmd::into_app().get_matches_from(args);
+        let mut cmd = StartCmd::try_parse_from(args).unwrap();
+        let mut config = ColliderConfig::new();
+        config.set("start.app_args", vec!["--from-config"]).unwrap();
+        config.set("start.env", vec!["GREETING=hi"]).unwrap();
+        cmd.layer_config(&matches, &config).unwrap();
+
+        collider_common::smol::block_on(cmd.exec_electron(&exe, ".".into(), Stdio::null()))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&received).unwrap(),
+            "hi\n.\n--from-config\n--from-cli\n"
+        );
     }
 }
@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use collider_common::{
+    miette::{self, Diagnostic},
+    thiserror::{self, Error},
+};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum AsarError {
+    #[error("{0}")]
+    #[diagnostic(code(collider::asar::io_error))]
+    IoError(String, #[source] std::io::Error),
+
+    #[error("{} links to {}, which isn't inside the app being packed.", .link.display(), .target.display())]
+    #[diagnostic(
+        code(collider::asar::link_outside_package),
+        help("An asar can only hold links to its own files. Replace the link with a copy of what it points to.")
+    )]
+    LinkOutsidePackage { link: PathBuf, target: PathBuf },
+
+    #[error("Invalid unpack pattern: {pattern}")]
+    #[diagnostic(
+        code(collider::asar::bad_pattern),
+        help("Patterns are globs like `*.node` or `**/assets/**`.")
+    )]
+    BadPattern {
+        pattern: String,
+        source: globset::Error,
+    },
+
+    #[error("{} changed while it was being packed.", .0.display())]
+    #[diagnostic(
+        code(collider::asar::file_changed),
+        help("Make sure nothing else is writing to the app while it's packed, then try again.")
+    )]
+    FileChanged(PathBuf),
+}
@@ -0,0 +1,424 @@
+//! Writes [asar](https://github.com/electron/asar) archives, the format
+//! Electron loads apps from, without needing Node.
+//!
+//! An asar is a JSON header describing the directory tree, wrapped in a
+//! couple of Chromium "pickles" (length-prefixed, 4-byte aligned buffers),
+//! followed by the contents of every file, back to back. Each file entry in
+//! the header records where its contents start, relative to the end of the
+//! header.
+//!
+//! Files that can't be used from inside an archive, like native modules, can
+//! be left out of it instead. They go in an `.unpacked` directory next to
+//! the archive, and their header entries point Electron there.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use collider_common::{
+    serde_json::{self, json, Map, Value},
+    tracing,
+};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use sha2::{Digest, Sha256};
+
+pub use errors::AsarError;
+
+mod errors;
+
+/// Size of the blocks each file's integrity hashes are taken over. Matches
+/// what the `asar` npm package uses.
+const BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Packs everything under `src` into a new asar at `dest`, like
+/// `asar pack <src> <dest>` would.
+///
+/// Files matching any of the `unpack` globs are copied into
+/// [`unpacked_dir`] instead. Like `asar pack --unpack`, a pattern without a
+/// `/` matches file names anywhere, e.g. `*.node`; others match paths
+/// relative to `src`, e.g. `assets/**`.
+///
+/// Symlinks are kept as links, as long as they point somewhere inside
+/// `src`.
+pub fn pack(src: &Path, dest: &Path, unpack: &[String]) -> Result<(), AsarError> {
+    let root = fs::canonicalize(src).map_err(|e| {
+        AsarError::IoError(format!("Failed to find directory {}", src.display()), e)
+    })?;
+    let unpacked = unpacked_dir(dest);
+    if unpacked.exists() {
+        fs::remove_dir_all(&unpacked).map_err(|e| {
+            AsarError::IoError(format!("Failed to clear out {}", unpacked.display()), e)
+        })?;
+    }
+    let mut packer = Packer {
+        root,
+        unpacked,
+        unpack: Unpack::new(unpack)?,
+        offset: 0,
+        files: Vec::new(),
+    };
+    let tree = packer.dir_entry(&packer.root.clone())?;
+    let files = packer.files;
+    let header = serde_json::to_vec(&tree).expect("BUG: asar headers are always valid JSON");
+    let write_err =
+        |e| AsarError::IoError(format!("Failed to write asar to {}", dest.display()), e);
+    let out = File::create(dest).map_err(write_err)?;
+    let mut out = BufWriter::new(out);
+    write_header(&mut out, &header).map_err(write_err)?;
+    for (path, size) in files {
+        let file = File::open(&path)
+            .map_err(|e| AsarError::IoError(format!("Failed to read {}", path.display()), e))?;
+        // Offsets in the header are already set in stone, so a file that
+        // grew or shrank since it was measured would corrupt everything
+        // after it.
+        let copied = io::copy(&mut file.take(size + 1), &mut out).map_err(write_err)?;
+        if copied != size {
+            return Err(AsarError::FileChanged(path));
+        }
+    }
+    out.flush().map_err(write_err)?;
+    Ok(())
+}
+
+/// Where the files [`pack`] leaves out of the archive at `asar` go. It has
+/// to be shipped right next to the archive.
+pub fn unpacked_dir(asar: &Path) -> PathBuf {
+    let mut dir = asar.as_os_str().to_owned();
+    dir.push(".unpacked");
+    dir.into()
+}
+
+/// Decides which files get left out of the archive.
+struct Unpack {
+    /// Patterns without a `/`, matched against file names.
+    names: GlobSet,
+    /// Everything else, matched against paths inside the archive.
+    paths: GlobSet,
+}
+
+impl Unpack {
+    fn new(patterns: &[String]) -> Result<Self, AsarError> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|source| AsarError::BadPattern {
+                    pattern: pattern.clone(),
+                    source,
+                })?;
+            if pattern.contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+        let build = |set: GlobSetBuilder| {
+            set.build().map_err(|source| AsarError::BadPattern {
+                pattern: patterns.join(", "),
+                source,
+            })
+        };
+        Ok(Unpack {
+            names: build(names)?,
+            paths: build(paths)?,
+        })
+    }
+
+    fn matches(&self, name: &str, path: &str) -> bool {
+        self.names.is_match(name) || self.paths.is_match(path)
+    }
+}
+
+/// State for a single [`pack`] run.
+struct Packer {
+    root: PathBuf,
+    unpacked: PathBuf,
+    unpack: Unpack,
+    /// Where the next packed file's contents will start.
+    offset: u64,
+    /// Every packed file, in the order their contents get written, with the
+    /// size they had when measured.
+    files: Vec<(PathBuf, u64)>,
+}
+
+/// Writes the size pickle, then the header pickle holding `header`.
+fn write_header(out: &mut impl Write, header: &[u8]) -> io::Result<()> {
+    let padded = (header.len() + 3) & !3;
+    // Header pickle: payload size, then the string's length, then the
+    // string itself, padded out to 4 bytes.
+    let header_pickle = 8 + padded;
+    // Size pickle: payload size (always 4), then the header pickle's size.
+    out.write_all(&4u32.to_le_bytes())?;
+    out.write_all(&(header_pickle as u32).to_le_bytes())?;
+    out.write_all(&((4 + padded) as u32).to_le_bytes())?;
+    out.write_all(&(header.len() as u32).to_le_bytes())?;
+    out.write_all(header)?;
+    out.write_all(&[0; 3][..padded - header.len()])
+}
+
+impl Packer {
+    /// Describes the directory `dir` for the header, queueing up its files'
+    /// contents to be written after it, or unpacking them.
+    fn dir_entry(&mut self, dir: &Path) -> Result<Value, AsarError> {
+        let read_err =
+            |path: &Path, e| AsarError::IoError(format!("Failed to read {}", path.display()), e);
+        let mut paths = fs::read_dir(dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .map_err(|e| read_err(dir, e))?;
+        // Keep the layout stable from one run to the next.
+        paths.sort();
+        let mut entries = Map::new();
+        for path in paths {
+            let name = path
+                .file_name()
+                .expect("BUG: directory entries always have names")
+                .to_string_lossy()
+                .into_owned();
+            let meta = fs::symlink_metadata(&path).map_err(|e| read_err(&path, e))?;
+            let entry = if meta.file_type().is_symlink() {
+                link_entry(&self.root, &path)?
+            } else if meta.is_dir() {
+                self.dir_entry(&path)?
+            } else {
+                let integrity = integrity(&path).map_err(|e| read_err(&path, e))?;
+                let mut entry = json!({
+                    "size": meta.len(),
+                    "integrity": integrity,
+                });
+                let relative = archive_path(&self.root, &path);
+                if self.unpack.matches(&name, &relative) {
+                    self.unpack_file(&path, &relative)?;
+                    entry["unpacked"] = true.into();
+                } else {
+                    // A string, because JSON numbers can't hold every u64.
+                    entry["offset"] = self.offset.to_string().into();
+                    self.offset += meta.len();
+                    self.files.push((path, meta.len()));
+                }
+                if is_executable(&meta) {
+                    entry["executable"] = true.into();
+                }
+                entry
+            };
+            entries.insert(name, entry);
+        }
+        Ok(json!({ "files": entries }))
+    }
+
+    /// Copies the file at `path` to `relative` under the unpacked directory.
+    fn unpack_file(&self, path: &Path, relative: &str) -> Result<(), AsarError> {
+        let dest = self.unpacked.join(relative);
+        tracing::debug!("Unpacking {}", relative);
+        fs::create_dir_all(dest.parent().expect("BUG: unpacked files have a parent"))
+            .and_then(|_| fs::copy(path, &dest))
+            .map_err(|e| {
+                AsarError::IoError(
+                    format!("Failed to unpack {} to {}", path.display(), dest.display()),
+                    e,
+                )
+            })?;
+        Ok(())
+    }
+}
+
+/// `path`'s location inside the archive, relative to `root` and always
+/// `/`-separated, the way the header spells it.
+fn archive_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .expect("BUG: packed files live under the root")
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Describes the symlink at `link`, as a path relative to `root`.
+fn link_entry(root: &Path, link: &Path) -> Result<Value, AsarError> {
+    let target = fs::canonicalize(link)
+        .map_err(|e| AsarError::IoError(format!("Failed to follow link {}", link.display()), e))?;
+    if !target.starts_with(root) {
+        return Err(AsarError::LinkOutsidePackage {
+            link: link.to_owned(),
+            target,
+        });
+    }
+    Ok(json!({ "link": archive_path(root, &target) }))
+}
+
+/// The SHA-256 of the whole file at `path`, plus one for each
+/// [`BLOCK_SIZE`] block of it, which Electron can check the file against
+/// when it's loaded.
+fn integrity(path: &Path) -> io::Result<Value> {
+    let mut file = File::open(path)?;
+    let mut whole = Sha256::new();
+    let mut blocks = Vec::new();
+    let mut buf = vec![0; BLOCK_SIZE];
+    loop {
+        let len = fill(&mut file, &mut buf)?;
+        whole.update(&buf[..len]);
+        blocks.push(hex::encode(Sha256::digest(&buf[..len])));
+        if len < BLOCK_SIZE {
+            break;
+        }
+    }
+    Ok(json!({
+        "algorithm": "SHA256",
+        "hash": hex::encode(whole.finalize()),
+        "blockSize": BLOCK_SIZE,
+        "blocks": blocks,
+    }))
+}
+
+/// Reads into `buf` until it's full or `reader` runs out.
+fn fill(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o100 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    /// Reads `asar` back into its header and the data after it.
+    fn read_asar(asar: &Path) -> (Value, Vec<u8>) {
+        let bytes = fs::read(asar).unwrap();
+        let u32_at = |at: usize| {
+            let word = bytes[at..at + 4].try_into().unwrap();
+            u32::from_le_bytes(word) as usize
+        };
+        assert_eq!(u32_at(0), 4);
+        let header_pickle = u32_at(4);
+        let header_len = u32_at(12);
+        let header = serde_json::from_slice(&bytes[16..16 + header_len]).unwrap();
+        (header, bytes[8 + header_pickle..].to_vec())
+    }
+
+    fn contents<'a>(data: &'a [u8], entry: &Value) -> &'a [u8] {
+        let offset: usize = entry["offset"].as_str().unwrap().parse().unwrap();
+        let size = entry["size"].as_u64().unwrap() as usize;
+        &data[offset..offset + size]
+    }
+
+    #[test]
+    fn packs_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("app");
+        fs::create_dir_all(src.join("lib")).unwrap();
+        fs::write(src.join("package.json"), r#"{"main": "lib/index.js"}"#).unwrap();
+        fs::write(src.join("lib").join("index.js"), "console.log('hi')").unwrap();
+        fs::write(src.join("empty"), "").unwrap();
+        let asar = dir.path().join("app.asar");
+
+        pack(&src, &asar, &[]).unwrap();
+
+        let (header, data) = read_asar(&asar);
+        let files = &header["files"];
+        assert_eq!(
+            contents(&data, &files["package.json"]),
+            br#"{"main": "lib/index.js"}"#
+        );
+        let index = &files["lib"]["files"]["index.js"];
+        assert_eq!(contents(&data, index), b"console.log('hi')");
+        assert_eq!(
+            index["integrity"]["hash"],
+            hex::encode(Sha256::digest(b"console.log('hi')"))
+        );
+        assert_eq!(index["integrity"]["blocks"].as_array().unwrap().len(), 1);
+        assert_eq!(files["empty"]["size"], 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_stay_inside() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("app");
+        fs::create_dir_all(src.join("node_modules").join(".bin")).unwrap();
+        fs::write(src.join("cli.js"), "").unwrap();
+        std::os::unix::fs::symlink("../../cli.js", src.join("node_modules/.bin/cli")).unwrap();
+        let asar = dir.path().join("app.asar");
+
+        pack(&src, &asar, &[]).unwrap();
+        let (header, _) = read_asar(&asar);
+        assert_eq!(
+            header["files"]["node_modules"]["files"][".bin"]["files"]["cli"],
+            json!({ "link": "cli.js" })
+        );
+
+        fs::write(dir.path().join("secret"), "").unwrap();
+        std::os::unix::fs::symlink("../secret", src.join("secret")).unwrap();
+        assert!(matches!(
+            pack(&src, &asar, &[]),
+            Err(AsarError::LinkOutsidePackage { .. })
+        ));
+    }
+
+    #[test]
+    fn unpacks_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("app");
+        let addon = src.join("node_modules").join("addon").join("build");
+        fs::create_dir_all(&addon).unwrap();
+        fs::create_dir_all(src.join("assets")).unwrap();
+        fs::write(addon.join("addon.node"), "native").unwrap();
+        fs::write(src.join("assets").join("icon.png"), "png").unwrap();
+        fs::write(src.join("index.js"), "js").unwrap();
+        let asar = dir.path().join("app.asar");
+        // Left over from an earlier pack.
+        fs::create_dir_all(unpacked_dir(&asar).join("stale")).unwrap();
+
+        let unpack = vec!["*.node".to_owned(), "assets/**".to_owned()];
+        pack(&src, &asar, &unpack).unwrap();
+
+        let (header, data) = read_asar(&asar);
+        let files = &header["files"];
+        let addon =
+            &files["node_modules"]["files"]["addon"]["files"]["build"]["files"]["addon.node"];
+        assert_eq!(addon["unpacked"], true);
+        assert!(addon.get("offset").is_none());
+        assert_eq!(files["assets"]["files"]["icon.png"]["unpacked"], true);
+        assert_eq!(contents(&data, &files["index.js"]), b"js");
+        assert_eq!(data.len(), 2);
+
+        let unpacked = unpacked_dir(&asar);
+        assert_eq!(unpacked, dir.path().join("app.asar.unpacked"));
+        assert_eq!(
+            fs::read_to_string(unpacked.join("node_modules/addon/build/addon.node")).unwrap(),
+            "native"
+        );
+        assert!(unpacked.join("assets").join("icon.png").is_file());
+        assert!(!unpacked.join("index.js").exists());
+        assert!(!unpacked.join("stale").exists());
+
+        assert!(matches!(
+            pack(&src, &asar, &["[".to_owned()]),
+            Err(AsarError::BadPattern { .. })
+        ));
+    }
+}
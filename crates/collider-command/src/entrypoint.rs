@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+
+use collider_common::serde_json;
+
+/// The `main` field of a package.json, or Node's `index.js` default if it
+/// doesn't have one.
+pub fn package_main(pkg: &serde_json::Value) -> &str {
+    pkg["main"].as_str().unwrap_or("index.js")
+}
+
+/// The file `require(main)` would load from `dir`, going through the same
+/// lookups Node does: `main` itself, then with a `.js` extension, then as a
+/// directory with an index.js in it. `None` if none of those exist.
+pub fn resolve_main(dir: &Path, main: &str) -> Option<PathBuf> {
+    let entry = dir.join(main);
+    // Node appends `.js` to the whole name, so `main.prod` is `main.prod.js`.
+    let mut with_js = entry.clone().into_os_string();
+    with_js.push(".js");
+    [
+        entry.clone(),
+        PathBuf::from(with_js),
+        entry.join("index.js"),
+    ]
+    .iter()
+    .find(|candidate| candidate.is_file())
+    .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn main_resolves_like_node() {
+        let dir = tempfile::tempdir().unwrap();
+        let pkg = serde_json::json!({});
+        assert_eq!(package_main(&pkg), "index.js");
+        assert_eq!(resolve_main(dir.path(), package_main(&pkg)), None);
+
+        std::fs::write(dir.path().join("main.js"), "").unwrap();
+        assert_eq!(
+            resolve_main(dir.path(), "main"),
+            Some(dir.path().join("main.js"))
+        );
+
+        std::fs::write(dir.path().join("main.prod.js"), "").unwrap();
+        assert_eq!(
+            resolve_main(dir.path(), "main.prod"),
+            Some(dir.path().join("main.prod.js"))
+        );
+
+        std::fs::create_dir(dir.path().join("app")).unwrap();
+        std::fs::write(dir.path().join("app").join("index.js"), "").unwrap();
+        let pkg = serde_json::json!({"main": "app"});
+        assert_eq!(
+            resolve_main(dir.path(), package_main(&pkg)),
+            Some(dir.path().join("app").join("index.js"))
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use collider_common::{
+    miette::{self, Diagnostic},
+    thiserror::{self, Error},
+};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ToolError {
+    #[error("Could not find `{tool}`, which collider needs for {needed_for}.")]
+    #[diagnostic(
+        code(collider::tool_not_found),
+        help(
+            "Make sure it's installed and available on your PATH. npm and npx come with Node.js."
+        )
+    )]
+    NotFound {
+        tool: String,
+        needed_for: String,
+        source: which::Error,
+    },
+
+    #[error("Failed to run `{command}`.")]
+    #[diagnostic(code(collider::tool_spawn_failed))]
+    SpawnFailed {
+        command: String,
+        source: std::io::Error,
+    },
+
+    #[error("`{command}` failed ({status}).")]
+    #[diagnostic(
+        code(collider::tool_failed),
+        help("Its output is shown above, unless `--quiet` was passed. Use `--verbose-child` to stream it live instead.")
+    )]
+    Failed {
+        command: String,
+        status: std::process::ExitStatus,
+    },
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum WebhookError {
+    #[error("Invalid webhook URL: {0}")]
+    #[diagnostic(
+        code(collider::webhook::invalid_url),
+        help("Webhook URLs must be absolute, e.g. `https://example.com/hooks/collider`.")
+    )]
+    InvalidUrl(String, #[source] url::ParseError),
+
+    #[error("Invalid webhook header: {0}")]
+    #[diagnostic(
+        code(collider::webhook::invalid_header),
+        help("Webhook headers must look like `Name: value`, e.g. `--webhook-header \"Authorization: Bearer <token>\"`.")
+    )]
+    InvalidHeader(String),
+
+    #[error(transparent)]
+    #[diagnostic(code(collider::webhook::electron_error))]
+    ElectronError(#[from] collider_electron::ElectronError),
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum PackageManagerError {
+    #[error("Unknown package manager: {0}")]
+    #[diagnostic(
+        code(collider::package_manager::unknown),
+        help("Supported package managers are npm, yarn, and pnpm.")
+    )]
+    Unknown(String),
+
+    #[error("Can't tell which package manager {} uses: found lockfiles for {}.", .dir.display(), .found.join(" and "))]
+    #[diagnostic(
+        code(collider::package_manager::ambiguous),
+        help(
+            "Pass `--package-manager <npm|yarn|pnpm>` to pick one, or delete the stale lockfile."
+        )
+    )]
+    Ambiguous {
+        dir: std::path::PathBuf,
+        found: Vec<String>,
+    },
+}
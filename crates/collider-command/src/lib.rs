@@ -7,6 +7,22 @@ pub use collider_config;
 pub use owo_colors;
 pub use tracing;
 
+pub use entrypoint::{package_main, resolve_main};
+pub use errors::{PackageManagerError, ToolError, WebhookError};
+pub use package_manager::{PackageManager, PACKAGE_MANAGERS};
+pub use prompt::ConfirmRetry;
+pub use tools::{
+    format_command_line, run_tool, shell_quote, tool_command, which_tool, ChildOutput,
+};
+pub use webhook::{Artifact, ColliderEvent, Webhook};
+
+mod entrypoint;
+mod errors;
+mod package_manager;
+mod prompt;
+mod tools;
+mod webhook;
+
 #[async_trait::async_trait]
 pub trait ColliderCommand {
     async fn execute(self) -> Result<()>;
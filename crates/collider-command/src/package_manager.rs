@@ -0,0 +1,204 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::errors::PackageManagerError;
+
+/// Names accepted by `--package-manager`.
+pub const PACKAGE_MANAGERS: &[&str] = &["npm", "yarn", "pnpm"];
+
+/// The package manager a project is managed with. This decides which tool
+/// collider runs to pack up and install a project, and with what arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Npm,
+    Yarn,
+    Pnpm,
+}
+
+impl PackageManager {
+    /// Figures out which package manager `dir` uses from the lockfile in it.
+    /// Returns `None` if there isn't one, and errors if there's more than
+    /// one, since then there's no telling which is current.
+    pub fn detect(dir: &Path) -> Result<Option<Self>, PackageManagerError> {
+        let found = [
+            ("package-lock.json", PackageManager::Npm),
+            ("yarn.lock", PackageManager::Yarn),
+            ("pnpm-lock.yaml", PackageManager::Pnpm),
+        ]
+        .iter()
+        .filter(|(lockfile, _)| dir.join(lockfile).is_file())
+        .map(|(_, pm)| *pm)
+        .collect::<Vec<_>>();
+        match found.as_slice() {
+            [] => Ok(None),
+            [pm] => Ok(Some(*pm)),
+            _ => Err(PackageManagerError::Ambiguous {
+                dir: dir.to_owned(),
+                found: found.iter().map(|pm| pm.to_string()).collect(),
+            }),
+        }
+    }
+
+    /// Picks the package manager for `dir`: `requested`, if given, then
+    /// whatever its lockfile says, then npm.
+    pub fn resolve(requested: Option<&str>, dir: &Path) -> Result<Self, PackageManagerError> {
+        match requested {
+            Some(pm) => pm.parse(),
+            None => Ok(Self::detect(dir)?.unwrap_or(PackageManager::Npm)),
+        }
+    }
+
+    /// The first of npm, yarn, and pnpm (in that order) that's installed,
+    /// for when there's no project to detect one from yet.
+    pub fn on_path() -> Option<Self> {
+        [
+            PackageManager::Npm,
+            PackageManager::Yarn,
+            PackageManager::Pnpm,
+        ]
+        .iter()
+        .copied()
+        .find(|pm| which::which(pm.as_str()).is_ok())
+    }
+
+    /// The executable to run.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Pnpm => "pnpm",
+        }
+    }
+
+    /// Arguments that pack the project in the working directory into a
+    /// tarball. See [`PackageManager::packed_tarball`] for where it ends up.
+    pub fn pack_args(&self) -> Vec<OsString> {
+        vec!["pack".into()]
+    }
+
+    /// Arguments that install only a project's production dependencies.
+    pub fn install_production_args(&self) -> Vec<OsString> {
+        match self {
+            PackageManager::Npm | PackageManager::Yarn => {
+                vec!["install".into(), "--production".into()]
+            }
+            PackageManager::Pnpm => vec!["install".into(), "--prod".into()],
+        }
+    }
+
+    /// The program to run a one-off npm package with, and its full argument
+    /// list, given `args` that start with the package spec (e.g.
+    /// `@electron/rebuild@3.2.13 --arch x64 ...`).
+    pub fn dlx(&self, args: Vec<OsString>) -> (&'static str, Vec<OsString>) {
+        match self {
+            PackageManager::Pnpm => {
+                let mut dlx = vec![OsString::from("dlx")];
+                dlx.extend(args);
+                ("pnpm", dlx)
+            }
+            // Yarn 1 has no `dlx`, and npx comes with Node either way.
+            PackageManager::Npm | PackageManager::Yarn => ("npx", args),
+        }
+    }
+
+    /// Picks the tarball's path out of what the pack command printed. It's
+    /// relative to the directory the command ran in, unless yarn made it
+    /// absolute.
+    pub fn packed_tarball(&self, stdout: &str) -> Option<String> {
+        match self {
+            // success Wrote tarball to "/path/to/app-v1.0.0.tgz".
+            PackageManager::Yarn => stdout
+                .lines()
+                .find_map(|line| line.split_once("Wrote tarball to \""))
+                .and_then(|(_, rest)| rest.split('"').next())
+                .map(String::from),
+            // npm and pnpm print the tarball's name last, after any notices.
+            PackageManager::Npm | PackageManager::Pnpm => stdout
+                .lines()
+                .map(str::trim)
+                .rfind(|line| !line.is_empty())
+                .map(String::from),
+        }
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PackageManager {
+    type Err = PackageManagerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "npm" => Ok(PackageManager::Npm),
+            "yarn" => Ok(PackageManager::Yarn),
+            "pnpm" => Ok(PackageManager::Pnpm),
+            other => Err(PackageManagerError::Unknown(other.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_from_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(PackageManager::detect(dir.path()).unwrap(), None);
+        assert_eq!(
+            PackageManager::resolve(None, dir.path()).unwrap(),
+            PackageManager::Npm
+        );
+
+        std::fs::write(dir.path().join("yarn.lock"), "").unwrap();
+        assert_eq!(
+            PackageManager::detect(dir.path()).unwrap(),
+            Some(PackageManager::Yarn)
+        );
+
+        std::fs::write(dir.path().join("pnpm-lock.yaml"), "").unwrap();
+        assert!(matches!(
+            PackageManager::detect(dir.path()),
+            Err(PackageManagerError::Ambiguous { .. })
+        ));
+        assert_eq!(
+            PackageManager::resolve(Some("pnpm"), dir.path()).unwrap(),
+            PackageManager::Pnpm
+        );
+    }
+
+    #[test]
+    fn one_off_packages() {
+        let args = || vec![OsString::from("asar@3.1.0"), OsString::from("pack")];
+        assert_eq!(PackageManager::Npm.dlx(args()), ("npx", args()));
+        assert_eq!(PackageManager::Yarn.dlx(args()), ("npx", args()));
+        assert_eq!(
+            PackageManager::Pnpm.dlx(args()),
+            (
+                "pnpm",
+                vec!["dlx".into(), "asar@3.1.0".into(), "pack".into()]
+            )
+        );
+    }
+
+    #[test]
+    fn finds_packed_tarball() {
+        assert_eq!(
+            PackageManager::Npm.packed_tarball("npm notice\napp-1.0.0.tgz\n"),
+            Some("app-1.0.0.tgz".into())
+        );
+        assert_eq!(
+            PackageManager::Yarn.packed_tarball(
+                "yarn pack v1.22.10\nsuccess Wrote tarball to \"/my app/app-v1.0.0.tgz\".\n"
+            ),
+            Some("/my app/app-v1.0.0.tgz".into())
+        );
+        assert_eq!(PackageManager::Pnpm.packed_tarball(""), None);
+    }
+}
@@ -0,0 +1,31 @@
+use collider_electron::{ElectronError, RetryPrompt};
+use dialoguer::{console, theme::ColorfulTheme, Confirm};
+
+/// Asks on the terminal whether to retry a failed network request.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConfirmRetry;
+
+impl ConfirmRetry {
+    /// A prompt, but only if there's a human around to answer it: stderr
+    /// has to be a terminal, and neither `--quiet` nor `--json` was passed.
+    /// Otherwise, callers should fall back to automatic retries.
+    pub fn if_interactive(quiet: bool, json: bool) -> Option<Self> {
+        if quiet || json || !console::user_attended_stderr() {
+            None
+        } else {
+            Some(ConfirmRetry)
+        }
+    }
+}
+
+impl RetryPrompt for ConfirmRetry {
+    fn should_retry(&self, error: &ElectronError) -> bool {
+        eprintln!("{}", error);
+        Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Network request failed — retry?")
+            .default(true)
+            .interact()
+            // If we can't even ask, don't loop.
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,235 @@
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Output, Stdio};
+
+use collider_common::smol::{
+    future,
+    io::{AsyncRead, AsyncReadExt},
+    process::Command,
+};
+
+use crate::errors::ToolError;
+
+/// Finds `name` on `PATH` and returns a [`Command`] ready to have arguments
+/// added to it. See [`tool_command`].
+pub fn which_tool(name: &str) -> Result<Command, ToolError> {
+    let path = which::which(name).map_err(|e| ToolError::NotFound {
+        tool: name.into(),
+        needed_for: needed_for(name).into(),
+        source: e,
+    })?;
+    Ok(tool_command(&path))
+}
+
+/// A [`Command`] for the tool at `path`. On Windows, tools like `npm` are
+/// `.cmd` shims that can't be spawned directly, so they get wrapped in
+/// `cmd /c`.
+pub fn tool_command(path: &Path) -> Command {
+    // TODO: For npm/npx, find and run npm-cli.js directly instead. This will
+    // help bypass the Terminate Batch Job b.s. on Windows.
+    if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/c");
+        cmd.arg(path);
+        cmd
+    } else {
+        Command::new(path)
+    }
+}
+
+fn needed_for(name: &str) -> &'static str {
+    match name {
+        "npm" | "npx" | "yarn" | "pnpm" => {
+            "installing, rebuilding, and packing your app's dependencies"
+        }
+        "git" => "initializing new projects",
+        "strip" => "stripping debug symbols from Linux binaries",
+        _ => "this command",
+    }
+}
+
+/// Quotes `arg` so it can be pasted into a shell as a single argument. Args
+/// that don't need quoting are left alone, to keep printed commands readable.
+pub fn shell_quote(arg: &OsStr) -> Cow<'_, str> {
+    let arg = arg.to_string_lossy();
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:@,+%^".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg
+    } else if cfg!(target_os = "windows") {
+        format!("\"{}\"", arg.replace('"', "\\\"")).into()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''")).into()
+    }
+}
+
+/// Renders a command the way someone would type it into a shell: `cd` into
+/// its working directory (if any), then env vars, the program, and its args,
+/// all quoted as needed.
+pub fn format_command_line<A: AsRef<OsStr>>(
+    cwd: Option<&Path>,
+    env: &[(&str, &OsStr)],
+    program: &OsStr,
+    args: &[A],
+) -> String {
+    let mut parts = Vec::new();
+    if let Some(cwd) = cwd {
+        parts.push(format!("cd {} &&", shell_quote(cwd.as_os_str())));
+    }
+    for (key, val) in env {
+        parts.push(format!("{}={}", key, shell_quote(val)));
+    }
+    parts.push(shell_quote(program).into_owned());
+    parts.extend(
+        args.iter()
+            .map(|arg| shell_quote(arg.as_ref()).into_owned()),
+    );
+    parts.join(" ")
+}
+
+/// How much of an external tool's output collider shows while running it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildOutput {
+    /// Stream everything live, as the tool writes it.
+    Stream,
+    /// Capture everything, and only dump it if the tool fails.
+    OnFailure,
+    /// Capture everything and never show it.
+    Silent,
+}
+
+impl ChildOutput {
+    /// Picks the output mode for a command's `--verbose-child` and `--quiet`
+    /// flags. `--quiet` wins, since it means no output at all.
+    pub fn from_flags(verbose_child: bool, quiet: bool) -> Self {
+        if quiet {
+            ChildOutput::Silent
+        } else if verbose_child {
+            ChildOutput::Stream
+        } else {
+            ChildOutput::OnFailure
+        }
+    }
+}
+
+/// Runs an external tool to completion, handling its output according to
+/// `output`. Stdout is always captured and returned (even when streaming),
+/// since some callers need to parse it. `command_line` is only used for
+/// error messages, and should be what [`format_command_line`] printed for it.
+pub async fn run_tool(
+    mut cmd: Command,
+    command_line: &str,
+    output: ChildOutput,
+) -> Result<Output, ToolError> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| ToolError::SpawnFailed {
+            command: command_line.into(),
+            source: e,
+        })?;
+    let stream = output == ChildOutput::Stream;
+    let stdout = child.stdout.take().expect("BUG: stdout should be piped");
+    let stderr = child.stderr.take().expect("BUG: stderr should be piped");
+    let (stdout, stderr) = future::try_zip(
+        tee(stdout, stream.then(std::io::stdout)),
+        tee(stderr, stream.then(std::io::stderr)),
+    )
+    .await
+    .map_err(|e| ToolError::SpawnFailed {
+        command: command_line.into(),
+        source: e,
+    })?;
+    let status = child.status().await.map_err(|e| ToolError::SpawnFailed {
+        command: command_line.into(),
+        source: e,
+    })?;
+    if !status.success() {
+        if output == ChildOutput::OnFailure {
+            let mut err = std::io::stderr();
+            // Best-effort: there's nowhere else to report these to.
+            let _ = err.write_all(&stdout);
+            let _ = err.write_all(&stderr);
+        }
+        return Err(ToolError::Failed {
+            command: command_line.into(),
+            status,
+        });
+    }
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Reads `reader` to the end, echoing each chunk to `echo` (if any) as it
+/// arrives.
+async fn tee<R, W>(mut reader: R, mut echo: Option<W>) -> std::io::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+    W: Write,
+{
+    let mut captured = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(captured);
+        }
+        if let Some(echo) = &mut echo {
+            echo.write_all(&buf[..read])?;
+            echo.flush()?;
+        }
+        captured.extend_from_slice(&buf[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_tool() {
+        let err = which_tool("collider-definitely-not-a-real-tool").unwrap_err();
+        assert!(
+            matches!(err, ToolError::NotFound { ref tool, .. } if tool == "collider-definitely-not-a-real-tool")
+        );
+        assert!(err.to_string().contains("needs for this command"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_lines_are_quoted() {
+        let line = format_command_line(
+            Some(Path::new("/my app")),
+            &[("FOO", OsStr::new("a b"))],
+            OsStr::new("npx"),
+            &["asar@3.1.0", "pack", "it's here", ""],
+        );
+        assert_eq!(
+            line,
+            r#"cd '/my app' && FOO='a b' npx asar@3.1.0 pack 'it'\''s here' ''"#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_tool_reports_command() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo packed.tgz; exit 3"]);
+        let err = collider_common::smol::block_on(run_tool(cmd, "npm pack", ChildOutput::Silent))
+            .unwrap_err();
+        assert!(matches!(err, ToolError::Failed { ref command, .. } if command == "npm pack"));
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo packed.tgz"]);
+        let output =
+            collider_common::smol::block_on(run_tool(cmd, "npm pack", ChildOutput::Stream))
+                .unwrap();
+        assert_eq!(output.stdout, b"packed.tgz\n");
+    }
+}
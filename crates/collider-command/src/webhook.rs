@@ -0,0 +1,336 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_compat::CompatExt;
+use collider_common::{
+    serde::Serialize,
+    serde_json,
+    smol::{self, fs, Task},
+    tracing,
+};
+use collider_electron::{build_http_client, ElectronEvent, ElectronInfo, Url};
+use sha2::{Digest, Sha256};
+
+use crate::errors::WebhookError;
+
+/// Structured events collider reports about a run. These serialize to JSON
+/// objects tagged with an `event` field, e.g. `{"event": "resolved", ...}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ColliderEvent {
+    /// A command settled on an Electron build to use, downloading it first
+    /// if needed.
+    Resolved {
+        command: String,
+        electron: ElectronInfo,
+    },
+    /// A command's download of the Electron build for `triple` got
+    /// `percent` of the way done. Sent at 0, 25, 50, 75 and 100%, or just 0
+    /// and 100% if the server didn't say how big the download is.
+    DownloadProgress {
+        command: String,
+        triple: String,
+        percent: u8,
+    },
+    /// `collider pack` finished writing its output.
+    PackComplete {
+        electron: ElectronInfo,
+        output: PathBuf,
+        artifacts: Vec<Artifact>,
+    },
+}
+
+/// A file produced by a command, along with enough information to check that
+/// what got uploaded somewhere is what collider actually wrote.
+#[derive(Debug, Clone, Serialize)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl Artifact {
+    pub async fn from_path(path: &Path) -> std::io::Result<Self> {
+        let data = fs::read(path).await?;
+        let size = data.len() as u64;
+        let sha256 = smol::unblock(move || hex::encode(Sha256::digest(&data))).await;
+        Ok(Artifact {
+            path: path.to_owned(),
+            size,
+            sha256,
+        })
+    }
+}
+
+/// Somewhere to POST [`ColliderEvent`]s to, configured with `--webhook` and
+/// `--webhook-header`.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    url: Url,
+    header: Option<(String, String)>,
+    client: reqwest::Client,
+    /// The last of the events queued up by [`Self::download_milestones`],
+    /// which waits on all the ones before it.
+    queued: Arc<Mutex<Option<Task<()>>>>,
+}
+
+impl Webhook {
+    pub fn new(url: &str, header: Option<&str>) -> Result<Self, WebhookError> {
+        let url = Url::parse(url).map_err(|e| WebhookError::InvalidUrl(url.into(), e))?;
+        let header = header.map(parse_header).transpose()?;
+        Ok(Webhook {
+            url,
+            header,
+            client: build_http_client(None, None)?,
+            queued: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Convenience for commands, which carry the (optional) global flags
+    /// around directly.
+    pub fn from_opts(
+        url: Option<&str>,
+        header: Option<&str>,
+    ) -> Result<Option<Self>, WebhookError> {
+        url.map(|url| Webhook::new(url, header)).transpose()
+    }
+
+    /// Delivers `event`, after any download milestones that are still on
+    /// their way. Webhooks are purely informational, so failures are logged
+    /// as warnings instead of failing whatever command sent them.
+    pub async fn send(&self, event: &ColliderEvent) {
+        let queued = self
+            .queued
+            .lock()
+            .expect("BUG: webhook lock poisoned")
+            .take();
+        if let Some(queued) = queued {
+            queued.await;
+        }
+        self.deliver(event).await;
+    }
+
+    /// A hook for [`ElectronOpts::on_event`](collider_electron::ElectronOpts::on_event)
+    /// that reports [`ColliderEvent::DownloadProgress`] milestones for
+    /// `command`. They go out in the background, in order, without holding
+    /// up the download.
+    pub fn download_milestones(
+        &self,
+        command: &str,
+    ) -> impl Fn(ElectronEvent) + Send + Sync + 'static {
+        let webhook = self.clone();
+        let command = command.to_owned();
+        // The download in progress, and the last milestone reported for it.
+        let current = Mutex::new(None::<(String, u8)>);
+        move |event| {
+            let mut current = current.lock().expect("BUG: webhook lock poisoned");
+            let percent = match (event, current.as_mut()) {
+                (ElectronEvent::DownloadStarted { triple, .. }, _) => {
+                    *current = Some((triple, 0));
+                    Some(0)
+                }
+                (ElectronEvent::DownloadProgress { done, total }, Some((_, last))) => {
+                    let percent = next_milestone(done, total, *last);
+                    if let Some(percent) = percent {
+                        *last = percent;
+                    }
+                    percent
+                }
+                (ElectronEvent::Extracting, Some(_)) => Some(100),
+                _ => None,
+            };
+            if let (Some(percent), Some((triple, _))) = (percent, current.as_ref()) {
+                webhook.queue(ColliderEvent::DownloadProgress {
+                    command: command.clone(),
+                    triple: triple.clone(),
+                    percent,
+                });
+            }
+        }
+    }
+
+    fn queue(&self, event: ColliderEvent) {
+        let mut queued = self.queued.lock().expect("BUG: webhook lock poisoned");
+        let before = queued.take();
+        let webhook = self.clone();
+        *queued = Some(smol::spawn(async move {
+            if let Some(before) = before {
+                before.await;
+            }
+            webhook.deliver(&event).await;
+        }));
+    }
+
+    async fn deliver(&self, event: &ColliderEvent) {
+        if let Err(e) = self.try_send(event).await {
+            tracing::warn!("Failed to deliver webhook to {}: {}", self.url, e);
+        }
+    }
+
+    async fn try_send(
+        &self,
+        event: &ColliderEvent,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut req = self
+            .client
+            .post(self.url.clone())
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_vec(event)?);
+        if let Some((name, value)) = &self.header {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        // reqwest needs Tokio for the whole request, timeouts included.
+        let res = async { req.send().await }.compat().await?;
+        if !res.status().is_success() {
+            return Err(format!("webhook responded with {}", res.status()).into());
+        }
+        Ok(())
+    }
+}
+
+/// The 25% step `done` of `total` bytes has reached, if it's past `last`.
+/// 100% is left for when the download's actually finished.
+fn next_milestone(done: u64, total: Option<u64>, last: u8) -> Option<u8> {
+    let total = total.filter(|total| *total > 0)?;
+    let percent = (done.min(total) * 100 / total) as u8 / 25 * 25;
+    if percent > last && percent < 100 {
+        Some(percent)
+    } else {
+        None
+    }
+}
+
+fn parse_header(header: &str) -> Result<(String, String), WebhookError> {
+    match header.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().into(), value.trim().into()))
+        }
+        _ => Err(WebhookError::InvalidHeader(header.into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_parsing() {
+        assert_eq!(
+            parse_header("Authorization: Bearer abc:123").unwrap(),
+            ("Authorization".into(), "Bearer abc:123".into())
+        );
+        assert!(parse_header("Authorization").is_err());
+        assert!(parse_header(": nope").is_err());
+    }
+
+    #[test]
+    fn milestones_every_quarter() {
+        let total = Some(400);
+        assert_eq!(next_milestone(99, total, 0), None);
+        assert_eq!(next_milestone(100, total, 0), Some(25));
+        assert_eq!(next_milestone(150, total, 25), None);
+        // A big chunk can skip right past a milestone.
+        assert_eq!(next_milestone(320, total, 25), Some(75));
+        assert_eq!(next_milestone(400, total, 75), None);
+        assert_eq!(next_milestone(100, None, 0), None);
+    }
+
+    /// Accepts `requests` POSTs, answering each with a 204. Returns the
+    /// server's URL and the JSON bodies, in the order they arrived.
+    fn record_posts(requests: usize) -> (String, std::thread::JoinHandle<Vec<serde_json::Value>>) {
+        use std::io::{BufRead, BufReader, Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut len = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("content-length:") {
+                        len = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(serde_json::from_slice(&body).unwrap());
+                write!(
+                    stream,
+                    "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+            bodies
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn milestones_arrive_before_later_events() {
+        let (url, server) = record_posts(4);
+        let webhook = Webhook::new(&url, None).unwrap();
+        let hook = webhook.download_milestones("start");
+        hook(ElectronEvent::DownloadStarted {
+            triple: "v13.1.7-linux-x64".into(),
+            total: Some(100),
+        });
+        hook(ElectronEvent::DownloadProgress {
+            done: 10,
+            total: Some(100),
+        });
+        hook(ElectronEvent::DownloadProgress {
+            done: 60,
+            total: Some(100),
+        });
+        hook(ElectronEvent::Extracting);
+        smol::block_on(webhook.send(&ColliderEvent::Resolved {
+            command: "start".into(),
+            electron: ElectronInfo {
+                version: "13.1.7".parse().unwrap(),
+                os: "linux".into(),
+                arch: "x64".into(),
+                triple: "v13.1.7-linux-x64".into(),
+                exe: "electron".into(),
+                dist_dir: ".".into(),
+                abi: None,
+                node: None,
+                chrome: None,
+            },
+        }));
+        let bodies = server.join().unwrap();
+        let events = bodies
+            .iter()
+            .map(|body| (body["event"].as_str().unwrap(), body["percent"].as_u64()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                ("download_progress", Some(0)),
+                ("download_progress", Some(50)),
+                ("download_progress", Some(100)),
+                ("resolved", None),
+            ]
+        );
+        assert_eq!(bodies[0]["triple"], "v13.1.7-linux-x64");
+    }
+
+    #[test]
+    fn artifact_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.asar");
+        std::fs::write(&path, b"hello").unwrap();
+        let artifact = smol::block_on(Artifact::from_path(&path)).unwrap();
+        assert_eq!(artifact.size, 5);
+        assert_eq!(
+            artifact.sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+    }
+}
@@ -6,31 +6,68 @@ use syn::{parse_macro_input, DeriveInput, Lit, LitStr};
 #[proc_macro_derive(ColliderConfigLayer, attributes(collider_config))]
 pub fn derive_collider_command(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let cmd = ColliderConfigLayer::from_derive_input(&input).unwrap();
-    quote!(#cmd).into()
+    match ColliderConfigLayer::from_derive_input(&input).and_then(ColliderConfigLayer::validate) {
+        Ok(cmd) => quote!(#cmd).into(),
+        Err(err) => err.write_errors().into(),
+    }
 }
 
+/// `#[collider_config(section = "start")]` on the struct makes keys in that
+/// config section (`[start]` in a `.colliderrc`) take precedence over
+/// top-level ones.
 #[derive(Debug, FromDeriveInput)]
-#[darling(supports(struct_named))]
+#[darling(attributes(collider_config), supports(struct_named))]
 struct ColliderConfigLayer {
     ident: syn::Ident,
-    generics: syn::Generics,
     data: ast::Data<(), ColliderCommandField>,
+    #[darling(default)]
+    section: Option<String>,
 }
 
 #[derive(Debug, FromField)]
-#[darling(forward_attrs)]
+#[darling(attributes(collider_config))]
 struct ColliderCommandField {
     ident: Option<syn::Ident>,
     ty: syn::Type,
-    attrs: Vec<syn::Attribute>,
+    /// `#[collider_config(ignore)]` fields are never read from config.
+    #[darling(default)]
+    ignore: bool,
+    /// `#[collider_config(append)]` fields are lists that get config values
+    /// first, followed by any values passed on the command line, instead of
+    /// the command line replacing config wholesale. With a section, top-level
+    /// values come first, then the section's, then the command line's.
+    #[darling(default)]
+    append: bool,
 }
 
-fn inner_type_of_option(ty: &syn::Type) -> Option<&syn::Type> {
+impl ColliderConfigLayer {
+    /// Catches what darling can't, pointing at the field that's wrong.
+    fn validate(self) -> darling::Result<Self> {
+        let errors = self
+            .data
+            .as_ref()
+            .take_struct()
+            .expect("BUG: darling only accepts structs with named fields")
+            .fields
+            .into_iter()
+            .filter(|field| field.append && inner_type_of_vec(&field.ty).is_none())
+            .map(|field| {
+                darling::Error::custom("#[collider_config(append)] can only be used on Vec fields")
+                    .with_span(&field.ty)
+            })
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(darling::Error::multiple(errors))
+        }
+    }
+}
+
+fn inner_type_of<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
     if let syn::Type::Path(syn::TypePath { path, .. }) = ty {
         if let Some(p) = path.segments.iter().next() {
-            // TODO: could be extended to support `Vec` too?
-            if p.ident != "Option" {
+            if p.ident != wrapper {
                 return None;
             }
 
@@ -44,27 +81,19 @@ fn inner_type_of_option(ty: &syn::Type) -> Option<&syn::Type> {
     None
 }
 
-fn collider_ignored(attr: &syn::Attribute) -> bool {
-    if let Ok(syn::Meta::List(meta_list)) = attr.parse_meta() {
-        if meta_list.path.get_ident().unwrap() == "collider_config" {
-            if let Some(syn::NestedMeta::Meta(syn::Meta::Path(p))) = meta_list.nested.first() {
-                return p.get_ident().unwrap() == "ignore";
-            }
-        }
-        // return meta_list.path.get_ident().unwrap() == "clap"
-        //     && !meta_list.nested.iter().any(|n| match n {
-        //         syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) => {
-        //             let path = nv.path.get_ident().unwrap();
-        //             path == "long" || path == "short"
-        //         }
-        //         _ => false,
-        //     });
-    }
-    false
+fn inner_type_of_option(ty: &syn::Type) -> Option<&syn::Type> {
+    inner_type_of(ty, "Option")
+}
+
+fn inner_type_of_vec(ty: &syn::Type) -> Option<&syn::Type> {
+    inner_type_of(ty, "Vec")
 }
 
-fn should_be_ignored(field: &ColliderCommandField) -> bool {
-    field.attrs.iter().any(|attr| collider_ignored(attr))
+fn is_bool(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(syn::TypePath { qself: None, path }) => path.is_ident("bool"),
+        _ => false,
+    }
 }
 
 impl ToTokens for ColliderConfigLayer {
@@ -72,45 +101,103 @@ impl ToTokens for ColliderConfigLayer {
         let ColliderConfigLayer {
             ref data,
             ref ident,
-            ..
+            ref section,
         } = *self;
         let fields = data
             .as_ref()
             .take_struct()
-            .expect(
-                "Enums not supported by derive macro. Implement ColliderCommandLayerConfig manually.",
-            )
+            .expect("BUG: darling only accepts structs with named fields")
             .fields;
         let field_defs = fields
             .clone()
             .into_iter()
-            .filter(|field| !should_be_ignored(field))
+            .filter(|field| !field.ignore)
             .map(|field| {
-                let ColliderCommandField { ident, ty, .. } = field;
-                let ident = ident.clone().unwrap();
-                let lit_str = Lit::Str(LitStr::new(&ident.to_string(), ident.span()));
+                let ColliderCommandField {
+                    ident, ty, append, ..
+                } = field;
+                let ident = ident.clone().expect("BUG: named fields have idents");
+                let name = ident.to_string();
+                // clap names args after their fields, kebab-cased.
+                let arg_id = Lit::Str(LitStr::new(&name.replace('_', "-"), ident.span()));
+                // Keys to read, from least to most specific.
+                let keys = std::iter::once(name.clone())
+                    .chain(section.iter().map(|section| format!("{}.{}", section, name)))
+                    .map(|key| Lit::Str(LitStr::new(&key, ident.span())))
+                    .collect::<Vec<_>>();
 
-                if let Some(inner) = inner_type_of_option(ty) {
+                if *append {
+                    let inner =
+                        inner_type_of_vec(ty).expect("BUG: append fields are validated as Vecs");
+                    quote! {
+                        {
+                            let mut merged = Vec::new();
+                            #(
+                                if let Ok(vals) = config.get_array(#keys) {
+                                    for val in vals {
+                                        let val = val.into_str().map_err(ColliderConfigError::ConfigError)?;
+                                        merged.push(#inner::from_str(&val).map_err(|e| ColliderConfigError::ConfigParseError(Box::new(e)))?);
+                                    }
+                                }
+                            )*
+                            merged.append(&mut self.#ident);
+                            self.#ident = merged;
+                        }
+                    }
+                } else if let Some(inner) = inner_type_of_vec(ty) {
+                    // Read as a list, with each item parsed like any other
+                    // value. Like other values, anything passed on the
+                    // command line replaces the whole list.
+                    let keys = keys.iter().rev();
+                    quote! {
+                        if args.occurrences_of(#arg_id) == 0 {
+                            if let Some(vals) = [#(#keys),*].iter().find_map(|key| config.get_array(key).ok()) {
+                                self.#ident = vals
+                                    .into_iter()
+                                    .map(|val| {
+                                        let val = val.into_str().map_err(ColliderConfigError::ConfigError)?;
+                                        #inner::from_str(&val).map_err(|e| ColliderConfigError::ConfigParseError(Box::new(e)))
+                                    })
+                                    .collect::<Result<_, ColliderConfigError>>()?;
+                            }
+                        }
+                    }
+                } else if is_bool(ty) {
+                    // Read as a real boolean, so `force = true` in TOML works
+                    // the same as `force = "true"`. Since clap flags can only
+                    // turn these on, config is consulted whenever the flag
+                    // wasn't passed.
+                    let keys = keys.iter().rev();
                     quote! {
-                        if args.occurrences_of(#lit_str) == 0 {
-                            if let Ok(val) = config.get_str(#lit_str) {
-                                self.#ident = #inner::from_str(&val).ok();
+                        if args.occurrences_of(#arg_id) == 0 {
+                            if let Some(key) = [#(#keys),*].iter().find(|key| config.get_str(key).is_ok()) {
+                                self.#ident = config.get_bool(key).map_err(ColliderConfigError::ConfigError)?;
                             }
                         }
                     }
                 } else {
+                    let keys = keys.iter().rev();
+                    let parse = if let Some(inner) = inner_type_of_option(ty) {
+                        quote! { Some(#inner::from_str(&val).map_err(|e| ColliderConfigError::ConfigParseError(Box::new(e)))?) }
+                    } else {
+                        quote! { #ty::from_str(&val).map_err(|e| ColliderConfigError::ConfigParseError(Box::new(e)))? }
+                    };
                     quote! {
-                        if args.occurrences_of(#lit_str) == 0 {
-                            if let Ok(val) = config.get_str(#lit_str) {
-                                self.#ident = #ty::from_str(&val).map_err(|e| ColliderConfigError::ConfigParseError(Box::new(e)))?;
+                        if args.occurrences_of(#arg_id) == 0 {
+                            if let Some(val) = [#(#keys),*].iter().find_map(|key| config.get_str(key).ok()) {
+                                self.#ident = #parse;
                             }
                         }
                     }
                 }
             });
 
+        // Named after the struct so more than one command can derive this in
+        // the same module.
+        let mod_name = quote::format_ident!("collider_command_layer_config_{}", ident);
         let ts = quote! {
-            mod collider_command_layer_config {
+            #[allow(non_snake_case)]
+            mod #mod_name {
                 use super::*;
 
                 use std::str::FromStr;
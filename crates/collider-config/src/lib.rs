@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub use clap::ArgMatches;
 use collider_common::miette::{self, Diagnostic, Result};
@@ -68,13 +68,16 @@ impl ColliderConfigOptions {
         self
     }
 
-    pub fn load(self) -> Result<ColliderConfig, ColliderConfigError> {
+    /// Loads and merges every config source, later ones overriding earlier
+    /// ones: the global config file, then `COLLIDER_CONFIG_*` environment
+    /// variables, then the package root's files in [`PKG_CONFIG_FILES`]
+    /// order.
+    pub fn load(self) -> Result<LoadedColliderConfig, ColliderConfigError> {
         let mut c = ColliderConfig::new();
+        let mut files = Vec::new();
         if self.global {
             if let Some(config_file) = self.global_config_file {
-                let path = config_file.display().to_string();
-                c.merge(File::with_name(&path[..]).required(false))
-                    .map_err(ColliderConfigError::ConfigError)?;
+                Self::merge_files(&mut c, &mut files, vec![config_file])?;
             }
         }
         if self.env {
@@ -82,26 +85,91 @@ impl ColliderConfigOptions {
                 .map_err(ColliderConfigError::ConfigError)?;
         }
         if let Some(root) = self.pkg_root {
-            c.merge(
-                File::with_name(&root.join("colliderrc").display().to_string()).required(false),
-            )
-            .map_err(ColliderConfigError::ConfigError)?;
-            c.merge(
-                File::with_name(&root.join(".colliderrc").display().to_string()).required(false),
-            )
-            .map_err(ColliderConfigError::ConfigError)?;
-            c.merge(
-                File::with_name(&root.join("colliderrc.toml").display().to_string())
-                    .required(false),
-            )
-            .map_err(ColliderConfigError::ConfigError)?;
-            c.merge(
-                File::with_name(&root.join(".colliderrc.toml").display().to_string())
-                    .required(false),
-            )
-            .map_err(ColliderConfigError::ConfigError)?;
+            let candidates = PKG_CONFIG_FILES
+                .iter()
+                .map(|name| root.join(name))
+                .collect();
+            Self::merge_files(&mut c, &mut files, candidates)?;
         }
-        Ok(c)
+        Ok(LoadedColliderConfig { config: c, files })
+    }
+
+    fn merge_files(
+        config: &mut ColliderConfig,
+        files: &mut Vec<PathBuf>,
+        candidates: Vec<PathBuf>,
+    ) -> Result<(), ColliderConfigError> {
+        for candidate in candidates {
+            if let Some(file) = find_config_file(&candidate) {
+                // `colliderrc` and `colliderrc.toml` can resolve to the same
+                // file. There's no point in merging it twice.
+                if !files.contains(&file) {
+                    config
+                        .merge(File::from(file.as_path()))
+                        .map_err(ColliderConfigError::ConfigError)?;
+                    files.push(file);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Config files looked for in a package root, from lowest to highest
+/// precedence: plain files, then dotfiles, each in TOML, JSON, then YAML.
+/// The bare names pick up the first file with any of
+/// [`CONFIG_EXTENSIONS`], so formats without an explicit entry still load.
+pub const PKG_CONFIG_FILES: &[&str] = &[
+    "colliderrc",
+    "colliderrc.toml",
+    "colliderrc.json",
+    "colliderrc.yaml",
+    "colliderrc.yml",
+    ".colliderrc",
+    ".colliderrc.toml",
+    ".colliderrc.json",
+    ".colliderrc.yaml",
+    ".colliderrc.yml",
+];
+
+/// Extensions that `config` knows how to parse. A config path without one of
+/// these will be looked up with each of them appended, same as
+/// `File::with_name` does.
+const CONFIG_EXTENSIONS: &[&str] = &["toml", "json", "yaml", "yml", "hjson", "ini"];
+
+fn find_config_file(path: &Path) -> Option<PathBuf> {
+    let has_known_ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| CONFIG_EXTENSIONS.contains(&ext))
+        .unwrap_or(false);
+    if has_known_ext && path.is_file() {
+        return Some(path.to_owned());
+    }
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_extension(ext))
+        .find(|path| path.is_file())
+}
+
+/// The result of [`ColliderConfigOptions::load`]: the merged config, plus the
+/// config files that were actually found and merged into it, in merge order.
+pub struct LoadedColliderConfig {
+    config: ColliderConfig,
+    files: Vec<PathBuf>,
+}
+
+impl LoadedColliderConfig {
+    pub fn config(&self) -> &ColliderConfig {
+        &self.config
+    }
+
+    pub fn loaded_files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn into_config(self) -> ColliderConfig {
+        self.config
     }
 }
 
@@ -120,7 +188,10 @@ mod tests {
     fn env_configs() -> Result<()> {
         let dir = tempdir()?;
         env::set_var("COLLIDER_CONFIG_STORE", dir.path().display().to_string());
-        let config = ColliderConfigOptions::new().global(false).load()?;
+        let config = ColliderConfigOptions::new()
+            .global(false)
+            .load()?
+            .into_config();
         env::remove_var("COLLIDER_CONFIG_STORE");
         assert_eq!(config.get_str("store")?, dir.path().display().to_string());
         Ok(())
@@ -133,9 +204,13 @@ mod tests {
         fs::write(&file, "store = \"hello world\"")?;
         let config = ColliderConfigOptions::new()
             .env(false)
-            .global_config_file(Some(file))
+            .global_config_file(Some(file.clone()))
             .load()?;
-        assert_eq!(config.get_str("store")?, String::from("hello world"));
+        assert_eq!(
+            config.config().get_str("store")?,
+            String::from("hello world")
+        );
+        assert_eq!(config.loaded_files(), &[file][..]);
         Ok(())
     }
 
@@ -145,7 +220,60 @@ mod tests {
             .global(false)
             .env(false)
             .load()?;
-        assert!(config.get_str("store").is_err());
+        assert!(config.config().get_str("store").is_err());
+        assert!(config.loaded_files().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn loaded_files() -> Result<()> {
+        let dir = tempdir()?;
+        let global = dir.path().join("global.toml");
+        let root = dir.path().join("pkg");
+        fs::create_dir(&root)?;
+        fs::write(root.join("colliderrc.toml"), "store = \"pkg\"")?;
+        fs::write(root.join(".colliderrc.toml"), "store = \"dotfile\"")?;
+        let config = ColliderConfigOptions::new()
+            .env(false)
+            .global_config_file(Some(global))
+            .pkg_root(Some(root.clone()))
+            .load()?;
+        assert_eq!(config.config().get_str("store")?, String::from("dotfile"));
+        assert_eq!(
+            config.loaded_files(),
+            &[root.join("colliderrc.toml"), root.join(".colliderrc.toml")][..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_and_yaml_configs() -> Result<()> {
+        let root = tempdir()?;
+        let load = || {
+            ColliderConfigOptions::new()
+                .global(false)
+                .env(false)
+                .pkg_root(Some(root.path().to_owned()))
+                .load()
+        };
+        fs::write(root.path().join("colliderrc.yaml"), "store: from-yaml\n")?;
+        assert_eq!(load()?.config().get_str("store")?, "from-yaml");
+
+        fs::write(
+            root.path().join("colliderrc.json"),
+            r#"{"store": "from-json"}"#,
+        )?;
+        fs::write(root.path().join(".colliderrc.yml"), "store: from-yml\n")?;
+        let config = load()?;
+        assert_eq!(config.config().get_str("store")?, "from-yml");
+        assert_eq!(
+            config.loaded_files(),
+            &[
+                root.path().join("colliderrc.json"),
+                root.path().join("colliderrc.yaml"),
+                root.path().join(".colliderrc.yml"),
+            ][..]
+        );
         Ok(())
     }
 }
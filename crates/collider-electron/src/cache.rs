@@ -0,0 +1,350 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use collider_common::{directories::ProjectDirs, smol::fs, tracing};
+use node_semver::{Range, Version};
+
+use crate::errors::ElectronError;
+use crate::targets::{exe_name, parse_triple};
+use crate::Electron;
+
+/// Environment variable that moves collider's Electron cache somewhere
+/// else, same as `--cache-dir`. Handy in sandboxed CI, where `$HOME` often
+/// isn't writable.
+pub const CACHE_DIR_ENV: &str = "COLLIDER_CACHE_DIR";
+
+/// Where collider keeps the Electron builds it downloads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheDirs {
+    /// Extracted builds, one directory per `<version>-<os>-<arch>` triple.
+    pub builds: PathBuf,
+    /// The zips those builds came from, including partial downloads.
+    pub downloads: PathBuf,
+}
+
+impl CacheDirs {
+    /// The cache in `cache_dir` if one's given, or else in
+    /// [`CACHE_DIR_ENV`] if that's set. Otherwise, builds go in the
+    /// platform's local data directory and zips in its cache directory.
+    pub fn resolve(cache_dir: Option<&Path>) -> Result<Self, ElectronError> {
+        let custom = cache_dir.map(Path::to_owned).or_else(|| {
+            std::env::var_os(CACHE_DIR_ENV)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+        });
+        if let Some(dir) = custom {
+            return Ok(CacheDirs::in_dir(&dir));
+        }
+        let dirs = ProjectDirs::from("", "", "collider").ok_or(ElectronError::NoProjectDir)?;
+        Ok(CacheDirs {
+            builds: dirs.data_local_dir().to_owned(),
+            downloads: dirs.cache_dir().to_owned(),
+        })
+    }
+
+    /// Where bisect keeps its copy of releases.json.
+    pub fn releases_json(&self) -> PathBuf {
+        self.downloads.join("releases.json")
+    }
+
+    /// The layout of a cache that's been moved to `dir`.
+    pub fn in_dir(dir: &Path) -> Self {
+        CacheDirs {
+            builds: dir.join("builds"),
+            downloads: dir.join("downloads"),
+        }
+    }
+}
+
+/// Extractions that haven't been touched in this long were abandoned by a
+/// collider that got killed partway through. Newer ones might still be in
+/// progress in another process.
+const STALE_EXTRACTION_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Where the build for `dest` is extracted to before it's renamed into
+/// place. It's hidden, and isn't named like a triple, so nothing takes it
+/// for a cached build.
+pub fn extraction_dir(dest: &Path) -> PathBuf {
+    let name = dest
+        .file_name()
+        .expect("BUG: build directories have a name")
+        .to_string_lossy();
+    dest.with_file_name(format!(".{}.tmp-{}", name, std::process::id()))
+}
+
+/// Renames the finished extraction at `tmp` to the build directory `dest`,
+/// with `replace` swapping out whatever's there. Otherwise, if another
+/// collider got a complete build into `dest` first, that one's kept instead.
+pub async fn move_into_place(tmp: &Path, dest: &Path, replace: bool) -> Result<(), ElectronError> {
+    if replace && fs::metadata(dest).await.is_ok() {
+        tracing::debug!("Replacing existing build at {}", dest.display());
+        if let Err(e) = fs::remove_dir_all(dest).await {
+            let _ = fs::remove_dir_all(tmp).await;
+            return Err(ElectronError::IoError(
+                format!("Failed to remove existing build at {}", dest.display()),
+                e,
+            ));
+        }
+    }
+    if let Err(e) = fs::rename(tmp, dest).await {
+        let _ = fs::remove_dir_all(tmp).await;
+        let exe = dest
+            .file_name()
+            .and_then(|name| parse_triple(name.to_str()?))
+            .map(|(_, platform, _)| dest.join(exe_name(&platform)));
+        let complete = match exe {
+            Some(exe) => fs::metadata(exe).await.is_ok(),
+            None => false,
+        };
+        if !complete {
+            return Err(ElectronError::IoError(
+                format!(
+                    "Failed to move extracted Electron into place at {}",
+                    dest.display()
+                ),
+                e,
+            ));
+        }
+        tracing::debug!(
+            "{} was extracted by someone else in the meantime. Using that.",
+            dest.display()
+        );
+    }
+    Ok(())
+}
+
+/// Deletes abandoned extractions (see [`extraction_dir`]) from `cache_dir`.
+pub(crate) fn remove_stale_extractions(cache_dir: &Path) -> std::io::Result<()> {
+    let entries = match std::fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let is_extraction = name
+            .to_str()
+            .map(|name| name.starts_with('.') && name.contains(".tmp-"))
+            .unwrap_or(false);
+        if !is_extraction {
+            continue;
+        }
+        let age = entry.metadata()?.modified()?.elapsed().unwrap_or_default();
+        if age >= STALE_EXTRACTION_AGE {
+            tracing::debug!("Removing stale extraction at {}", entry.path().display());
+            std::fs::remove_dir_all(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Finds the newest Electron build in `cache_dir` that satisfies `range` for
+/// the given target, returning its version and the directory it lives in.
+/// Builds that are missing their executable (say, from an interrupted
+/// extraction) are ignored.
+pub fn best_cached(
+    range: &Range,
+    os: &str,
+    arch: &str,
+    include_prerelease: bool,
+    cache_dir: &Path,
+) -> Option<(Version, PathBuf)> {
+    std::fs::read_dir(cache_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let (version, entry_os, entry_arch) = parse_triple(name.to_str()?)?;
+            if entry_os != os || entry_arch != arch {
+                return None;
+            }
+            if version.is_prerelease() && !include_prerelease {
+                return None;
+            }
+            if !range.satisfies(&version) {
+                return None;
+            }
+            let dist = entry.path();
+            if !dist.join(exe_name(os)).exists() {
+                return None;
+            }
+            Some((version, dist))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+}
+
+/// Every complete Electron build in `cache_dir`, newest first. Directories
+/// that aren't named like a target triple, or that are missing their
+/// executable, are skipped.
+pub(crate) fn list_cached_in(cache_dir: &Path) -> std::io::Result<Vec<Electron>> {
+    let mut builds = std::fs::read_dir(cache_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let (version, os, arch) = parse_triple(name.to_str()?)?;
+            let dist_dir = entry.path();
+            let exe = dist_dir.join(exe_name(&os));
+            if !exe.exists() {
+                return None;
+            }
+            Some(Electron {
+                exe,
+                dist_dir,
+                version,
+                os,
+                arch,
+                node: None,
+                chrome: None,
+                abi: None,
+            })
+        })
+        .collect::<Vec<_>>();
+    builds.sort_by(|a, b| {
+        b.version
+            .cmp(&a.version)
+            .then_with(|| a.triple().cmp(&b.triple()))
+    });
+    Ok(builds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_cache() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (triple, exe) in &[
+            ("v12.0.0-linux-x64", Some("electron")),
+            ("v13.1.7-linux-x64", Some("electron")),
+            ("v13.2.0-linux-x64", Some("electron")),
+            ("v13.3.0-linux-x64", None),
+            ("v13.4.0-linux-arm64", Some("electron")),
+            ("v13.5.0-win32-x64", Some("electron.exe")),
+            ("v14.0.0-beta.1-linux-x64", Some("electron")),
+        ] {
+            let dist = dir.path().join(triple);
+            std::fs::create_dir_all(&dist).unwrap();
+            if let Some(exe) = exe {
+                std::fs::write(dist.join(exe), "").unwrap();
+            }
+        }
+        std::fs::create_dir_all(dir.path().join("html-launcher")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn custom_cache_dir_wins() {
+        let dirs = CacheDirs::resolve(Some(Path::new("/ci/collider"))).unwrap();
+        assert_eq!(dirs, CacheDirs::in_dir(Path::new("/ci/collider")));
+        assert_eq!(dirs.builds, Path::new("/ci/collider/builds"));
+        assert_eq!(dirs.downloads, Path::new("/ci/collider/downloads"));
+        assert_eq!(
+            dirs.releases_json(),
+            Path::new("/ci/collider/downloads/releases.json")
+        );
+    }
+
+    #[test]
+    fn picks_newest_matching_build() {
+        let cache = fixture_cache();
+        let (version, dist) =
+            best_cached(&"^13".parse().unwrap(), "linux", "x64", false, cache.path()).unwrap();
+        assert_eq!(version.to_string(), "13.2.0");
+        assert_eq!(dist, cache.path().join("v13.2.0-linux-x64"));
+
+        let (version, _) =
+            best_cached(&"*".parse().unwrap(), "win32", "x64", false, cache.path()).unwrap();
+        assert_eq!(version.to_string(), "13.5.0");
+    }
+
+    #[test]
+    fn prereleases_and_misses() {
+        let cache = fixture_cache();
+        let range = ">=14.0.0-beta.0".parse().unwrap();
+        assert!(best_cached(&range, "linux", "x64", false, cache.path()).is_none());
+        let (version, _) = best_cached(&range, "linux", "x64", true, cache.path()).unwrap();
+        assert_eq!(version.to_string(), "14.0.0-beta.1");
+
+        assert!(best_cached(&"^15".parse().unwrap(), "linux", "x64", true, cache.path()).is_none());
+        assert!(best_cached(
+            &"*".parse().unwrap(),
+            "darwin",
+            "x64",
+            false,
+            &cache.path().join("nope")
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn lists_complete_builds_newest_first() {
+        let cache = fixture_cache();
+        let triples = list_cached_in(cache.path())
+            .unwrap()
+            .iter()
+            .map(|electron| electron.triple())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            triples,
+            vec![
+                "v14.0.0-beta.1-linux-x64",
+                "v13.5.0-win32-x64",
+                "v13.4.0-linux-arm64",
+                "v13.2.0-linux-x64",
+                "v13.1.7-linux-x64",
+                "v12.0.0-linux-x64",
+            ]
+        );
+    }
+
+    #[test]
+    fn only_stale_extractions_are_removed() {
+        let cache = fixture_cache();
+        let stale = cache.path().join(".v13.6.0-linux-x64.tmp-1");
+        std::fs::create_dir(&stale).unwrap();
+        std::fs::write(stale.join("electron"), "").unwrap();
+        let an_hour_ago = filetime::FileTime::from_system_time(
+            std::time::SystemTime::now() - STALE_EXTRACTION_AGE,
+        );
+        filetime::set_file_mtime(&stale, an_hour_ago).unwrap();
+        let fresh = extraction_dir(&cache.path().join("v13.7.0-linux-x64"));
+        std::fs::create_dir(&fresh).unwrap();
+        std::fs::write(fresh.join("electron"), "").unwrap();
+
+        // Neither counts as a cached build.
+        assert_eq!(list_cached_in(cache.path()).unwrap().len(), 6);
+        remove_stale_extractions(cache.path()).unwrap();
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        assert!(cache.path().join("v13.3.0-linux-x64").exists());
+    }
+
+    #[test]
+    fn extractions_move_into_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("v13.1.7-linux-x64");
+        let extract = |contents: &str| {
+            let tmp = extraction_dir(&dest);
+            std::fs::create_dir(&tmp).unwrap();
+            std::fs::write(tmp.join("electron"), contents).unwrap();
+            tmp
+        };
+        let exe = dest.join("electron");
+
+        let tmp = extract("first");
+        collider_common::smol::block_on(move_into_place(&tmp, &dest, false)).unwrap();
+        assert!(!tmp.exists());
+        assert_eq!(std::fs::read_to_string(&exe).unwrap(), "first");
+
+        // Someone else got there first.
+        let tmp = extract("second");
+        collider_common::smol::block_on(move_into_place(&tmp, &dest, false)).unwrap();
+        assert!(!tmp.exists());
+        assert_eq!(std::fs::read_to_string(&exe).unwrap(), "first");
+
+        let tmp = extract("forced");
+        collider_common::smol::block_on(move_into_place(&tmp, &dest, true)).unwrap();
+        assert_eq!(std::fs::read_to_string(&exe).unwrap(), "forced");
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use collider_common::tracing;
+use sha2::{Digest, Sha256};
+
+/// Recreates the tree at `from` under `to`, hard-linking each file to a copy
+/// of it in the content-addressed `store` (adding it to the store first if
+/// it isn't there yet). Builds that share a runtime end up sharing its bytes
+/// on disk. Where linking isn't possible (e.g. `to` and `store` are on
+/// different filesystems), files are copied instead.
+///
+/// Anything already at a destination path is removed first, so this never
+/// writes through an existing link into the store. Callers that edit linked
+/// files afterwards must go through [`unshare`] to get a private copy.
+pub fn link_tree(from: &Path, to: &Path, store: &Path) -> io::Result<LinkStats> {
+    let mut stats = LinkStats::default();
+    link_dir(from, to, store, &mut stats)?;
+    tracing::debug!(
+        "Linked {} file(s) from {} and copied {}",
+        stats.linked,
+        store.display(),
+        stats.copied
+    );
+    Ok(stats)
+}
+
+/// How [`link_tree`] placed each file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LinkStats {
+    pub linked: usize,
+    pub copied: usize,
+}
+
+/// Copies the tree at `from` into `to`, recreating symlinks as symlinks
+/// rather than copying whatever they point at. macOS bundles depend on
+/// their frameworks' relative links surviving the copy.
+pub fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let src = entry.path();
+        let dest = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_tree(&src, &dest)?;
+            continue;
+        }
+        remove_existing(&dest)?;
+        if file_type.is_symlink() {
+            copy_symlink(&src, &dest)?;
+        } else {
+            fs::copy(&src, &dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// The total size of the files under `path`. Symlinks count as themselves,
+/// not whatever they point at, or macOS frameworks get counted twice.
+pub fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let meta = fs::symlink_metadata(entry.path())?;
+        size += if meta.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            meta.len()
+        };
+    }
+    Ok(size)
+}
+
+/// Replaces a (possibly) hard-linked file with a private copy of itself, so
+/// it can be modified without touching the store or other builds.
+pub fn unshare(path: &Path) -> io::Result<()> {
+    let tmp = path.with_extension("collider-unshare");
+    fs::copy(path, &tmp)?;
+    fs::rename(&tmp, path)
+}
+
+fn link_dir(from: &Path, to: &Path, store: &Path, stats: &mut LinkStats) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let src = entry.path();
+        let dest = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            link_dir(&src, &dest, store, stats)?;
+            continue;
+        }
+        remove_existing(&dest)?;
+        if file_type.is_symlink() {
+            // macOS frameworks are full of these. They're tiny, and need to
+            // stay links, so they're recreated rather than stored.
+            copy_symlink(&src, &dest)?;
+            continue;
+        }
+        let stored = store_file(&src, store)?;
+        if fs::hard_link(&stored, &dest).is_ok() {
+            stats.linked += 1;
+        } else {
+            fs::copy(&stored, &dest)?;
+            stats.copied += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Adds `src` to the store, if it isn't there already, and returns its path
+/// in the store.
+fn store_file(src: &Path, store: &Path) -> io::Result<PathBuf> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(src)?, &mut hasher)?;
+    let hash = hex::encode(hasher.finalize());
+    let stored = store.join(&hash[..2]).join(&hash[2..]);
+    if !stored.exists() {
+        let parent = stored.parent().expect("BUG: store paths have parents");
+        fs::create_dir_all(parent)?;
+        // Copy under a temporary name, so a half-written file never shows up
+        // under a real hash.
+        let tmp = parent.join(format!("{}.tmp", &hash[2..]));
+        fs::copy(src, &tmp)?;
+        fs::rename(&tmp, &stored)?;
+    }
+    Ok(stored)
+}
+
+fn remove_existing(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(src: &Path, dest: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(src)?, dest)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::copy(src, dest).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn dir_size_skips_symlinked_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let versions = tmp.path().join("Versions");
+        fs::create_dir_all(versions.join("A")).unwrap();
+        fs::write(versions.join("A").join("Electron Framework"), [0; 100]).unwrap();
+        std::os::unix::fs::symlink("A", versions.join("Current")).unwrap();
+        let link_len = fs::symlink_metadata(versions.join("Current"))
+            .unwrap()
+            .len();
+        assert_eq!(dir_size(tmp.path()).unwrap(), 100 + link_len);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn builds_share_stored_files() {
+        use std::os::unix::fs::MetadataExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dist = tmp.path().join("dist");
+        std::fs::create_dir_all(dist.join("resources")).unwrap();
+        std::fs::write(dist.join("electron"), "runtime").unwrap();
+        std::fs::write(dist.join("resources").join("default_app.asar"), "asar").unwrap();
+        let store = tmp.path().join("store");
+
+        let stats = link_tree(&dist, &tmp.path().join("a"), &store).unwrap();
+        assert_eq!(stats.linked + stats.copied, 2);
+        link_tree(&dist, &tmp.path().join("b"), &store).unwrap();
+        let a = std::fs::metadata(tmp.path().join("a").join("electron")).unwrap();
+        let b = std::fs::metadata(tmp.path().join("b").join("electron")).unwrap();
+        assert_eq!(a.ino(), b.ino());
+
+        // Editing one build must not leak into the store or other builds.
+        let edited = tmp.path().join("a").join("electron");
+        unshare(&edited).unwrap();
+        std::fs::write(&edited, "patched").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join("b").join("electron")).unwrap(),
+            "runtime"
+        );
+
+        // Re-linking over an existing build replaces files instead of
+        // writing through them.
+        link_tree(&dist, &tmp.path().join("a"), &store).unwrap();
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "runtime");
+    }
+}
@@ -1,6 +1,8 @@
 use std::cmp;
+use std::path::PathBuf;
 
 use collider_common::{
+    chrono::{DateTime, Local, Utc},
     miette::{self, Diagnostic, NamedSource, SourceOffset},
     thiserror::{self, Error},
 };
@@ -41,16 +43,16 @@ pub enum ElectronError {
     #[diagnostic(code(collider::electron::zip_error))]
     ZipError(#[from] zip::result::ZipError),
 
-    #[error(transparent)]
-    #[diagnostic(code(collider::electron::github_api))]
-    GitHubApiError(octocrab::Error),
-
-    #[error("{0}")]
+    #[error("{message}{}", rate_limit_reset(.resets_at))]
     #[diagnostic(
         code(collider::electron::github_api::request_limit),
-        help("Consider passing in a GitHub API Token using `--github-token`, or using a different one."),
+        help("Wait for the rate limit to reset, or pass in a GitHub API Token using `--github-token` or the `GH_TOKEN` or `GITHUB_TOKEN` environment variables, or use a different one."),
     )]
-    GitHubApiLimit(octocrab::GitHubError),
+    GitHubApiLimit {
+        message: String,
+        /// When GitHub said the limit resets, if it did.
+        resets_at: Option<DateTime<Utc>>,
+    },
 
     #[error("Could not find matching Electron files for release: {target}.")]
     #[diagnostic(code(collider::electron::missing_electron_files))]
@@ -63,17 +65,24 @@ pub enum ElectronError {
     #[diagnostic(code(collider::electron::matching_version_not_found))]
     MatchingVersionNotFound(node_semver::Range),
 
+    #[error("No cached Electron version matches `electron@{0}`, and collider is offline.")]
+    #[diagnostic(
+        code(collider::electron::no_cached_version_offline),
+        help("Run once without `--offline` to download a matching version, or ask for one that's already cached.")
+    )]
+    NoCachedVersionOffline(node_semver::Range),
+
     #[error("Unsupported architecture: {0}.")]
     #[diagnostic(
         code(collider::electron::unsupported_arch),
-        help("Electron only supports ia32, x64, arm64, and arm7l.")
+        help("Electron only supports ia32, x64, arm64, and armv7l.")
     )]
     UnsupportedArch(String),
 
     #[error("Unsupported platform: {0}.")]
     #[diagnostic(
         code(collider::electron::unsupported_arch),
-        help("Electron only supports win32, linux, and darwin.")
+        help("Electron only supports win32, linux, darwin, and mas (the Mac App Store build).")
     )]
     UnsupportedPlatform(String),
 
@@ -89,22 +98,61 @@ pub enum ElectronError {
     #[diagnostic(code(collider::electron::parse_package_json))]
     ParsePackageJson(#[from] collider_common::serde_json::Error),
 
-    #[error("Electron process exited with an error")]
+    #[error("Electron at {} failed to launch: {reason}", .exe.display())]
+    #[diagnostic(
+        code(collider::electron::launch_verification_failed),
+        help("The download may be incomplete, or this system may be missing libraries Electron needs. Try again with `--force` to download it again.")
+    )]
+    LaunchVerificationFailed { exe: PathBuf, reason: String },
+
+    #[error("Electron at {} is incomplete: {reason}", .dir.display())]
+    #[diagnostic(
+        code(collider::electron::incomplete_electron),
+        help("Copying it may have been interrupted, or the disk may be full. Free up some space and try again, or use `--force` to download Electron again.")
+    )]
+    IncompleteElectron { dir: PathBuf, reason: String },
+
+    #[error("Checksum mismatch for {file}: expected {expected}, got {actual}.")]
+    #[diagnostic(
+        code(collider::electron::checksum_mismatch),
+        help("The download was corrupted or tampered with. If you're using a mirror, make sure it's serving the same files as Electron's GitHub releases.")
+    )]
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("The server is rate limiting downloads, and still was after retrying. It asked to wait {}s before trying again.", .retry_after.as_secs())]
+    #[diagnostic(
+        code(collider::electron::throttled),
+        help("Wait a bit and try again. If you're using a mirror, it may have stricter limits than GitHub.")
+    )]
+    Throttled { retry_after: std::time::Duration },
+
+    #[error("Electron process exited with an error ({0})")]
     #[diagnostic(code(collider::electron::electron_error))]
-    ElectronFailed,
+    ElectronFailed(std::process::ExitStatus),
 }
 
-impl From<octocrab::Error> for ElectronError {
-    fn from(err: octocrab::Error) -> Self {
-        match err {
-            octocrab::Error::GitHub {
-                source: ref gh_err, ..
-            } if gh_err.message.contains("rate limit exceeded") => {
-                ElectronError::GitHubApiLimit(gh_err.clone())
-            }
-            _ => ElectronError::GitHubApiError(err),
-        }
-    }
+/// ` The rate limit resets in 12 minutes (at 14:05 local).`, for
+/// [`ElectronError::GitHubApiLimit`]. Nothing if GitHub didn't say when.
+fn rate_limit_reset(resets_at: &Option<DateTime<Utc>>) -> String {
+    let resets_at = match resets_at {
+        Some(resets_at) => resets_at,
+        None => return String::new(),
+    };
+    let secs = (*resets_at - Utc::now()).num_seconds().max(0);
+    let in_mins = match (secs + 59) / 60 {
+        0 => "any moment now".into(),
+        1 => "in 1 minute".into(),
+        mins => format!("in {} minutes", mins),
+    };
+    format!(
+        " The rate limit resets {} (at {} local).",
+        in_mins,
+        resets_at.with_timezone(&Local).format("%H:%M")
+    )
 }
 
 impl ElectronError {
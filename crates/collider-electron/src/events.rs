@@ -0,0 +1,64 @@
+use std::sync::{Arc, Mutex};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use node_semver::Version;
+
+/// What [`ElectronOpts::ensure_electron`](crate::ElectronOpts::ensure_electron)
+/// is up to, for showing progress in a UI. See
+/// [`ElectronOpts::on_event`](crate::ElectronOpts::on_event).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElectronEvent {
+    /// Looking for a version that satisfies the requested range.
+    ResolvingVersion,
+    /// Picked this version. If it's already cached, [`Done`](Self::Done)
+    /// comes next.
+    VersionResolved(Version),
+    /// Started downloading the build for `triple`. `total` is its size in
+    /// bytes, if the server said. A retried download starts again.
+    DownloadStarted { triple: String, total: Option<u64> },
+    /// `done` of `total` bytes are downloaded so far. Resumed downloads start
+    /// out with the bytes that were already there.
+    DownloadProgress { done: u64, total: Option<u64> },
+    /// The download's been verified, and is being unpacked into the cache.
+    Extracting,
+    /// The build is ready to use.
+    Done,
+}
+
+pub(crate) type OnEvent = Arc<dyn Fn(ElectronEvent) + Send + Sync>;
+
+/// The progress bar collider shows on stderr while downloading Electron.
+pub(crate) fn progress_bar() -> OnEvent {
+    let current = Mutex::new(None::<ProgressBar>);
+    Arc::new(move |event| {
+        let mut current = current.lock().expect("BUG: progress bar lock poisoned");
+        match event {
+            ElectronEvent::DownloadStarted { triple, total } => {
+                let bar = match total {
+                    Some(total) => ProgressBar::new(total).with_style(
+                        ProgressStyle::default_bar()
+                            .template("{msg} [{bar:40}] {bytes}/{total_bytes} ({eta})")
+                            .progress_chars("=> "),
+                    ),
+                    // No Content-Length, so there's nothing to measure against.
+                    None => ProgressBar::new_spinner().with_style(
+                        ProgressStyle::default_spinner().template("{spinner} {msg} {bytes}"),
+                    ),
+                };
+                bar.set_message(format!("Downloading electron-{}", triple));
+                *current = Some(bar);
+            }
+            ElectronEvent::DownloadProgress { done, .. } => {
+                if let Some(bar) = &*current {
+                    bar.set_position(done);
+                }
+            }
+            ElectronEvent::Extracting | ElectronEvent::Done => {
+                if let Some(bar) = current.take() {
+                    bar.finish_and_clear();
+                }
+            }
+            ElectronEvent::ResolvingVersion | ElectronEvent::VersionResolved(_) => {}
+        }
+    })
+}
@@ -0,0 +1,292 @@
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path};
+
+use collider_common::tracing;
+use zip::ZipArchive;
+
+use crate::errors::ElectronError;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Extracts every entry in `archive` into `dest`. Unlike
+/// [`ZipArchive::extract`], this recreates symlinks as symlinks (macOS
+/// framework bundles don't work without them, e.g. `Versions/Current`) and
+/// applies each entry's Unix permissions.
+pub(crate) fn extract_zip<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    dest: &Path,
+) -> Result<(), ElectronError> {
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let rel = match entry.enclosed_name() {
+            Some(rel) => rel.to_owned(),
+            None => {
+                tracing::warn!("Skipping zip entry with unsafe path: {}", entry.name());
+                continue;
+            }
+        };
+        let is_dir = entry.is_dir();
+        let mode = entry.unix_mode();
+        write_entry(dest, &rel, is_dir, mode, &mut entry).map_err(|e| {
+            ElectronError::IoError(format!("Failed to extract {}", rel.display()), e)
+        })?;
+    }
+    Ok(())
+}
+
+/// Writes a single zip entry out to `rel` inside `dest`.
+fn write_entry(
+    dest: &Path,
+    rel: &Path,
+    is_dir: bool,
+    mode: Option<u32>,
+    contents: &mut impl Read,
+) -> io::Result<()> {
+    // `enclosed_name` only vouches for the entry's own path. An earlier
+    // symlink entry could still redirect one of its directories elsewhere.
+    check_no_symlinked_parents(dest, rel)?;
+    let out = dest.join(rel);
+    if is_dir {
+        return fs::create_dir_all(out);
+    }
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if mode.map(|mode| mode & S_IFMT == S_IFLNK).unwrap_or(false) {
+        // A symlink entry's contents are its target.
+        let mut target = String::new();
+        contents.read_to_string(&mut target)?;
+        if !link_stays_inside(rel, Path::new(&target)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Symlink points outside the archive: {}", target),
+            ));
+        }
+        return write_symlink(&target, &out);
+    }
+    // Links from an earlier, interrupted extraction shouldn't get written
+    // through.
+    if fs::symlink_metadata(&out).is_ok() {
+        fs::remove_file(&out)?;
+    }
+    io::copy(contents, &mut fs::File::create(&out)?)?;
+    set_mode(&out, mode)
+}
+
+/// Fails if any directory on the way from `dest` to `rel` is a symlink.
+fn check_no_symlinked_parents(dest: &Path, rel: &Path) -> io::Result<()> {
+    let mut dir = dest.to_owned();
+    for component in rel.parent().into_iter().flat_map(Path::components) {
+        dir.push(component);
+        if let Ok(meta) = fs::symlink_metadata(&dir) {
+            if meta.file_type().is_symlink() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Entry would be written through a symlink: {}",
+                        dir.display()
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a symlink at `rel` pointing to `target` resolves to somewhere
+/// inside the extraction directory.
+fn link_stays_inside(rel: &Path, target: &Path) -> bool {
+    let mut depth = rel.components().count().saturating_sub(1);
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+#[cfg(unix)]
+fn write_symlink(target: &str, out: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(out).is_ok() {
+        fs::remove_file(out)?;
+    }
+    std::os::unix::fs::symlink(target, out)
+}
+
+#[cfg(not(unix))]
+fn write_symlink(target: &str, out: &Path) -> io::Result<()> {
+    // Only macOS builds have these, and they can't run here anyway. Keep the
+    // old behavior of writing the target out as a file.
+    fs::write(out, target)
+}
+
+/// Makes sure `path` (if it exists) is executable by everyone who can read
+/// it, whatever mode it was extracted with.
+#[cfg(unix)]
+pub(crate) fn ensure_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = match fs::metadata(path) {
+        Ok(meta) => meta.permissions().mode(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    // Add an execute bit wherever there's a read bit.
+    let wanted = mode | ((mode & 0o444) >> 2);
+    if wanted != mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(wanted))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn ensure_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Whether a file could be run as a program. Outside of Unix, there's no
+/// execute bit to check, so any file will do.
+#[cfg(unix)]
+pub(crate) fn is_executable(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.is_file() && meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_executable(meta: &fs::Metadata) -> bool {
+    meta.is_file()
+}
+
+#[cfg(unix)]
+fn set_mode(out: &Path, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    match mode {
+        Some(mode) => fs::set_permissions(out, fs::Permissions::from_mode(mode & 0o7777)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_mode(_out: &Path, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn symlinks_are_recreated() {
+        let dir = tempfile::tempdir().unwrap();
+        let rel = Path::new("Electron Framework.framework/Versions/Current");
+        let link = |target: &str| {
+            write_entry(
+                dir.path(),
+                rel,
+                false,
+                Some(S_IFLNK | 0o755),
+                &mut target.as_bytes(),
+            )
+        };
+        link("A").unwrap();
+        assert_eq!(fs::read_link(dir.path().join(rel)).unwrap(), Path::new("A"));
+        // Re-extracting over an existing link replaces it.
+        link("B").unwrap();
+        assert_eq!(fs::read_link(dir.path().join(rel)).unwrap(), Path::new("B"));
+    }
+
+    #[test]
+    fn symlinks_cannot_escape() {
+        let outside = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let link = |rel: &str, target: &str| {
+            write_entry(
+                dir.path(),
+                Path::new(rel),
+                false,
+                Some(S_IFLNK | 0o755),
+                &mut target.as_bytes(),
+            )
+        };
+        let file = |rel: &str| {
+            write_entry(
+                dir.path(),
+                Path::new(rel),
+                false,
+                Some(0o644),
+                &mut "x".as_bytes(),
+            )
+        };
+        assert!(link("x", outside.path().to_str().unwrap()).is_err());
+        assert!(link("x", "../outside").is_err());
+        assert!(link("a/x", "../../outside").is_err());
+        assert!(fs::symlink_metadata(dir.path().join("x")).is_err());
+
+        // Each of these stays inside on its own, but `sub/a/..` is the
+        // parent of the extraction directory once `sub/a` exists.
+        link("sub/a", "..").unwrap();
+        link("sub/b", "a/..").unwrap();
+        assert!(file("sub/a/f").is_err());
+        assert!(file("sub/b/f").is_err());
+        assert!(!dir.path().join("f").exists());
+        assert_eq!(fs::read_dir(outside.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn exe_is_made_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("electron");
+        // No Unix mode in the zip, like archives made on Windows.
+        write_entry(
+            dir.path(),
+            Path::new("electron"),
+            false,
+            None,
+            &mut "#!/bin/sh\n".as_bytes(),
+        )
+        .unwrap();
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o644)).unwrap();
+        ensure_executable(&exe).unwrap();
+        assert_eq!(
+            fs::metadata(&exe).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        ensure_executable(&dir.path().join("missing")).unwrap();
+    }
+
+    #[test]
+    fn files_keep_their_permissions() {
+        let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        let exe = zip::write::FileOptions::default().unix_permissions(0o755);
+        zip.start_file("electron", exe).unwrap();
+        zip.write_all(b"#!/bin/sh\n").unwrap();
+        zip.add_directory("resources/", Default::default()).unwrap();
+        let data = zip::write::FileOptions::default().unix_permissions(0o644);
+        zip.start_file("resources/default_app.asar", data).unwrap();
+        zip.write_all(b"asar").unwrap();
+        let mut archive = ZipArchive::new(zip.finish().unwrap()).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        extract_zip(&mut archive, dir.path()).unwrap();
+        let mode = |p: &str| {
+            fs::metadata(dir.path().join(p))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777
+        };
+        assert_eq!(mode("electron"), 0o755);
+        assert_eq!(mode("resources/default_app.asar"), 0o644);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("resources/default_app.asar")).unwrap(),
+            "asar"
+        );
+    }
+}
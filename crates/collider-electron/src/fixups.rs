@@ -0,0 +1,94 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use collider_common::tracing;
+use node_semver::Version;
+
+/// A correction for a packaging defect in one specific Electron release,
+/// like a missing symlink or a wrong permission. It's applied to the
+/// extracted build right after download.
+pub(crate) struct Fixup {
+    /// The exact Electron version with the defect.
+    pub version: &'static str,
+    /// The Electron platform name (`darwin`, `mas`, ...) whose build has it.
+    pub platform: &'static str,
+    /// What's being fixed, for the logs.
+    pub description: &'static str,
+    /// Fixes up the build extracted into the given directory.
+    pub apply: fn(&Path) -> io::Result<()>,
+}
+
+/// Known-broken releases, and how to fix them. Entries should link to the
+/// upstream issue they work around. There are none right now.
+pub(crate) const FIXUPS: &[Fixup] = &[];
+
+/// A user-supplied hook that runs after every extraction. See
+/// [`ElectronOpts::post_extract`](crate::ElectronOpts::post_extract).
+pub(crate) type PostExtract = Arc<dyn Fn(&Path, &Version, &str) -> io::Result<()> + Send + Sync>;
+
+/// Applies each of `fixups` that's for exactly this `version` and `platform`
+/// to the build extracted into `dir`. Returns how many were applied.
+pub(crate) fn apply_fixups(
+    fixups: &[Fixup],
+    dir: &Path,
+    version: &Version,
+    platform: &str,
+) -> io::Result<usize> {
+    let version = version.to_string();
+    let mut applied = 0;
+    for fixup in fixups {
+        if fixup.version == version && fixup.platform == platform {
+            tracing::debug!(
+                "Fixing up electron@{} ({}): {}",
+                version,
+                platform,
+                fixup.description
+            );
+            (fixup.apply)(dir)?;
+            applied += 1;
+        }
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mark(dir: &Path) -> io::Result<()> {
+        std::fs::write(dir.join("fixed"), "")
+    }
+
+    #[test]
+    fn fixups_only_apply_to_their_release() {
+        let fixups = [
+            Fixup {
+                version: "13.1.7",
+                platform: "darwin",
+                description: "test fixup",
+                apply: mark,
+            },
+            Fixup {
+                version: "13.1.8",
+                platform: "darwin",
+                description: "other version",
+                apply: |_| panic!("should not run"),
+            },
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let version = "13.1.7".parse().unwrap();
+
+        assert_eq!(
+            apply_fixups(&fixups, dir.path(), &version, "linux").unwrap(),
+            0
+        );
+        assert!(!dir.path().join("fixed").exists());
+
+        assert_eq!(
+            apply_fixups(&fixups, dir.path(), &version, "darwin").unwrap(),
+            1
+        );
+        assert!(dir.path().join("fixed").exists());
+    }
+}
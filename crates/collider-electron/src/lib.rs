@@ -1,31 +1,97 @@
+use std::ffi::{OsStr, OsString};
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use async_compat::CompatExt;
 use collider_common::{
-    directories::ProjectDirs,
-    serde::Deserialize,
+    serde::{Deserialize, Serialize},
     serde_json,
-    smol::{self, fs, io::AsyncWriteExt},
+    smol::{
+        self, fs, future,
+        io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+        process::Command,
+        stream::StreamExt,
+        Timer,
+    },
     tracing,
 };
 use node_semver::{Range, Version};
+use once_cell::sync::OnceCell;
+pub use reqwest::Url;
+use reqwest::{header::RANGE, StatusCode};
+use sha2::{Digest, Sha256};
 
-use errors::ElectronError;
+pub use cache::{best_cached, extraction_dir, move_into_place, CacheDirs, CACHE_DIR_ENV};
+pub use cas::{copy_tree, dir_size, link_tree, unshare, LinkStats};
+pub use errors::ElectronError;
+pub use events::ElectronEvent;
+use events::OnEvent;
+use extract::{ensure_executable, extract_zip, is_executable};
+use fixups::{apply_fixups, PostExtract, FIXUPS};
+pub use retry::{
+    check_throttled, retry_async, retry_with_prompt, RetryPolicy, RetryPrompt, DEFAULT_MAX_RETRIES,
+    DEFAULT_RETRY_DELAY,
+};
+pub use targets::{
+    exe_name, expand_targets, host_arch, host_platform, parse_triple, platform_archs, Platform,
+    ARCHS, PLATFORMS,
+};
+pub use version_source::{parse_tool_versions, project_electron_range};
 
+mod cache;
+mod cas;
 mod errors;
+mod events;
+mod extract;
+mod fixups;
+mod retry;
+mod targets;
+mod version_source;
 
 #[derive(Debug, Clone, Deserialize)]
 struct PackageJson {
+    // releases.json entries don't have one.
+    #[serde(default)]
     name: String,
     version: Version,
+    // These are only present in releases.json entries.
+    node: Option<String>,
+    chrome: Option<String>,
+    modules: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Electron {
     exe: PathBuf,
+    dist_dir: PathBuf,
     version: Version,
     os: String,
     arch: String,
+    node: Option<String>,
+    chrome: Option<String>,
+    abi: Option<String>,
+}
+
+/// Everything collider knows about a particular Electron build, in one
+/// serializable bundle. Anything that reports on an Electron (JSON output,
+/// pack manifests, etc) should embed this instead of picking fields off of
+/// [`Electron`] by hand.
+#[derive(Debug, Clone, Serialize)]
+pub struct ElectronInfo {
+    pub version: Version,
+    pub os: String,
+    pub arch: String,
+    pub triple: String,
+    pub exe: PathBuf,
+    pub dist_dir: PathBuf,
+    /// `NODE_MODULE_VERSION` of this build. Only known if the version was
+    /// resolved against releases.json.
+    pub abi: Option<String>,
+    pub node: Option<String>,
+    pub chrome: Option<String>,
 }
 
 impl Electron {
@@ -45,53 +111,192 @@ impl Electron {
         &self.arch
     }
 
-    pub async fn copy_files(&self, to: &Path) -> Result<Self, ElectronError> {
+    /// Directory the Electron distribution was extracted (or copied) into.
+    pub fn dist_dir(&self) -> &Path {
+        &self.dist_dir
+    }
+
+    pub fn triple(&self) -> String {
+        format!("v{}-{}-{}", self.version, self.os, self.arch)
+    }
+
+    pub fn info(&self) -> ElectronInfo {
+        ElectronInfo {
+            version: self.version.clone(),
+            os: self.os.clone(),
+            arch: self.arch.clone(),
+            triple: self.triple(),
+            exe: self.exe.clone(),
+            dist_dir: self.dist_dir.clone(),
+            abi: self.abi.clone(),
+            node: self.node.clone(),
+            chrome: self.chrome.clone(),
+        }
+    }
+
+    /// Every Electron build that's already been downloaded into collider's
+    /// cache (see [`CacheDirs::resolve`]), for any target, newest version
+    /// first. Useful for offering an offline choice of versions. Builds that
+    /// are missing their executable (say, from an interrupted extraction)
+    /// are skipped.
+    pub fn list_cached(cache_dir: Option<&Path>) -> Result<Vec<Electron>, ElectronError> {
+        let dirs = CacheDirs::resolve(cache_dir)?;
+        let cache_dir = &dirs.builds;
+        if !cache_dir.exists() {
+            return Ok(Vec::new());
+        }
+        cache::list_cached_in(cache_dir).map_err(|e| {
+            ElectronError::IoError(
+                format!("Failed to read cache directory at {}", cache_dir.display()),
+                e,
+            )
+        })
+    }
+
+    /// Runs this Electron with `args`, with stdio inherited from collider,
+    /// and waits for it to exit. A non-zero exit is an
+    /// [`ElectronError::ElectronFailed`].
+    pub async fn launch(&self, args: &[OsString]) -> Result<ExitStatus, ElectronError> {
+        launch_exe(&self.exe, args, &[], Stdio::inherit()).await
+    }
+
+    /// Checks that this Electron's files look complete: the `version` file
+    /// Electron ships with matches [`Electron::version`], and the executable
+    /// is there and can be run. Much cheaper than actually launching it, so
+    /// it's worth doing after every copy.
+    pub async fn verify_version(&self) -> Result<(), ElectronError> {
+        let incomplete = |reason: String| ElectronError::IncompleteElectron {
+            dir: self.dist_dir.clone(),
+            reason,
+        };
+        let version_file = self.dist_dir.join("version");
+        match fs::read_to_string(&version_file).await {
+            Ok(found) => {
+                let found = found.trim().trim_start_matches('v');
+                if found != self.version.to_string() {
+                    return Err(incomplete(format!(
+                        "its version file says `{}`, but expected {}",
+                        found, self.version
+                    )));
+                }
+            }
+            // macOS builds keep their version in the app bundle's Info.plist.
+            Err(e)
+                if e.kind() == std::io::ErrorKind::NotFound
+                    && matches!(self.os.as_str(), "darwin" | "mas") => {}
+            Err(e) => {
+                return Err(incomplete(format!(
+                    "couldn't read {}: {}",
+                    version_file.display(),
+                    e
+                )))
+            }
+        }
+        match fs::metadata(&self.exe).await {
+            Ok(meta) if is_executable(&meta) => Ok(()),
+            Ok(_) => Err(incomplete(format!(
+                "{} is not executable",
+                self.exe.display()
+            ))),
+            Err(e) => Err(incomplete(format!(
+                "couldn't find {}: {}",
+                self.exe.display(),
+                e
+            ))),
+        }
+    }
+
+    /// Copies this Electron's files into `to`. With a `cas_store`, files are
+    /// hard-linked out of that content-addressed store instead (see
+    /// [`link_tree`]), so builds sharing a runtime don't each need a full copy
+    /// of it. Either way, files in the copy that get edited afterwards should
+    /// go through [`unshare`] first.
+    pub async fn copy_files(
+        &self,
+        to: &Path,
+        cas_store: Option<&Path>,
+    ) -> Result<Self, ElectronError> {
         fs::create_dir_all(&to).await.map_err(|e| {
             ElectronError::IoError(
                 "Failed to create directories to copy electron files into.".into(),
                 e,
             )
         })?;
-        let from_clone = self
-            .exe()
-            .parent()
-            .expect("BUG: This should have a parent")
-            .to_owned();
+        // The whole distribution, not just the exe's directory: on macOS,
+        // that's buried inside Electron.app.
+        let from_clone = self.dist_dir.clone();
         let to_clone = to.to_owned();
-        smol::unblock(move || {
-            let mut opts = fs_extra::dir::CopyOptions::new();
-            opts.overwrite = true;
-            opts.content_only = true;
-            fs_extra::dir::copy(from_clone, to_clone, &opts)
-        })
-        .await?;
+        if let Some(store) = cas_store {
+            let store = store.to_owned();
+            smol::unblock(move || link_tree(&from_clone, &to_clone, &store))
+                .await
+                .map_err(|e| {
+                    ElectronError::IoError(
+                        format!("Failed to link electron files into {}.", to.display()),
+                        e,
+                    )
+                })?;
+        } else {
+            // An earlier copy might have been linked out of a store. Copying
+            // over it would write straight through into the store.
+            if fs::metadata(&to_clone).await.is_ok() {
+                fs::remove_dir_all(&to_clone).await.map_err(|e| {
+                    ElectronError::IoError(
+                        format!("Failed to clear out {}.", to_clone.display()),
+                        e,
+                    )
+                })?;
+            }
+            smol::unblock(move || copy_tree(&from_clone, &to_clone))
+                .await
+                .map_err(|e| {
+                    ElectronError::IoError(
+                        format!("Failed to copy electron files into {}.", to.display()),
+                        e,
+                    )
+                })?;
+        }
         Ok(Electron {
             exe: to.join(
                 self.exe()
-                    .file_name()
-                    .expect("BUG: This definitely should have had a file name."),
+                    .strip_prefix(&self.dist_dir)
+                    .expect("BUG: The exe should be inside the distribution."),
             ),
+            dist_dir: to.to_owned(),
             version: self.version.clone(),
             os: self.os.clone(),
             arch: self.arch.clone(),
+            node: self.node.clone(),
+            chrome: self.chrome.clone(),
+            abi: self.abi.clone(),
         })
     }
 }
 
+#[derive(Default)]
 pub struct ElectronOpts {
     force: Option<bool>,
     range: Option<Range>,
     include_prerelease: Option<bool>,
-}
-
-impl Default for ElectronOpts {
-    fn default() -> Self {
-        Self {
-            force: None,
-            range: None,
-            include_prerelease: None,
-        }
-    }
+    verify_launch: Option<bool>,
+    verify_exe: Option<bool>,
+    project_dir: Option<PathBuf>,
+    progress_bar: Option<bool>,
+    always_resolve: Option<bool>,
+    mirror: Option<Url>,
+    proxy: Option<Url>,
+    timeout: Option<Duration>,
+    client: OnceCell<reqwest::Client>,
+    github_token: Option<String>,
+    max_retries: Option<u32>,
+    retry_delay: Option<Duration>,
+    retry_prompt: Option<Arc<dyn RetryPrompt>>,
+    offline: Option<bool>,
+    platform: Option<Platform>,
+    arch: Option<String>,
+    post_extract: Option<PostExtract>,
+    cache_dir: Option<PathBuf>,
+    on_event: Vec<OnEvent>,
 }
 
 impl ElectronOpts {
@@ -114,59 +319,284 @@ impl ElectronOpts {
         self
     }
 
-    pub async fn ensure_electron(self) -> Result<Electron, ElectronError> {
-        let dirs = ProjectDirs::from("", "", "collider").ok_or(ElectronError::NoProjectDir)?;
-        let range = self.range.clone().unwrap_or_else(Range::any);
-        let os = match std::env::consts::OS {
-            "windows" => "win32",
-            "macos" => "darwin",
-            "linux" => "linux",
-            // TODO: "mas"?
-            _ => {
-                return Err(ElectronError::UnsupportedPlatform(
-                    std::env::consts::OS.into(),
-                ))
-            }
+    /// After downloading Electron for the current host, make sure it actually
+    /// launches and reports the version we asked for.
+    pub fn verify_launch(mut self, verify_launch: bool) -> Self {
+        self.verify_launch = Some(verify_launch);
+        self
+    }
+
+    /// Before using an already-cached Electron for the current host, make
+    /// sure it still launches and reports its version. A broken build (say,
+    /// one that's lost some of its libraries) is deleted and downloaded
+    /// again, or is an error when [`offline`](Self::offline).
+    pub fn verify_exe(mut self, verify_exe: bool) -> Self {
+        self.verify_exe = Some(verify_exe);
+        self
+    }
+
+    /// Directory to look for a project-pinned Electron version in (see
+    /// [`project_electron_range`]). Only used if no [`range`](Self::range)
+    /// was given.
+    pub fn project_dir(mut self, project_dir: impl AsRef<Path>) -> Self {
+        self.project_dir = Some(project_dir.as_ref().to_owned());
+        self
+    }
+
+    /// Show a progress bar on stderr while downloading Electron. Off by
+    /// default, so library consumers don't get terminal output they didn't
+    /// ask for.
+    pub fn progress_bar(mut self, progress_bar: bool) -> Self {
+        self.progress_bar = Some(progress_bar);
+        self
+    }
+
+    /// Always look up the newest release matching the range, instead of
+    /// taking the fast path and reusing the current collider version's
+    /// Electron when that already satisfies it.
+    pub fn always_resolve(mut self, always_resolve: bool) -> Self {
+        self.always_resolve = Some(always_resolve);
+        self
+    }
+
+    /// Never touch the network: only pick from Electron builds that are
+    /// already cached. The newest cached build satisfying the range is used,
+    /// and it's an error if there isn't one.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = Some(offline);
+        self
+    }
+
+    /// Get Electron for `platform` instead of the platform collider is
+    /// running on, e.g. [`Platform::Mas`] to package for the Mac App Store.
+    /// Builds for another platform can't be launched here.
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Get Electron for `arch` (one of [`ARCHS`], e.g. `armv7l`) instead of
+    /// the architecture collider is running on. Like
+    /// [`platform`](Self::platform), this is for cross-targeting.
+    pub fn arch(mut self, arch: &str) -> Self {
+        self.arch = Some(arch.into());
+        self
+    }
+
+    /// Download Electron (and its checksums) from this mirror instead of
+    /// GitHub releases. Takes precedence over the `COLLIDER_ELECTRON_MIRROR`
+    /// and `ELECTRON_MIRROR` environment variables.
+    pub fn mirror(mut self, mirror: Url) -> Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
+    /// Send every request through this proxy, instead of whatever
+    /// `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY` say. See
+    /// [`build_http_client`].
+    pub fn proxy(mut self, proxy: Url) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Give up on any request that hasn't finished after `timeout`. That
+    /// includes reading the whole response, so leave room for downloading a
+    /// full Electron build. Timed-out requests are retried like other
+    /// transient failures. There's no timeout by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// GitHub API token to authenticate downloads from GitHub with, instead
+    /// of the one in [`GITHUB_TOKEN_ENVS`]. It's never sent to mirrors.
+    pub fn github_token(mut self, github_token: impl AsRef<str>) -> Self {
+        self.github_token = Some(github_token.as_ref().into());
+        self
+    }
+
+    /// How many times to retry downloads that fail with transient errors
+    /// (dropped connections, timeouts, 5xx responses). Defaults to
+    /// [`DEFAULT_MAX_RETRIES`].
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// How long to wait before the first retry of a failed download. Each
+    /// retry after that waits twice as long. Defaults to
+    /// [`DEFAULT_RETRY_DELAY`].
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = Some(retry_delay);
+        self
+    }
+
+    /// Ask `prompt` whether to retry transient download failures, instead of
+    /// retrying automatically up to [`max_retries`](Self::max_retries)
+    /// times. Meant for interactive sessions.
+    pub fn retry_prompt(mut self, prompt: impl RetryPrompt + 'static) -> Self {
+        self.retry_prompt = Some(Arc::new(prompt));
+        self
+    }
+
+    /// Run `hook` on every freshly extracted Electron build, after
+    /// collider's own fixups for known-broken releases. It's called with the
+    /// build's directory, its version, and its platform (`darwin`, `mas`,
+    /// ...), and a failure fails the download.
+    pub fn post_extract(
+        mut self,
+        hook: impl Fn(&Path, &Version, &str) -> std::io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.post_extract = Some(Arc::new(hook));
+        self
+    }
+
+    /// Call `hook` with each [`ElectronEvent`] as
+    /// [`ensure_electron`](Self::ensure_electron) goes along, e.g. to show
+    /// its progress in a GUI. Can be called more than once to add more hooks.
+    pub fn on_event(mut self, hook: impl Fn(ElectronEvent) + Send + Sync + 'static) -> Self {
+        self.on_event.push(Arc::new(hook));
+        self
+    }
+
+    /// Keep downloaded Electron builds in `cache_dir`, instead of
+    /// [`CACHE_DIR_ENV`] or the platform's usual directories. See
+    /// [`CacheDirs::resolve`].
+    pub fn cache_dir(mut self, cache_dir: impl AsRef<Path>) -> Self {
+        self.cache_dir = Some(cache_dir.as_ref().to_owned());
+        self
+    }
+
+    /// The `Authorization` header collider sends along when fetching `url`,
+    /// if any. Only GitHub URLs get the [`github_token`](Self::github_token),
+    /// or the token from the environment if there isn't one.
+    pub fn github_auth_header(&self, url: &str) -> Option<String> {
+        github_auth_header(url, self.github_token.as_deref())
+    }
+
+    async fn retrying<T, F, Fut>(&self, op: F) -> Result<T, ElectronError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ElectronError>>,
+    {
+        let policy = RetryPolicy {
+            max_retries: self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            base_delay: self.retry_delay.unwrap_or(DEFAULT_RETRY_DELAY),
+        };
+        retry_with_prompt(policy, self.retry_prompt.as_deref(), op).await
+    }
+
+    /// The client every request goes through, built on first use so that
+    /// connections are kept alive from one request to the next.
+    fn client(&self) -> Result<&reqwest::Client, ElectronError> {
+        self.client
+            .get_or_try_init(|| build_http_client(self.proxy.as_ref(), self.timeout))
+    }
+
+    fn get(&self, url: &str) -> Result<reqwest::RequestBuilder, ElectronError> {
+        let req = self.client()?.get(url);
+        Ok(match self.github_auth_header(url) {
+            Some(auth) => req.header(reqwest::header::AUTHORIZATION, auth),
+            None => req,
+        })
+    }
+
+    /// Sends `req`, turning rate-limited responses into
+    /// [`ElectronError::Throttled`] so retries know to wait them out.
+    async fn send(&self, req: reqwest::RequestBuilder) -> Result<reqwest::Response, ElectronError> {
+        // reqwest starts the timeout's timer as soon as `send` is called, so
+        // that has to happen inside the Tokio context too.
+        check_throttled(async { req.send().await }.compat().await?)
+    }
+
+    /// Every released Electron version matching the configured range (and
+    /// prerelease setting), newest first. This only looks at the release
+    /// list, and never downloads or caches anything.
+    pub async fn list_available_versions(&self) -> Result<Vec<Version>, ElectronError> {
+        let range = self.requested_range().await?;
+        let releases = self.fetch_releases().await?;
+        Ok(
+            matching_releases(releases, &range, self.include_prerelease.unwrap_or(false))
+                .into_iter()
+                .map(|pkg| pkg.version)
+                .collect(),
+        )
+    }
+
+    /// The range set with [`ElectronOpts::range`], or else whatever the
+    /// project pins, or else any version at all.
+    async fn requested_range(&self) -> Result<Range, ElectronError> {
+        Ok(match (&self.range, &self.project_dir) {
+            (Some(range), _) => range.clone(),
+            (None, Some(dir)) => project_electron_range(dir)
+                .await?
+                .unwrap_or_else(Range::any),
+            (None, None) => Range::any(),
+        })
+    }
+
+    pub async fn ensure_electron(mut self) -> Result<Electron, ElectronError> {
+        if self.progress_bar.unwrap_or(false) {
+            self.on_event.push(events::progress_bar());
         }
-        .to_string();
-        let arch = match std::env::consts::ARCH {
-            "x86" => "ia32",
-            "x86_64" => "x64",
-            "aarch64" => "arm64",
-            _ => {
-                return Err(ElectronError::UnsupportedArch(
-                    std::env::consts::ARCH.into(),
-                ))
-            }
+        let dirs = CacheDirs::resolve(self.cache_dir.as_deref())?;
+        let builds = dirs.builds.clone();
+        if let Err(e) = smol::unblock(move || cache::remove_stale_extractions(&builds)).await {
+            tracing::debug!("Couldn't clean up stale extractions: {}", e);
         }
-        .to_string();
+        self.emit(ElectronEvent::ResolvingVersion);
+        let range = self.requested_range().await?;
+        let os = self.target_platform()?;
+        let arch = self.target_arch()?;
 
         // First, we check to see if we can get a concrete version based on
         // what we have. This is a fast path that completely avoids external
         // requests.
         tracing::debug!("Looking up current collider version.");
-        if let Some(version) = self.current_collider_version().await? {
+        if let Some(version) = self.fast_path_version().await? {
             if !self.force.unwrap_or(false) && range.satisfies(&version) {
                 let triple = self.get_target_triple(&version, &os, &arch)?;
-                let exe = dirs
-                    .data_local_dir()
-                    .join(&triple)
-                    .join(self.get_exe_name());
-                if fs::metadata(&exe).await.is_ok() {
+                let dist_dir = dirs.builds.join(&triple);
+                let exe = dist_dir.join(self.get_exe_name());
+                if fs::metadata(&exe).await.is_ok()
+                    && self
+                        .cached_build_works(&exe, &dist_dir, &version, &os, &arch)
+                        .await?
+                {
+                    self.emit(ElectronEvent::VersionResolved(version.clone()));
+                    self.emit(ElectronEvent::Done);
                     return Ok(Electron {
                         exe,
+                        dist_dir,
                         os,
                         arch,
                         version: version.clone(),
+                        node: None,
+                        chrome: None,
+                        abi: None,
                     });
                 }
             }
         }
 
+        if self.offline.unwrap_or(false) {
+            tracing::debug!("Offline: picking from cached Electron builds.");
+            let electron = self.offline_electron(&range, &os, &arch, &dirs.builds)?;
+            if self.verify_exe.unwrap_or(false) && os == host_platform()? && arch == host_arch()? {
+                // There's no getting another copy, so a broken one is fatal.
+                verify_launch(&electron.exe, &electron.version, VERIFY_EXE_TIMEOUT).await?;
+            }
+            self.emit(ElectronEvent::VersionResolved(electron.version.clone()));
+            self.emit(ElectronEvent::Done);
+            return Ok(electron);
+        }
+
         tracing::debug!("Current collider version missing or not useable. Looking up matching Electron releases.");
-        let version = self.pick_electron_version(&range).await?;
+        let release = self.pick_electron_release(&range).await?;
+        let version = release.version;
+        self.emit(ElectronEvent::VersionResolved(version.clone()));
         let triple = self.get_target_triple(&version, &os, &arch)?;
-        let dest = dirs.data_local_dir().join(&triple).to_owned();
+        let dest = dirs.builds.join(&triple);
 
         tracing::info!(
             "Selected electron@{version} ({triple})",
@@ -174,60 +604,151 @@ impl ElectronOpts {
             triple = triple
         );
 
+        let cached_exe = dest.join(self.get_exe_name());
+        let verified_cached = !self.force.unwrap_or(false)
+            && self.verify_exe.unwrap_or(false)
+            && fs::metadata(&cached_exe).await.is_ok()
+            && self
+                .cached_build_works(&cached_exe, &dest, &version, &os, &arch)
+                .await?;
+
         let zip = self.pick_electron_zip(&version, &triple);
+        let shasums = self.pick_shasums_url(&version);
         let exe = self
-            .ensure_electron_exe(&dirs, &dest, &zip, &triple)
+            .ensure_electron_exe(&dirs, &dest, &zip, &shasums, &triple)
             .await?;
+        // Cross-target downloads can't be launched here, so there's nothing
+        // to verify for those.
+        if self.verify_launch.unwrap_or(false)
+            && !verified_cached
+            && os == host_platform()?
+            && arch == host_arch()?
+        {
+            tracing::debug!("Verifying that {} launches.", exe.display());
+            verify_launch(&exe, &version, VERIFY_LAUNCH_TIMEOUT).await?;
+        }
+        self.emit(ElectronEvent::Done);
         Ok(Electron {
             exe,
+            dist_dir: dest,
             version,
             os,
             arch,
+            node: release.node,
+            chrome: release.chrome,
+            abi: release.modules,
         })
     }
 
-    async fn current_collider_version(&self) -> Result<Option<Version>, ElectronError> {
-        for parent in std::env::current_exe()
-            .map_err(ElectronError::CurrentExeFailure)?
-            .parent()
-            .expect("this should definitely have a parent")
-            .ancestors()
-        {
-            let pkg_path = parent.join("package.json");
-            if fs::metadata(&pkg_path).await.is_ok() {
-                let pkg_src = fs::read_to_string(&pkg_path).await.map_err(|e| {
-                    ElectronError::IoError(format!("Failed to read {}", pkg_path.display()), e)
-                })?;
-                let pkg: PackageJson = serde_json::from_str(&pkg_src).map_err(|e| {
-                    ElectronError::from_json_err(e, pkg_path.display().to_string(), pkg_src)
+    /// With [`verify_exe`](Self::verify_exe), checks that the cached build
+    /// in `dist_dir` launches. If it doesn't, it's deleted so it gets
+    /// downloaded again. Builds for other targets can't be checked here, and
+    /// are always assumed to work.
+    async fn cached_build_works(
+        &self,
+        exe: &Path,
+        dist_dir: &Path,
+        version: &Version,
+        os: &str,
+        arch: &str,
+    ) -> Result<bool, ElectronError> {
+        if !self.verify_exe.unwrap_or(false) || os != host_platform()? || arch != host_arch()? {
+            return Ok(true);
+        }
+        tracing::debug!("Verifying that cached {} launches.", exe.display());
+        match verify_launch(exe, version, VERIFY_EXE_TIMEOUT).await {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                tracing::warn!("{} Downloading it again.", e);
+                fs::remove_dir_all(dist_dir).await.map_err(|e| {
+                    ElectronError::IoError(
+                        format!("Failed to remove broken build at {}", dist_dir.display()),
+                        e,
+                    )
                 })?;
-                if pkg.name == "collider" {
-                    return Ok(Some(pkg.version));
-                }
+                Ok(false)
             }
         }
-        Ok(None)
     }
 
-    async fn pick_electron_version(&self, range: &Range) -> Result<Version, ElectronError> {
-        if let Some(version) = self.current_collider_version().await? {
+    fn offline_electron(
+        &self,
+        range: &Range,
+        os: &str,
+        arch: &str,
+        cache_dir: &Path,
+    ) -> Result<Electron, ElectronError> {
+        let (version, dist_dir) = best_cached(
+            range,
+            os,
+            arch,
+            self.include_prerelease.unwrap_or(false),
+            cache_dir,
+        )
+        .ok_or_else(|| ElectronError::NoCachedVersionOffline(range.clone()))?;
+        tracing::info!("Using cached electron@{} (offline)", version);
+        Ok(Electron {
+            exe: dist_dir.join(exe_name(os)),
+            dist_dir,
+            version,
+            os: os.into(),
+            arch: arch.into(),
+            node: None,
+            chrome: None,
+            abi: None,
+        })
+    }
+
+    /// The current collider version, unless we've been told to always
+    /// resolve against the release list instead.
+    async fn fast_path_version(&self) -> Result<Option<Version>, ElectronError> {
+        if self.always_resolve.unwrap_or(false) {
+            tracing::debug!("Skipping fast path: always resolving versions.");
+            return Ok(None);
+        }
+        current_collider_version().await
+    }
+
+    async fn pick_electron_release(&self, range: &Range) -> Result<PackageJson, ElectronError> {
+        if let Some(version) = self.fast_path_version().await? {
             if range.satisfies(&version) {
-                return Ok(version);
+                return Ok(PackageJson {
+                    name: "electron".into(),
+                    version,
+                    node: None,
+                    chrome: None,
+                    modules: None,
+                });
             }
         }
 
-        let releases: Vec<PackageJson> =
-            reqwest::get("https://releases.electronjs.org/releases.json")
-                .compat()
+        let releases = self.fetch_releases().await?;
+        matching_releases(releases, range, self.include_prerelease.unwrap_or(false))
+            .into_iter()
+            .next()
+            .ok_or_else(|| ElectronError::MatchingVersionNotFound(range.clone()))
+    }
+
+    async fn fetch_releases(&self) -> Result<Vec<PackageJson>, ElectronError> {
+        let json = self.fetch_releases_json().await?;
+        serde_json::from_str(&json)
+            .map_err(|e| ElectronError::from_json_err(e, RELEASES_JSON_URL.into(), json))
+    }
+
+    /// The raw releases.json that versions get resolved against, fetched
+    /// through the same client (and retries) as everything else, for callers
+    /// that want to keep their own copy of it.
+    pub async fn fetch_releases_json(&self) -> Result<String, ElectronError> {
+        self.retrying(|| async {
+            Ok(self
+                .send(self.get(RELEASES_JSON_URL)?)
                 .await?
-                .json()
+                .error_for_status()?
+                .text()
                 .compat()
-                .await?;
-        releases
-            .iter()
-            .find(|pkg| range.satisfies(&pkg.version))
-            .map(|pkg| pkg.version.clone())
-            .ok_or_else(|| ElectronError::MatchingVersionNotFound(range.clone()))
+                .await?)
+        })
+        .await
     }
 
     fn get_target_triple(
@@ -239,18 +760,81 @@ impl ElectronOpts {
         Ok(format!("v{}-{}-{}", version, os, arch))
     }
 
+    fn download_base(&self, version: &Version) -> String {
+        let mirror = self
+            .mirror
+            .as_ref()
+            .map(|url| url.to_string())
+            .or_else(|| std::env::var("COLLIDER_ELECTRON_MIRROR").ok())
+            .or_else(|| std::env::var("ELECTRON_MIRROR").ok());
+        let custom_dir = std::env::var("ELECTRON_CUSTOM_DIR").ok();
+        electron_download_base(mirror.as_deref(), custom_dir.as_deref(), version)
+    }
+
     fn pick_electron_zip(&self, version: &Version, triple: &str) -> String {
-        format!(
-            "https://github.com/electron/electron/releases/download/v{}/electron-{}.zip",
-            version, triple
-        )
+        format!("{}/electron-{}.zip", self.download_base(version), triple)
+    }
+
+    fn pick_shasums_url(&self, version: &Version) -> String {
+        format!("{}/SHASUMS256.txt", self.download_base(version))
+    }
+
+    /// Checks the downloaded zip against the release's SHASUMS256.txt. If the
+    /// checksums can't be fetched at all (some mirrors don't carry them), we
+    /// warn and carry on, but a mismatch is always an error.
+    async fn verify_checksum(
+        &self,
+        shasums_url: &str,
+        file_name: &str,
+        actual: &str,
+    ) -> Result<(), ElectronError> {
+        tracing::debug!("Fetching checksums from {}", shasums_url);
+        let shasums = match self
+            .retrying(|| async {
+                Ok(self
+                    .send(self.get(shasums_url)?)
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .compat()
+                    .await?)
+            })
+            .await
+        {
+            Ok(shasums) => shasums,
+            Err(e) => {
+                tracing::warn!(
+                    "Could not fetch checksums for {}, so it won't be verified: {}",
+                    file_name,
+                    e
+                );
+                return Ok(());
+            }
+        };
+        match expected_checksum(&shasums, file_name) {
+            Some(expected) if expected.eq_ignore_ascii_case(actual) => Ok(()),
+            Some(expected) => Err(ElectronError::ChecksumMismatch {
+                file: file_name.into(),
+                expected: expected.into(),
+                actual: actual.into(),
+            }),
+            None => {
+                tracing::warn!(
+                    "{} isn't listed in {}, so it won't be verified.",
+                    file_name,
+                    shasums_url
+                );
+                Ok(())
+            }
+        }
     }
 
     async fn ensure_electron_exe(
         &self,
-        dirs: &ProjectDirs,
+        dirs: &CacheDirs,
         dest: &Path,
         zip: &str,
+        shasums: &str,
         triple: &str,
     ) -> Result<PathBuf, ElectronError> {
         if self.force.unwrap_or(false) || fs::metadata(&dest).await.is_err() {
@@ -264,7 +848,7 @@ impl ElectronOpts {
                     e,
                 )
             })?;
-            let cache = dirs.cache_dir();
+            let cache = &dirs.downloads;
             fs::create_dir_all(cache).await.map_err(|e| {
                 ElectronError::IoError(
                     format!("Failed to create cache directory, at {}", cache.display()),
@@ -272,58 +856,70 @@ impl ElectronOpts {
                 )
             })?;
 
-            tracing::debug!("Fetching zip file from {}", zip);
-            let mut res = reqwest::get(zip.to_string()).compat().await?;
-            let zip_dest = cache.join(format!("electron-{}.zip", triple));
-
-            tracing::debug!("Writing zip file to {}", zip_dest.display());
-            let mut file = fs::File::create(&zip_dest).await.map_err(|e| {
-                ElectronError::IoError(
-                    format!("Failed to create file at {}.", zip_dest.display()),
-                    e,
-                )
-            })?;
-            let mut written = 0;
-            while let Some(chunk) = res.chunk().compat().await? {
-                file.write_all(chunk.as_ref()).await.map_err(|e| {
-                    ElectronError::IoError(format!("Failed to read data chunk from {}", zip), e)
-                })?;
-                written += chunk.len();
-            }
-            file.flush().await.map_err(|e| {
-                ElectronError::IoError(
-                    format!("Failed to flush out file handle for {}", zip_dest.display()),
-                    e,
-                )
-            })?;
-            std::mem::drop(file);
-            tracing::debug!("Wrote {} bytes to zip file", written);
-
-            let dest = dest.to_owned();
-            tracing::debug!("Extracting zip file to {}", dest.display());
-            let zip_dest_clone = zip_dest.clone();
-            smol::unblock(move || -> Result<(), ElectronError> {
-                let fd = std::fs::File::open(&zip_dest).map_err(|e| {
+            let zip_name = format!("electron-{}.zip", triple);
+            let zip_dest = cache.join(&zip_name);
+            // --force means starting completely fresh, so any partial
+            // download from an earlier attempt goes too.
+            let mut resume = !self.force.unwrap_or(false);
+            if !resume && fs::metadata(&zip_dest).await.is_ok() {
+                tracing::debug!("Discarding partial download at {}", zip_dest.display());
+                fs::remove_file(&zip_dest).await.map_err(|e| {
                     ElectronError::IoError(
-                        format!("Failed to open file at {}.", zip_dest.display()),
+                        format!(
+                            "Failed to remove partial download at {}.",
+                            zip_dest.display()
+                        ),
                         e,
                     )
                 })?;
-                let mut archive = zip::ZipArchive::new(fd)?;
-                // TODO: move this to its own method and do it manually, then
-                // manually handle symlinks to make it work on macOS:
-                // https://github.com/zip-rs/zip/pull/213
-                archive.extract(&dest)?;
-                Ok(())
-            })
-            .await?;
+            }
+            loop {
+                // Retries pick up where the failed attempt left off, unless
+                // we're not resuming at all.
+                let (checksum, resumed) = self
+                    .retrying(|| self.download_zip(zip, &zip_dest, triple, resume))
+                    .await?;
+                match self.verify_checksum(shasums, &zip_name, &checksum).await {
+                    Ok(()) => break,
+                    Err(e) => {
+                        // Don't leave a bad zip lying around.
+                        let _ = fs::remove_file(&zip_dest).await;
+                        if !resumed {
+                            return Err(e);
+                        }
+                        // The partial file we picked up might have been bad
+                        // to begin with. Give it one clean shot.
+                        tracing::warn!(
+                            "Resumed download of {} failed verification. Downloading it again from scratch.",
+                            zip_name
+                        );
+                        resume = false;
+                    }
+                }
+            }
+
+            // Extract next to the final directory and only move it into
+            // place once it's complete, so an interrupted extraction never
+            // looks like a cached build.
+            self.emit(ElectronEvent::Extracting);
+            let tmp = cache::extraction_dir(dest);
+            if fs::metadata(&tmp).await.is_ok() {
+                tracing::debug!("Removing leftover extraction at {}", tmp.display());
+                let _ = fs::remove_dir_all(&tmp).await;
+            }
+            tracing::debug!("Extracting zip file to {}", tmp.display());
+            if let Err(e) = self.extract_build(&zip_dest, &tmp, triple).await {
+                let _ = fs::remove_dir_all(&tmp).await;
+                return Err(e);
+            }
+            cache::move_into_place(&tmp, dest, self.force.unwrap_or(false)).await?;
 
             tracing::debug!("Deleting zip file. We don't need it anymore.");
-            fs::remove_file(&zip_dest_clone).await.map_err(|e| {
+            fs::remove_file(&zip_dest).await.map_err(|e| {
                 ElectronError::IoError(
                     format!(
                         "Failed to remove temporary zip file at {}.",
-                        zip_dest_clone.display()
+                        zip_dest.display()
                     ),
                     e,
                 )
@@ -332,12 +928,977 @@ impl ElectronOpts {
         Ok(dest.join(self.get_exe_name()))
     }
 
+    /// Extracts the zip at `zip` into `dir`, and gets it ready to run.
+    async fn extract_build(
+        &self,
+        zip: &Path,
+        dir: &Path,
+        triple: &str,
+    ) -> Result<(), ElectronError> {
+        let zip = zip.to_owned();
+        let dest = dir.to_owned();
+        let exe_name = self.get_exe_name();
+        smol::unblock(move || -> Result<(), ElectronError> {
+            let fd = std::fs::File::open(&zip).map_err(|e| {
+                ElectronError::IoError(format!("Failed to open file at {}.", zip.display()), e)
+            })?;
+            let mut archive = zip::ZipArchive::new(fd)?;
+            extract_zip(&mut archive, &dest)?;
+            // Zips made without Unix modes would otherwise leave this
+            // unlaunchable.
+            let exe = dest.join(exe_name);
+            ensure_executable(&exe).map_err(|e| {
+                ElectronError::IoError(format!("Failed to make {} executable", exe.display()), e)
+            })
+        })
+        .await?;
+        self.fix_up_extracted(dir, triple).await
+    }
+
+    /// Applies any built-in fixups for this exact release, then the user's
+    /// [`post_extract`](Self::post_extract) hook, to the build just extracted
+    /// into `dest`.
+    async fn fix_up_extracted(&self, dest: &Path, triple: &str) -> Result<(), ElectronError> {
+        let (version, platform, _) = match parse_triple(triple) {
+            Some(parsed) => parsed,
+            None => return Ok(()),
+        };
+        let dir = dest.to_owned();
+        let hook = self.post_extract.clone();
+        smol::unblock(move || {
+            apply_fixups(FIXUPS, &dir, &version, &platform)?;
+            match hook {
+                Some(hook) => hook(&dir, &version, &platform),
+                None => Ok(()),
+            }
+        })
+        .await
+        .map_err(|e| {
+            ElectronError::IoError(
+                format!("Failed to fix up extracted Electron at {}", dest.display()),
+                e,
+            )
+        })
+    }
+
+    /// Downloads `url` to `zip_dest`, returning the hex SHA-256 of the whole
+    /// file and whether an earlier partial download was resumed. When
+    /// `resume` is set and a partial file is already there, only the rest of
+    /// it is requested. Servers that don't support ranges get a full
+    /// download instead.
+    async fn download_zip(
+        &self,
+        url: &str,
+        zip_dest: &Path,
+        triple: &str,
+        resume: bool,
+    ) -> Result<(String, bool), ElectronError> {
+        let mut existing = if resume {
+            fs::metadata(zip_dest)
+                .await
+                .map(|meta| meta.len())
+                .ok()
+                .filter(|len| *len > 0)
+        } else {
+            None
+        };
+
+        tracing::debug!("Fetching zip file from {}", url);
+        let mut req = self.get(url)?;
+        if let Some(existing) = existing {
+            tracing::debug!("Resuming download from byte {}", existing);
+            req = req.header(RANGE, format!("bytes={}-", existing));
+        }
+        let mut res = self.send(req).await?;
+        if existing.is_some() && res.status() != StatusCode::PARTIAL_CONTENT {
+            // A 200 means the server ignored the range, and we can just use
+            // it from the start. Anything else (like a 416 because the file
+            // is somehow already longer than it should be) means starting
+            // over with a plain request.
+            tracing::debug!(
+                "Server responded with {} to a range request. Restarting download.",
+                res.status()
+            );
+            existing = None;
+            if !res.status().is_success() {
+                res = self.send(self.get(url)?).await?;
+            }
+        }
+        let mut res = res.error_for_status()?;
+
+        let mut hasher = Sha256::new();
+        let mut file = match existing {
+            Some(_) => {
+                // The checksum covers the whole file, so the bytes we
+                // already have need to go through the hasher too.
+                let path = zip_dest.to_owned();
+                hasher = smol::unblock(move || {
+                    let mut existing = std::fs::File::open(&path)?;
+                    std::io::copy(&mut existing, &mut hasher)?;
+                    Ok::<_, std::io::Error>(hasher)
+                })
+                .await
+                .map_err(|e| {
+                    ElectronError::IoError(
+                        format!("Failed to read partial download at {}.", zip_dest.display()),
+                        e,
+                    )
+                })?;
+                fs::OpenOptions::new().append(true).open(zip_dest).await
+            }
+            None => fs::File::create(zip_dest).await,
+        }
+        .map_err(|e| {
+            ElectronError::IoError(
+                format!("Failed to create file at {}.", zip_dest.display()),
+                e,
+            )
+        })?;
+
+        tracing::debug!("Writing zip file to {}", zip_dest.display());
+        let offset = existing.unwrap_or(0);
+        let total = res.content_length().map(|len| len + offset);
+        self.emit(ElectronEvent::DownloadStarted {
+            triple: triple.into(),
+            total,
+        });
+        let mut written = offset;
+        self.emit(ElectronEvent::DownloadProgress {
+            done: written,
+            total,
+        });
+        while let Some(chunk) = res.chunk().compat().await? {
+            file.write_all(chunk.as_ref()).await.map_err(|e| {
+                ElectronError::IoError(format!("Failed to read data chunk from {}", url), e)
+            })?;
+            hasher.update(&chunk);
+            written += chunk.len() as u64;
+            self.emit(ElectronEvent::DownloadProgress {
+                done: written,
+                total,
+            });
+        }
+        file.flush().await.map_err(|e| {
+            ElectronError::IoError(
+                format!("Failed to flush out file handle for {}", zip_dest.display()),
+                e,
+            )
+        })?;
+        tracing::debug!("Wrote {} bytes to zip file", written);
+        Ok((hex::encode(hasher.finalize()), existing.is_some()))
+    }
+
+    fn emit(&self, event: ElectronEvent) {
+        for hook in &self.on_event {
+            hook(event.clone());
+        }
+    }
+
+    fn target_platform(&self) -> Result<String, ElectronError> {
+        match self.platform {
+            Some(platform) => Ok(platform.to_string()),
+            None => Ok(host_platform()?.to_string()),
+        }
+    }
+
+    fn target_arch(&self) -> Result<String, ElectronError> {
+        match &self.arch {
+            Some(arch) if ARCHS.contains(&arch.as_str()) => Ok(arch.clone()),
+            Some(arch) => Err(ElectronError::UnsupportedArch(arch.clone())),
+            None => Ok(host_arch()?.to_string()),
+        }
+    }
+
     fn get_exe_name(&self) -> String {
-        match std::env::consts::OS {
-            "windows" => "electron.exe".into(),
-            "macos" => "Electron.app/Contents/MacOS/Electron".into(),
-            "linux" => "electron".into(),
-            _ => "electron".into(),
+        exe_name(&self.target_platform().unwrap_or_else(|_| "linux".into())).into()
+    }
+}
+
+/// Environment variables a GitHub token is read from when none is given
+/// explicitly, in order of preference. The same ones `gh` uses.
+pub const GITHUB_TOKEN_ENVS: &[&str] = &["GH_TOKEN", "GITHUB_TOKEN"];
+
+/// The `explicit` token, or else the first non-empty one in
+/// [`GITHUB_TOKEN_ENVS`], looked up with `var`.
+fn resolve_github_token(
+    explicit: Option<&str>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    explicit.map(String::from).or_else(|| {
+        GITHUB_TOKEN_ENVS
+            .iter()
+            .filter_map(|name| var(name))
+            .find(|token| !token.is_empty())
+    })
+}
+
+/// The `Authorization` header to send along when fetching `url`, if any.
+/// Only GitHub URLs get one: `github_token` if given, or else the token from
+/// [`GITHUB_TOKEN_ENVS`].
+pub fn github_auth_header(url: &str, github_token: Option<&str>) -> Option<String> {
+    let token = resolve_github_token(github_token, |var| std::env::var(var).ok())?;
+    let host = Url::parse(url).ok()?.host_str()?.to_owned();
+    if host == "github.com" || host.ends_with(".github.com") {
+        Some(format!("token {}", token))
+    } else {
+        None
+    }
+}
+
+const RELEASES_JSON_URL: &str = "https://releases.electronjs.org/releases.json";
+
+const DEFAULT_MIRROR: &str = "https://github.com/electron/electron/releases/download/";
+
+/// The HTTP client for everything collider downloads. reqwest already sends
+/// requests through the proxies in `HTTPS_PROXY`, `HTTP_PROXY` and
+/// `ALL_PROXY` (minus any hosts in `NO_PROXY`). An explicit `proxy` replaces
+/// all of those. `timeout` covers each whole request, response body included.
+pub fn build_http_client(
+    proxy: Option<&Url>,
+    timeout: Option<Duration>,
+) -> Result<reqwest::Client, ElectronError> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy.clone())?);
+    }
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    Ok(builder.build()?)
+}
+
+/// Builds the base URL for a release's files the same way `@electron/get`
+/// does: `<mirror>/<custom dir>`. The custom dir defaults to `v<version>`,
+/// and can use `{{ version }}` as a placeholder for the bare version number.
+fn electron_download_base(
+    mirror: Option<&str>,
+    custom_dir: Option<&str>,
+    version: &Version,
+) -> String {
+    let mirror = mirror.unwrap_or(DEFAULT_MIRROR);
+    let dir = match custom_dir {
+        Some(dir) => dir.replace("{{ version }}", &version.to_string()),
+        None => format!("v{}", version),
+    };
+    format!("{}/{}", mirror.trim_end_matches('/'), dir.trim_matches('/'))
+}
+
+/// Looks up `file_name`'s checksum in the contents of a SHASUMS256.txt file,
+/// which has lines like `<sha256> *electron-v13.1.7-linux-x64.zip`.
+pub fn expected_checksum<'a>(shasums: &'a str, file_name: &str) -> Option<&'a str> {
+    shasums.lines().find_map(|line| {
+        let (sum, name) = line.trim().split_once(' ')?;
+        if name.trim_start_matches('*') == file_name {
+            Some(sum)
+        } else {
+            None
         }
+    })
+}
+
+/// How long to wait for `electron --version` before giving up on it.
+const VERIFY_LAUNCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The same, for builds that have launched before. The first launch can be
+/// slow while the OS vets the new binary, but later ones shouldn't be.
+const VERIFY_EXE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The version of Electron collider itself is running under, if it's running
+/// as part of an Electron app (found via the nearest `package.json` named
+/// `collider` above the current executable).
+pub async fn current_collider_version() -> Result<Option<Version>, ElectronError> {
+    for parent in std::env::current_exe()
+        .map_err(ElectronError::CurrentExeFailure)?
+        .parent()
+        .expect("this should definitely have a parent")
+        .ancestors()
+    {
+        let pkg_path = parent.join("package.json");
+        if fs::metadata(&pkg_path).await.is_ok() {
+            let pkg_src = fs::read_to_string(&pkg_path).await.map_err(|e| {
+                ElectronError::IoError(format!("Failed to read {}", pkg_path.display()), e)
+            })?;
+            let pkg: PackageJson = serde_json::from_str(&pkg_src).map_err(|e| {
+                ElectronError::from_json_err(e, pkg_path.display().to_string(), pkg_src)
+            })?;
+            if pkg.name == "collider" {
+                return Ok(Some(pkg.version));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The releases out of releases.json (which lists every Electron release)
+/// that match `range`, newest first. Prereleases are only considered when
+/// asked for.
+fn matching_releases(
+    releases: Vec<PackageJson>,
+    range: &Range,
+    include_prerelease: bool,
+) -> Vec<PackageJson> {
+    let mut matching = releases
+        .into_iter()
+        .filter(|pkg| {
+            (include_prerelease || !pkg.version.is_prerelease()) && range.satisfies(&pkg.version)
+        })
+        .collect::<Vec<_>>();
+    matching.sort_by(|a, b| b.version.cmp(&a.version));
+    matching
+}
+
+/// Like [`Electron::launch`], but for an Electron executable at an arbitrary
+/// path, with extra environment variables, and with control over what it
+/// gets for stdin.
+pub async fn launch_exe(
+    exe: &Path,
+    args: &[OsString],
+    env: &[(&str, &OsStr)],
+    stdin: Stdio,
+) -> Result<ExitStatus, ElectronError> {
+    tracing::debug!("Launching executable at {}", exe.display());
+    let status = Command::new(exe)
+        .args(args)
+        .envs(env.iter().copied())
+        .stdin(stdin)
+        .status()
+        .await
+        .map_err(|e| launch_failed(exe, e))?;
+    exit_status(status)
+}
+
+/// Which of Electron's output streams a captured line came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        }
+    }
+}
+
+/// Like [`launch_exe`], but with Electron's stdout and stderr piped instead
+/// of inherited. Each line is handed to `on_line` as soon as it's read, from
+/// whichever stream it came from.
+pub async fn launch_exe_captured(
+    exe: &Path,
+    args: &[OsString],
+    env: &[(&str, &OsStr)],
+    stdin: Stdio,
+    on_line: impl FnMut(OutputStream, &str),
+) -> Result<ExitStatus, ElectronError> {
+    tracing::debug!("Launching executable at {}", exe.display());
+    let mut child = Command::new(exe)
+        .args(args)
+        .envs(env.iter().copied())
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| launch_failed(exe, e))?;
+    // Both streams have to be drained to EOF at the same time: stopping at
+    // whichever ends first would drop the rest of the other, and could leave
+    // Electron blocked on a full pipe.
+    let on_line = Mutex::new(on_line);
+    future::try_zip(
+        read_lines(
+            child.stdout.take().expect("BUG: stdout is piped"),
+            OutputStream::Stdout,
+            &on_line,
+        ),
+        read_lines(
+            child.stderr.take().expect("BUG: stderr is piped"),
+            OutputStream::Stderr,
+            &on_line,
+        ),
+    )
+    .await?;
+    let status = child.status().await.map_err(|e| launch_failed(exe, e))?;
+    exit_status(status)
+}
+
+async fn read_lines(
+    reader: impl AsyncRead + Unpin,
+    stream: OutputStream,
+    on_line: &Mutex<impl FnMut(OutputStream, &str)>,
+) -> Result<(), ElectronError> {
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next().await {
+        let line = line.map_err(|e| {
+            ElectronError::IoError(format!("Failed to read Electron's {}", stream.as_str()), e)
+        })?;
+        (on_line.lock().expect("BUG: on_line panicked"))(stream, &line);
+    }
+    Ok(())
+}
+
+fn launch_failed(exe: &Path, e: std::io::Error) -> ElectronError {
+    ElectronError::IoError(format!("Failed to launch Electron at {}", exe.display()), e)
+}
+
+fn exit_status(status: ExitStatus) -> Result<ExitStatus, ElectronError> {
+    if status.success() {
+        Ok(status)
+    } else {
+        Err(ElectronError::ElectronFailed(status))
+    }
+}
+
+async fn verify_launch(
+    exe: &Path,
+    version: &Version,
+    timeout: Duration,
+) -> Result<(), ElectronError> {
+    let failed = |reason: String| ElectronError::LaunchVerificationFailed {
+        exe: exe.to_owned(),
+        reason,
+    };
+    let output = Command::new(exe)
+        .arg("--version")
+        .kill_on_drop(true)
+        .output();
+    let timeout = async {
+        Timer::after(timeout).await;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "timed out waiting for `electron --version`",
+        ))
+    };
+    let output = smol::future::or(output, timeout)
+        .await
+        .map_err(|e| failed(e.to_string()))?;
+    if !output.status.success() {
+        return Err(failed(format!(
+            "`electron --version` exited with {}",
+            output.status
+        )));
+    }
+    let reported = String::from_utf8_lossy(&output.stdout);
+    let reported = reported.trim().trim_start_matches('v');
+    if reported != version.to_string() {
+        return Err(failed(format!(
+            "expected version {}, but it reported `{}`",
+            version, reported
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_serializes_all_fields() {
+        let electron = Electron {
+            exe: PathBuf::from("/cache/v13.1.7-linux-x64/electron"),
+            dist_dir: PathBuf::from("/cache/v13.1.7-linux-x64"),
+            version: "13.1.7".parse().unwrap(),
+            os: "linux".into(),
+            arch: "x64".into(),
+            node: Some("14.16.0".into()),
+            chrome: Some("91.0.4472.164".into()),
+            abi: Some("89".into()),
+        };
+        let info = serde_json::to_value(electron.info()).unwrap();
+        assert_eq!(
+            info,
+            serde_json::json!({
+                "version": "13.1.7",
+                "os": "linux",
+                "arch": "x64",
+                "triple": "v13.1.7-linux-x64",
+                "exe": "/cache/v13.1.7-linux-x64/electron",
+                "dist_dir": "/cache/v13.1.7-linux-x64",
+                "abi": "89",
+                "node": "14.16.0",
+                "chrome": "91.0.4472.164",
+            })
+        );
+    }
+
+    #[test]
+    fn download_urls() {
+        let version = "13.1.7".parse().unwrap();
+        assert_eq!(
+            electron_download_base(None, None, &version),
+            "https://github.com/electron/electron/releases/download/v13.1.7"
+        );
+        assert_eq!(
+            electron_download_base(
+                Some("https://npmmirror.com/mirrors/electron"),
+                None,
+                &version
+            ),
+            "https://npmmirror.com/mirrors/electron/v13.1.7"
+        );
+        assert_eq!(
+            electron_download_base(
+                Some("https://mirror.example.com/electron/"),
+                Some("{{ version }}"),
+                &version,
+            ),
+            "https://mirror.example.com/electron/13.1.7"
+        );
+    }
+
+    #[test]
+    fn github_token_only_sent_to_github() {
+        let opts = ElectronOpts::new().github_token("abc123");
+        assert_eq!(
+            opts.github_auth_header(
+                "https://github.com/electron/electron/releases/download/v13.1.7/SHASUMS256.txt"
+            ),
+            Some("token abc123".into())
+        );
+        assert_eq!(
+            opts.github_auth_header(
+                "https://npmmirror.com/mirrors/electron/v13.1.7/SHASUMS256.txt"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn github_token_falls_back_to_env() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, val)| val.to_string())
+            }
+        };
+        let both = env(&[("GITHUB_TOKEN", "from-actions"), ("GH_TOKEN", "from-gh")]);
+        assert_eq!(
+            resolve_github_token(Some("explicit"), both),
+            Some("explicit".into())
+        );
+        assert_eq!(resolve_github_token(None, both), Some("from-gh".into()));
+        assert_eq!(
+            resolve_github_token(
+                None,
+                env(&[("GH_TOKEN", ""), ("GITHUB_TOKEN", "from-actions")])
+            ),
+            Some("from-actions".into())
+        );
+        assert_eq!(resolve_github_token(None, env(&[])), None);
+    }
+
+    #[test]
+    fn shasums_lookup() {
+        let shasums = "\
+            aaaa *electron-v13.1.7-darwin-x64.zip\n\
+            bbbb *electron-v13.1.7-linux-x64.zip\n\
+            cccc electron-api.json\n";
+        assert_eq!(
+            expected_checksum(shasums, "electron-v13.1.7-linux-x64.zip"),
+            Some("bbbb")
+        );
+        assert_eq!(
+            expected_checksum(shasums, "electron-api.json"),
+            Some("cccc")
+        );
+        assert_eq!(
+            expected_checksum(shasums, "electron-v13.1.7-win32-x64.zip"),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    fn stub_electron(dir: &Path, output: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let exe = dir.join("electron");
+        std::fs::write(&exe, format!("#!/bin/sh\necho {}\n", output)).unwrap();
+        std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+        exe
+    }
+
+    #[test]
+    fn release_lookup_respects_prerelease_flag() {
+        let releases: Vec<PackageJson> = serde_json::from_str(
+            r#"[
+                {"version": "13.1.6", "node": "14.16.0", "modules": "89"},
+                {"version": "14.0.0-beta.3", "node": "14.17.0", "modules": "89"},
+                {"version": "13.1.7", "node": "14.16.0", "modules": "89"}
+            ]"#,
+        )
+        .unwrap();
+        let pick = |range: &str, include_prerelease| {
+            matching_releases(
+                releases.clone(),
+                &range.parse().unwrap(),
+                include_prerelease,
+            )
+            .into_iter()
+            .map(|pkg| pkg.version.to_string())
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(pick("*", false), vec!["13.1.7", "13.1.6"]);
+        assert!(pick("^14.0.0-beta.1", false).is_empty());
+        assert_eq!(pick("^14.0.0-beta.1", true), vec!["14.0.0-beta.3"]);
+        assert!(pick("^15", true).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn verify_version_catches_incomplete_copies() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let exe = stub_electron(dir.path(), "v13.1.7");
+        let electron = Electron {
+            exe: exe.clone(),
+            dist_dir: dir.path().to_owned(),
+            version: "13.1.7".parse().unwrap(),
+            os: "linux".into(),
+            arch: "x64".into(),
+            node: None,
+            chrome: None,
+            abi: None,
+        };
+        let is_incomplete = |res| matches!(res, Err(ElectronError::IncompleteElectron { .. }));
+        smol::block_on(async {
+            assert!(is_incomplete(electron.verify_version().await));
+
+            std::fs::write(dir.path().join("version"), "13.1.6").unwrap();
+            assert!(is_incomplete(electron.verify_version().await));
+
+            std::fs::write(dir.path().join("version"), "v13.1.7\n").unwrap();
+            assert!(electron.verify_version().await.is_ok());
+
+            std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o644)).unwrap();
+            assert!(is_incomplete(electron.verify_version().await));
+
+            std::fs::remove_file(&exe).unwrap();
+            assert!(is_incomplete(electron.verify_version().await));
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_keep_bundle_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dist = dir.path().join("dist");
+        let versions = dist
+            .join("Electron.app")
+            .join("Contents")
+            .join("Frameworks")
+            .join("Electron Framework.framework")
+            .join("Versions");
+        std::fs::create_dir_all(versions.join("A")).unwrap();
+        symlink("A", versions.join("Current")).unwrap();
+        let exe = dist.join(exe_name("darwin"));
+        std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        std::fs::write(&exe, "").unwrap();
+        let electron = Electron {
+            exe,
+            dist_dir: dist.clone(),
+            version: "13.1.7".parse().unwrap(),
+            os: "darwin".into(),
+            arch: "x64".into(),
+            node: None,
+            chrome: None,
+            abi: None,
+        };
+        let store = dir.path().join("store");
+        for (to, store) in &[("copied", None), ("linked", Some(store.as_path()))] {
+            let to = dir.path().join(to);
+            let copy = smol::block_on(electron.copy_files(&to, *store)).unwrap();
+            assert_eq!(copy.exe(), to.join(exe_name("darwin")));
+            let current = versions
+                .strip_prefix(&dist)
+                .map(|versions| to.join(versions).join("Current"))
+                .unwrap();
+            assert_eq!(std::fs::read_link(current).unwrap(), Path::new("A"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn verify_launch_checks_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = stub_electron(dir.path(), "v13.1.7");
+        smol::block_on(async {
+            assert!(
+                verify_launch(&exe, &"13.1.7".parse().unwrap(), VERIFY_LAUNCH_TIMEOUT)
+                    .await
+                    .is_ok()
+            );
+            assert!(matches!(
+                verify_launch(&exe, &"13.1.8".parse().unwrap(), VERIFY_LAUNCH_TIMEOUT).await,
+                Err(ElectronError::LaunchVerificationFailed { .. })
+            ));
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broken_cached_builds_are_removed() {
+        let cache = tempfile::tempdir().unwrap();
+        let (os, arch) = (host_platform().unwrap(), host_arch().unwrap());
+        let version: Version = "13.1.7".parse().unwrap();
+        let check = |opts: &ElectronOpts, dist: &Path, exe: &Path| {
+            smol::block_on(opts.cached_build_works(exe, dist, &version, os, arch)).unwrap()
+        };
+
+        let good = cache.path().join("good");
+        std::fs::create_dir(&good).unwrap();
+        let exe = stub_electron(&good, "v13.1.7");
+        assert!(check(&ElectronOpts::new().verify_exe(true), &good, &exe));
+        assert!(good.exists());
+
+        // Only checked when asked to.
+        let broken = cache.path().join("broken");
+        std::fs::create_dir(&broken).unwrap();
+        let exe = stub_electron(&broken, "'error while loading shared libraries'; exit 127");
+        assert!(check(&ElectronOpts::new(), &broken, &exe));
+        assert!(broken.exists());
+        assert!(!check(&ElectronOpts::new().verify_exe(true), &broken, &exe));
+        assert!(!broken.exists());
+    }
+
+    #[test]
+    fn offline_uses_newest_cached_match() {
+        let cache = tempfile::tempdir().unwrap();
+        for triple in &[
+            "v13.1.7-linux-x64",
+            "v13.2.0-linux-x64",
+            "v14.0.0-linux-x64",
+        ] {
+            let dist = cache.path().join(triple);
+            std::fs::create_dir_all(&dist).unwrap();
+            std::fs::write(dist.join("electron"), "").unwrap();
+        }
+        let opts = ElectronOpts::new().offline(true);
+        let electron = opts
+            .offline_electron(&"^13".parse().unwrap(), "linux", "x64", cache.path())
+            .unwrap();
+        assert_eq!(electron.triple(), "v13.2.0-linux-x64");
+        assert_eq!(
+            electron.exe(),
+            cache.path().join("v13.2.0-linux-x64").join("electron")
+        );
+        assert!(matches!(
+            opts.offline_electron(&"^15".parse().unwrap(), "linux", "x64", cache.path()),
+            Err(ElectronError::NoCachedVersionOffline(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn launch_maps_exit_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = stub_electron(dir.path(), "ok");
+        let args = ["--trace-warnings".into(), ".".into()];
+        assert!(smol::block_on(launch_exe(&exe, &args, &[], Stdio::null())).is_ok());
+
+        std::fs::write(&exe, "#!/bin/sh\nexit 1\n").unwrap();
+        assert!(matches!(
+            smol::block_on(launch_exe(&exe, &args, &[], Stdio::null())),
+            Err(ElectronError::ElectronFailed(status)) if status.code() == Some(1)
+        ));
+        assert!(matches!(
+            smol::block_on(launch_exe(
+                &dir.path().join("nope"),
+                &args,
+                &[],
+                Stdio::null()
+            )),
+            Err(ElectronError::IoError(..))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn captured_launch_sees_both_streams() {
+        let dir = tempfile::tempdir().unwrap();
+        // stderr closes early while stdout keeps going, past what a pipe
+        // buffers, so nothing after the close may get dropped.
+        let exe = stub_electron(
+            dir.path(),
+            "out; echo err >&2; exec 2>&-; sleep 0.1; seq 20000; exit 3",
+        );
+        let mut lines = Vec::new();
+        let res = smol::block_on(launch_exe_captured(
+            &exe,
+            &[],
+            &[],
+            Stdio::null(),
+            |stream, line| lines.push((stream, line.to_owned())),
+        ));
+        assert!(matches!(res, Err(ElectronError::ElectronFailed(_))));
+        lines.sort_by_key(|(stream, _)| stream.as_str());
+        assert_eq!(lines.len(), 20002);
+        assert_eq!(
+            lines[..2],
+            [
+                (OutputStream::Stderr, "err".into()),
+                (OutputStream::Stdout, "out".into())
+            ]
+        );
+        assert_eq!(lines.last(), Some(&(OutputStream::Stdout, "20000".into())));
+    }
+
+    /// Serves `body` over HTTP for `requests` requests, honoring `Range:
+    /// bytes=N-` headers. Returns the server's URL and each request's Range
+    /// header, if any.
+    fn serve_ranges(
+        body: &'static [u8],
+        requests: usize,
+    ) -> (String, std::thread::JoinHandle<Vec<Option<String>>>) {
+        use std::io::{BufRead, BufReader, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/electron.zip", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut ranges = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut range = None;
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("range: bytes=") {
+                        range = Some(value.trim_end_matches('-').to_owned());
+                    }
+                }
+                let start = range.as_deref().map(|r| r.parse().unwrap()).unwrap_or(0);
+                let status = if range.is_some() {
+                    "206 Partial Content"
+                } else {
+                    "200 OK"
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len() - start
+                )
+                .unwrap();
+                stream.write_all(&body[start..]).unwrap();
+                ranges.push(range);
+            }
+            ranges
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn explicit_proxy_is_used() {
+        use std::io::{BufRead, BufReader, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut stream = listener.incoming().next().unwrap().unwrap();
+            let mut lines = BufReader::new(&stream).lines().map(|line| line.unwrap());
+            let request = lines.next().unwrap();
+            for line in lines {
+                if line.is_empty() {
+                    break;
+                }
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+            )
+            .unwrap();
+            request
+        });
+        let opts = ElectronOpts::new().proxy(proxy.parse().unwrap());
+        let body = smol::block_on(async {
+            opts.send(opts.get("http://electron.invalid/releases.json").unwrap())
+                .await
+                .unwrap()
+                .text()
+                .compat()
+                .await
+                .unwrap()
+        });
+        assert_eq!(body, "ok");
+        assert_eq!(
+            server.join().unwrap(),
+            "GET http://electron.invalid/releases.json HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn one_client_with_a_timeout() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/releases.json", listener.local_addr().unwrap());
+        let opts = ElectronOpts::new().timeout(Duration::from_millis(100));
+        assert!(std::ptr::eq(opts.client().unwrap(), opts.client().unwrap()));
+
+        // The connection is accepted, but never answered.
+        let err = smol::block_on(opts.send(opts.get(&url).unwrap())).unwrap_err();
+        assert!(matches!(err, ElectronError::HttpError(ref e) if e.is_timeout()));
+        assert!(err.is_transient());
+        drop(listener);
+    }
+
+    #[test]
+    fn resumes_partial_downloads() {
+        const BODY: &[u8] = b"pretend this is a very large electron zip";
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("electron-v13.1.7-linux-x64.zip");
+        let opts = ElectronOpts::new();
+
+        std::fs::write(&dest, &BODY[..10]).unwrap();
+        let (url, server) = serve_ranges(BODY, 2);
+        let (checksum, resumed) =
+            smol::block_on(opts.download_zip(&url, &dest, "v13.1.7-linux-x64", true)).unwrap();
+        assert!(resumed);
+        assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+        assert_eq!(checksum, hex::encode(Sha256::digest(BODY)));
+
+        // Without resuming (as with --force), the partial file is replaced.
+        std::fs::write(&dest, &BODY[..10]).unwrap();
+        let (_, resumed) =
+            smol::block_on(opts.download_zip(&url, &dest, "v13.1.7-linux-x64", false)).unwrap();
+        assert!(!resumed);
+        assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+        assert_eq!(server.join().unwrap(), vec![Some("10".to_owned()), None]);
+    }
+
+    #[test]
+    fn reports_events_for_cached_builds() {
+        let dir = tempfile::tempdir().unwrap();
+        let dist = CacheDirs::in_dir(dir.path())
+            .builds
+            .join("v13.1.7-linux-x64");
+        std::fs::create_dir_all(&dist).unwrap();
+        std::fs::write(dist.join(exe_name("linux")), "").unwrap();
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let electron = smol::block_on(
+            ElectronOpts::new()
+                .range("^13".parse().unwrap())
+                .platform("linux".parse().unwrap())
+                .arch("x64")
+                .always_resolve(true)
+                .offline(true)
+                .cache_dir(dir.path())
+                .on_event(move |event| seen.lock().unwrap().push(event))
+                .ensure_electron(),
+        )
+        .unwrap();
+        assert_eq!(electron.dist_dir(), dist);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ElectronEvent::ResolvingVersion,
+                ElectronEvent::VersionResolved("13.1.7".parse().unwrap()),
+                ElectronEvent::Done,
+            ]
+        );
     }
 }
@@ -0,0 +1,347 @@
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use collider_common::{
+    chrono::{DateTime, TimeZone, Utc},
+    smol::Timer,
+    tracing,
+};
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    StatusCode,
+};
+
+use crate::errors::ElectronError;
+
+/// How many times idempotent requests are retried unless
+/// [`ElectronOpts::max_retries`](crate::ElectronOpts::max_retries) says
+/// otherwise.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// How long to wait before the first retry unless
+/// [`ElectronOpts::retry_delay`](crate::ElectronOpts::retry_delay) says
+/// otherwise. Each retry after that waits twice as long as the last.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// How persistently to retry requests that fail with transient errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt. Zero means no retries at all.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubling with each one after that.
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+}
+
+/// Longest we'll wait when a server asks us to come back later, no matter
+/// what its `Retry-After` says.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Asks a human whether a request that failed with a transient error should
+/// be tried again. When one is configured (see
+/// [`ElectronOpts::retry_prompt`](crate::ElectronOpts::retry_prompt)), it
+/// replaces the automatic retry budget, so someone at a terminal can fix
+/// their connection and carry on instead of starting over.
+pub trait RetryPrompt: Send + Sync {
+    fn should_retry(&self, error: &ElectronError) -> bool;
+}
+
+/// Like [`retry_async`], but if `prompt` is given, transient failures are
+/// retried (right away) for as long as it says so, instead of using the
+/// automatic budget.
+pub async fn retry_with_prompt<T, F, Fut>(
+    policy: RetryPolicy,
+    prompt: Option<&dyn RetryPrompt>,
+    mut op: F,
+) -> Result<T, ElectronError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ElectronError>>,
+{
+    let max_retries = policy.max_retries;
+    let mut attempt = 0;
+    let mut throttled = 0;
+    loop {
+        match op().await {
+            // The server told us exactly how long to wait, so there's no
+            // need to guess with backoff, or to bother a human about it.
+            Err(ElectronError::Throttled { retry_after }) if throttled < max_retries => {
+                let delay = retry_after.min(MAX_RETRY_AFTER);
+                throttled += 1;
+                tracing::warn!(
+                    "Server is rate limiting downloads. Retrying in {}s ({}/{}).",
+                    delay.as_secs(),
+                    throttled,
+                    max_retries
+                );
+                Timer::after(delay).await;
+            }
+            Err(e) if e.is_transient() => match prompt {
+                Some(prompt) if prompt.should_retry(&e) => {
+                    tracing::debug!("Retrying after: {}", e);
+                }
+                None if attempt < max_retries => {
+                    let delay = backoff(policy.base_delay, attempt);
+                    attempt += 1;
+                    tracing::warn!(
+                        "{} Retrying in {:.1}s ({}/{}).",
+                        e,
+                        delay.as_secs_f64(),
+                        attempt,
+                        max_retries
+                    );
+                    Timer::after(delay).await;
+                }
+                _ => return Err(e),
+            },
+            res => return res,
+        }
+    }
+}
+
+/// Runs `op`, retrying it up to `policy.max_retries` more times with
+/// exponential backoff (plus some jitter) whenever it fails with a transient error. `op`
+/// must be safe to repeat, so this should only wrap idempotent requests.
+/// Rate-limited responses (see [`check_throttled`]) are retried separately,
+/// after however long the server asked for.
+pub async fn retry_async<T, F, Fut>(policy: RetryPolicy, op: F) -> Result<T, ElectronError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ElectronError>>,
+{
+    retry_with_prompt(policy, None, op).await
+}
+
+/// Turns `429 Too Many Requests` and `503 Service Unavailable` responses that
+/// come with a `Retry-After` header into [`ElectronError::Throttled`], so
+/// they can be retried after the requested delay, and GitHub's rate limit
+/// responses into [`ElectronError::GitHubApiLimit`]. Everything else is
+/// passed through untouched.
+pub fn check_throttled(res: reqwest::Response) -> Result<reqwest::Response, ElectronError> {
+    match throttled_error(res.status(), res.headers()) {
+        Some(err) => Err(err),
+        None => Ok(res),
+    }
+}
+
+fn throttled_error(status: StatusCode, headers: &HeaderMap) -> Option<ElectronError> {
+    let header = |name| headers.get(name).and_then(|val| val.to_str().ok());
+    if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+        if let Some(retry_after) = header(RETRY_AFTER.as_str()).and_then(parse_retry_after) {
+            return Some(ElectronError::Throttled { retry_after });
+        }
+    }
+    // GitHub's hourly limit. Its responses say when it resets, which can be
+    // a good while away, so there's no point retrying.
+    if (status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS)
+        && header("x-ratelimit-remaining").map(str::trim) == Some("0")
+    {
+        return Some(ElectronError::GitHubApiLimit {
+            message: "GitHub's rate limit was exceeded.".into(),
+            resets_at: header("x-ratelimit-reset")
+                .and_then(|secs| secs.trim().parse().ok())
+                .and_then(|secs| Utc.timestamp_opt(secs, 0).single()),
+        });
+    }
+    None
+}
+
+/// Parses a `Retry-After` value, which is either a number of seconds or an
+/// HTTP date.
+fn parse_retry_after(val: &str) -> Option<Duration> {
+    let val = val.trim();
+    if let Ok(secs) = val.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(val).ok()?;
+    // A date in the past just means "go ahead".
+    Some(
+        (at.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+fn backoff(base_delay: Duration, attempt: u32) -> Duration {
+    // Not worth a whole RNG dependency: this just keeps a bunch of clients
+    // that failed at the same moment from retrying in lockstep.
+    let jitter = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_millis() % 250)
+        .unwrap_or(0);
+    base_delay * 2u32.saturating_pow(attempt) + Duration::from_millis(jitter.into())
+}
+
+impl ElectronError {
+    /// Whether this error might go away if the request is simply tried
+    /// again: dropped connections, timeouts, and server-side errors. Client
+    /// errors, rate limits, and bad checksums never will.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ElectronError::HttpError(e) => {
+                e.is_connect()
+                    || e.is_timeout()
+                    || e.is_body()
+                    || e.status().map(|s| s.is_server_error()).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use async_compat::CompatExt;
+    use collider_common::smol;
+
+    /// Answers "yes" a fixed number of times, then "no".
+    struct Answers(AtomicU32);
+
+    impl RetryPrompt for Answers {
+        fn should_retry(&self, _error: &ElectronError) -> bool {
+            self.0
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .is_ok()
+        }
+    }
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+        }
+    }
+
+    fn refused_addr() -> std::net::SocketAddr {
+        // Nothing's listening on this port once the listener is dropped.
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[test]
+    fn prompt_answers_drive_retries() {
+        let addr = refused_addr();
+        let attempts = Cell::new(0);
+        let prompt = Answers(AtomicU32::new(2));
+        // The automatic budget is ignored when there's someone to ask.
+        let res = smol::block_on(retry_with_prompt(policy(0), Some(&prompt), || {
+            attempts.set(attempts.get() + 1);
+            async {
+                reqwest::get(format!("http://{}", addr))
+                    .compat()
+                    .await
+                    .map_err(ElectronError::from)
+            }
+        }));
+        assert!(res.is_err());
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn retry_after_parsing() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::from_secs(0))
+        );
+        let soon = (Utc::now() + collider_common::chrono::Duration::seconds(30)).to_rfc2822();
+        let parsed = parse_retry_after(&soon).unwrap();
+        assert!(parsed > Duration::from_secs(25) && parsed <= Duration::from_secs(30));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn github_rate_limits() {
+        let resets_at = Utc::now() + collider_common::chrono::Duration::minutes(30);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", "0".parse().unwrap());
+        headers.insert(
+            "x-ratelimit-reset",
+            resets_at.timestamp().to_string().parse().unwrap(),
+        );
+        let err = throttled_error(StatusCode::FORBIDDEN, &headers).unwrap();
+        assert!(!err.is_transient());
+        let local = resets_at
+            .with_timezone(&collider_common::chrono::Local)
+            .format("%H:%M");
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "GitHub's rate limit was exceeded. The rate limit resets in 30 minutes (at {} local).",
+                local
+            )
+        );
+
+        headers.remove("x-ratelimit-reset");
+        assert_eq!(
+            throttled_error(StatusCode::FORBIDDEN, &headers)
+                .unwrap()
+                .to_string(),
+            "GitHub's rate limit was exceeded."
+        );
+
+        // Plain permission problems aren't rate limits.
+        headers.insert("x-ratelimit-remaining", "42".parse().unwrap());
+        assert!(throttled_error(StatusCode::FORBIDDEN, &headers).is_none());
+    }
+
+    #[test]
+    fn throttling_exhausts_to_throttled() {
+        let attempts = Cell::new(0);
+        let res: Result<(), _> = smol::block_on(retry_async(policy(2), || {
+            attempts.set(attempts.get() + 1);
+            async {
+                Err(ElectronError::Throttled {
+                    retry_after: Duration::from_millis(1),
+                })
+            }
+        }));
+        assert!(matches!(res, Err(ElectronError::Throttled { .. })));
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        let addr = refused_addr();
+        let attempts = Cell::new(0);
+        let res = smol::block_on(retry_async(policy(1), || {
+            attempts.set(attempts.get() + 1);
+            async {
+                reqwest::get(format!("http://{}", addr))
+                    .compat()
+                    .await
+                    .map_err(ElectronError::from)
+            }
+        }));
+        assert!(res.unwrap_err().is_transient());
+        assert_eq!(attempts.get(), 2);
+
+        let attempts = Cell::new(0);
+        let res: Result<(), _> = smol::block_on(retry_async(policy(3), || {
+            attempts.set(attempts.get() + 1);
+            async {
+                Err(ElectronError::MatchingVersionNotFound(
+                    node_semver::Range::any(),
+                ))
+            }
+        }));
+        assert!(res.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+}
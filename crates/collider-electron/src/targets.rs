@@ -0,0 +1,234 @@
+use std::fmt;
+use std::str::FromStr;
+
+use node_semver::Version;
+
+use crate::errors::ElectronError;
+
+/// Every platform Electron publishes release artifacts for.
+pub const PLATFORMS: &[&str] = &["win32", "darwin", "linux", "mas"];
+
+/// Every architecture Electron publishes release artifacts for.
+pub const ARCHS: &[&str] = &["ia32", "x64", "arm64", "armv7l"];
+
+/// Architectures Electron actually ships for a given platform.
+pub fn platform_archs(platform: &str) -> &'static [&'static str] {
+    match platform {
+        "darwin" | "mas" => &["x64", "arm64"],
+        "win32" => &["ia32", "x64", "arm64"],
+        _ => ARCHS,
+    }
+}
+
+/// A platform Electron publishes builds for. `Mas` is the Mac App Store
+/// flavor of the macOS build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Win32,
+    Darwin,
+    Linux,
+    Mas,
+}
+
+impl Platform {
+    /// The name Electron uses for this platform in release artifacts.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Platform::Win32 => "win32",
+            Platform::Darwin => "darwin",
+            Platform::Linux => "linux",
+            Platform::Mas => "mas",
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = ElectronError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "win32" => Ok(Platform::Win32),
+            "darwin" => Ok(Platform::Darwin),
+            "linux" => Ok(Platform::Linux),
+            "mas" => Ok(Platform::Mas),
+            _ => Err(ElectronError::UnsupportedPlatform(s.into())),
+        }
+    }
+}
+
+/// The Electron platform name for the platform collider is running on.
+pub fn host_platform() -> Result<&'static str, ElectronError> {
+    electron_platform(std::env::consts::OS)
+}
+
+/// The Electron arch name for the architecture collider is running on.
+pub fn host_arch() -> Result<&'static str, ElectronError> {
+    electron_arch(std::env::consts::ARCH)
+}
+
+fn electron_platform(os: &str) -> Result<&'static str, ElectronError> {
+    match os {
+        "windows" => Ok("win32"),
+        "macos" => Ok("darwin"),
+        "linux" => Ok("linux"),
+        // Mac App Store builds never run as a plain host platform. Ask for
+        // them with `ElectronOpts::platform`.
+        _ => Err(ElectronError::UnsupportedPlatform(os.into())),
+    }
+}
+
+fn electron_arch(arch: &str) -> Result<&'static str, ElectronError> {
+    match arch {
+        "x86" => Ok("ia32"),
+        "x86_64" => Ok("x64"),
+        "aarch64" => Ok("arm64"),
+        // Rust calls 32-bit ARM "arm". Electron only ships ARMv7 builds.
+        "arm" => Ok("armv7l"),
+        _ => Err(ElectronError::UnsupportedArch(arch.into())),
+    }
+}
+
+/// Expands a platform/arch pair into every `(platform, arch)` target it
+/// covers. Either side can be `"all"`, in which case it expands to every
+/// value Electron ships for the other side. This is only meaningful for
+/// operations that download or pack, since nothing can run a foreign target.
+pub fn expand_targets(platform: &str, arch: &str) -> Result<Vec<(String, String)>, ElectronError> {
+    let platforms = if platform == "all" {
+        PLATFORMS.to_vec()
+    } else if PLATFORMS.contains(&platform) {
+        vec![platform]
+    } else {
+        return Err(ElectronError::UnsupportedPlatform(platform.into()));
+    };
+    if arch != "all" && !ARCHS.contains(&arch) {
+        return Err(ElectronError::UnsupportedArch(arch.into()));
+    }
+    let mut targets = Vec::new();
+    for platform in platforms {
+        for supported in platform_archs(platform) {
+            if arch == "all" || arch == *supported {
+                targets.push((platform.to_string(), supported.to_string()));
+            }
+        }
+    }
+    if targets.is_empty() {
+        return Err(ElectronError::UnsupportedArch(format!(
+            "{}-{}",
+            platform, arch
+        )));
+    }
+    Ok(targets)
+}
+
+/// Path to the Electron executable inside an extracted distribution for
+/// `platform`.
+pub fn exe_name(platform: &str) -> &'static str {
+    match platform {
+        "win32" => "electron.exe",
+        "darwin" | "mas" => "Electron.app/Contents/MacOS/Electron",
+        _ => "electron",
+    }
+}
+
+/// Splits a target triple like `v13.1.7-linux-x64` (which is also the name of
+/// each build's directory in the cache) back into its version, platform, and
+/// arch. This splits from the right, since prerelease versions have dashes
+/// of their own.
+pub fn parse_triple(triple: &str) -> Option<(Version, String, String)> {
+    let mut parts = triple.rsplitn(3, '-');
+    let arch = parts.next()?;
+    let platform = parts.next()?;
+    let version = parts.next()?.strip_prefix('v')?.parse().ok()?;
+    Some((version, platform.into(), arch.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_archs_for_platform() {
+        let targets = expand_targets("linux", "all").unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                ("linux".to_string(), "ia32".to_string()),
+                ("linux".to_string(), "x64".to_string()),
+                ("linux".to_string(), "arm64".to_string()),
+                ("linux".to_string(), "armv7l".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn all_platforms_for_arch() {
+        let targets = expand_targets("all", "ia32").unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                ("win32".to_string(), "ia32".to_string()),
+                ("linux".to_string(), "ia32".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_combination() {
+        assert!(expand_targets("darwin", "ia32").is_err());
+        assert!(expand_targets("beos", "all").is_err());
+        assert!(expand_targets("linux", "mips").is_err());
+    }
+
+    #[test]
+    fn platform_override_targets_mas() {
+        let opts = crate::ElectronOpts::new().platform("mas".parse().unwrap());
+        let platform = opts.target_platform().unwrap();
+        assert_eq!(platform, "mas");
+        let version: Version = "13.1.7".parse().unwrap();
+        assert_eq!(
+            opts.get_target_triple(&version, &platform, "arm64")
+                .unwrap(),
+            "v13.1.7-mas-arm64"
+        );
+        assert_eq!(opts.get_exe_name(), "Electron.app/Contents/MacOS/Electron");
+
+        let host = crate::ElectronOpts::new().target_platform().unwrap();
+        assert_eq!(host, host_platform().unwrap());
+        assert!("macos".parse::<Platform>().is_err());
+    }
+
+    #[test]
+    fn arm_maps_to_armv7l() {
+        let arch = electron_arch("arm").unwrap();
+        let version: Version = "13.1.7".parse().unwrap();
+        let triple = crate::ElectronOpts::new()
+            .get_target_triple(&version, "linux", arch)
+            .unwrap();
+        assert_eq!(triple, "v13.1.7-linux-armv7l");
+        let (_, _, parsed_arch) = parse_triple(&triple).unwrap();
+        assert_eq!(parsed_arch, "armv7l");
+        assert!(electron_arch("mips").is_err());
+
+        let opts = crate::ElectronOpts::new().arch("armv7l");
+        assert_eq!(opts.target_arch().unwrap(), "armv7l");
+        assert!(matches!(
+            crate::ElectronOpts::new().arch("arm").target_arch(),
+            Err(ElectronError::UnsupportedArch(_))
+        ));
+    }
+
+    #[test]
+    fn triple_parsing() {
+        let (version, platform, arch) = parse_triple("v14.0.0-beta.1-darwin-arm64").unwrap();
+        assert_eq!(version.to_string(), "14.0.0-beta.1");
+        assert_eq!(platform, "darwin");
+        assert_eq!(arch, "arm64");
+        assert!(parse_triple("html-launcher").is_none());
+    }
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use collider_common::{smol::fs, tracing};
+use node_semver::Range;
+
+use crate::errors::ElectronError;
+
+/// Looks for an Electron version pinned by the project in `dir`. For now, the
+/// only place this looks is an asdf-style `.tool-versions` file.
+pub async fn project_electron_range(dir: &Path) -> Result<Option<Range>, ElectronError> {
+    let tool_versions = dir.join(".tool-versions");
+    if fs::metadata(&tool_versions).await.is_err() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&tool_versions).await.map_err(|e| {
+        ElectronError::IoError(format!("Failed to read {}", tool_versions.display()), e)
+    })?;
+    match parse_tool_versions(&contents) {
+        Some(version) => {
+            tracing::debug!(
+                "Using electron@{} from {}",
+                version,
+                tool_versions.display()
+            );
+            Ok(Some(version.parse()?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Finds the `electron` entry in the contents of a `.tool-versions` file. If
+/// the entry lists more than one version, the first one wins, same as asdf.
+pub fn parse_tool_versions(contents: &str) -> Option<&str> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .find_map(|line| {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some("electron"), Some(version)) => Some(version),
+                _ => None,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_versions_parsing() {
+        let with_electron = "nodejs 14.17.0\n# electron 1.0.0\nelectron 13.1.7 12.0.0 # pinned\n";
+        assert_eq!(parse_tool_versions(with_electron), Some("13.1.7"));
+        assert_eq!(parse_tool_versions("nodejs 14.17.0\nruby 3.0.1\n"), None);
+        assert_eq!(parse_tool_versions("electron\n"), None);
+    }
+}
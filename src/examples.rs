@@ -0,0 +1,247 @@
+use collider_command::{
+    async_trait::async_trait,
+    clap::{self, Clap},
+    collider_config::{self, ColliderConfigLayer},
+    ColliderCommand,
+};
+use collider_common::{miette::Result, serde_json};
+
+/// A runnable example invocation of `collider`. These are kept in code,
+/// rather than just in docs, so tests can make sure they still parse.
+pub struct Example {
+    pub about: &'static str,
+    pub args: &'static [&'static str],
+}
+
+impl Example {
+    pub fn command(&self) -> &'static str {
+        self.args[0]
+    }
+
+    pub fn to_command_line(&self) -> String {
+        format!("collider {}", self.args.join(" "))
+    }
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        about: "Bisect interactively between two Electron versions.",
+        args: &[
+            "bisect",
+            "--start",
+            "12.0.0",
+            "--end",
+            "13.0.0",
+            "--interactive",
+        ],
+    },
+    Example {
+        about: "Bisect a regression that needs two repro apps to both pass.",
+        args: &["bisect", "repro/startup", "repro/menus", "--combine", "and"],
+    },
+    Example {
+        about: "Bisect in CI, judging each version with a test script.",
+        args: &["bisect", "--command", "npx playwright test"],
+    },
+    Example {
+        about: "Bisect a bug that only shows up with the GPU disabled.",
+        args: &["bisect", ".", "--", "--disable-gpu"],
+    },
+    Example {
+        about: "Remove all but the two newest cached Electron versions.",
+        args: &["cache", "clean", "--keep", "2"],
+    },
+    Example {
+        about:
+            "Show which cached Electron builds haven't been used in a month, without removing them.",
+        args: &["clean", "--older-than", "30", "--dry-run"],
+    },
+    Example {
+        about: "Export cached Electron 13 builds, e.g. to seed a CI cache.",
+        args: &["cache", "export", "electron-cache.tar.gz", "--using", "13"],
+    },
+    Example {
+        about: "Make `electron` in this shell run collider's Electron 13.",
+        args: &["env", "--using", "13", "--shell", "bash"],
+    },
+    Example {
+        about: "Gather details about your setup to paste into a bug report.",
+        args: &["info", "--json"],
+    },
+    Example {
+        about: "Check that collider's tools are installed, failing the CI job if not.",
+        args: &["doctor"],
+    },
+    Example {
+        about: "Scaffold a new app into ./my-app.",
+        args: &["new", "my-app"],
+    },
+    Example {
+        about: "Scaffold a new TypeScript app into ./my-app.",
+        args: &["new", "my-app", "--template", "typescript"],
+    },
+    Example {
+        about: "List the templates new can scaffold from.",
+        args: &["new", "--list-templates"],
+    },
+    Example {
+        about: "Scaffold a new app from the v2 tag of a boilerplate repository.",
+        args: &[
+            "new",
+            "my-app",
+            "--template-repo",
+            "https://github.com/example/electron-boilerplate.git",
+            "--template-ref",
+            "v2",
+        ],
+    },
+    Example {
+        about: "Scaffold a new app without any prompts, e.g. in CI.",
+        args: &["new", "--name", "my-app", "--yes"],
+    },
+    Example {
+        about: "Scaffold a new app inside an existing repo, without installing anything yet.",
+        args: &["new", "packages/my-app", "--no-git", "--no-install"],
+    },
+    Example {
+        about: "Pack the app in the current directory into ./dist.",
+        args: &["pack", ".", "-o", "dist"],
+    },
+    Example {
+        about: "Pack the app, keeping its native modules and videos out of app.asar.",
+        args: &[
+            "pack",
+            ".",
+            "--asar-unpack",
+            "*.node",
+            "--asar-unpack",
+            "assets/videos/**",
+        ],
+    },
+    Example {
+        about: "Pack for Windows and macOS, archiving each app for distribution.",
+        args: &[
+            "pack",
+            ".",
+            "--platform",
+            "win32,darwin",
+            "--target",
+            "zip,tar.gz",
+        ],
+    },
+    Example {
+        about: "Pack a macOS app with its own bundle identifier.",
+        args: &[
+            "pack",
+            ".",
+            "--platform",
+            "darwin",
+            "--app-id",
+            "com.example.my-app",
+        ],
+    },
+    Example {
+        about: "Pack without rebuilding native modules against Electron, e.g. when they're already built for it.",
+        args: &["pack", ".", "--skip-rebuild"],
+    },
+    Example {
+        about: "Show the npm/npx commands pack would run, without running them.",
+        args: &["pack", ".", "--dry-run"],
+    },
+    Example {
+        about: "Pack for Windows and macOS, on both x64 and arm64, all at once.",
+        args: &[
+            "pack",
+            ".",
+            "--platform",
+            "win32,darwin",
+            "--arch",
+            "x64",
+            "--arch",
+            "arm64",
+        ],
+    },
+    Example {
+        about: "See whether there's a newer collider, without installing it.",
+        args: &["self-update", "--check"],
+    },
+    Example {
+        about: "Start the app in the current directory.",
+        args: &["start"],
+    },
+    Example {
+        about: "Start the app with a debugger listening on port 9229, paused until one attaches.",
+        args: &["start", "--inspect-brk=9229"],
+    },
+    Example {
+        about: "Start the app with the newest Electron 13 release.",
+        args: &["start", ".", "--using", "13"],
+    },
+    Example {
+        about: "Start the app, keeping downloaded Electron builds in the project, e.g. where $HOME isn't writable.",
+        args: &["start", ".", "--cache-dir", ".collider-cache"],
+    },
+    Example {
+        about: "Pass arguments through to the app itself, after `--`.",
+        args: &["start", ".", "--", "--enable-logging", "--my-flag=foo"],
+    },
+    Example {
+        about: "Launch bundler output, serving absolute asset paths out of ./dist.",
+        args: &["start", "dist/index.html", "--base-href", "dist"],
+    },
+];
+
+#[derive(Debug, Clap, ColliderConfigLayer)]
+pub struct ExamplesCmd {
+    #[clap(about = "Only show examples for this subcommand.")]
+    command: Option<String>,
+
+    #[clap(from_global)]
+    json: bool,
+}
+
+#[async_trait]
+impl ColliderCommand for ExamplesCmd {
+    async fn execute(self) -> Result<()> {
+        let examples = EXAMPLES.iter().filter(|example| {
+            self.command
+                .as_ref()
+                .map(|cmd| cmd == example.command())
+                .unwrap_or(true)
+        });
+        if self.json {
+            let examples = examples
+                .map(|example| {
+                    serde_json::json!({
+                        "about": example.about,
+                        "command": example.to_command_line(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&examples).unwrap());
+        } else {
+            for example in examples {
+                println!("# {}", example.about);
+                println!("{}\n", example.to_command_line());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Collider;
+
+    #[test]
+    fn examples_parse() {
+        for example in EXAMPLES {
+            let args = std::iter::once("collider").chain(example.args.iter().copied());
+            if let Err(e) = Collider::try_parse_from(args) {
+                panic!("`{}` failed to parse: {}", example.to_command_line(), e);
+            }
+        }
+    }
+}
@@ -13,6 +13,10 @@ use collider_common::{
     miette::{Context, Result},
 };
 
+pub use examples::{Example, EXAMPLES};
+
+mod examples;
+
 #[derive(Debug, Clap)]
 #[clap(
     author = "Kat Marchán <kzm@zkat.tech>",
@@ -40,6 +44,24 @@ pub struct Collider {
     quiet: bool,
     #[clap(global = true, long, about = "Format output as JSON.")]
     json: bool,
+    #[clap(
+        global = true,
+        long,
+        about = "Directory to keep downloaded Electron builds in. Defaults to $COLLIDER_CACHE_DIR, or else the platform's usual cache location."
+    )]
+    cache_dir: Option<PathBuf>,
+    #[clap(
+        global = true,
+        long,
+        about = "URL to POST JSON events about this run to, such as Electron resolution, download progress and pack results. Delivery failures only produce a warning."
+    )]
+    webhook: Option<String>,
+    #[clap(
+        global = true,
+        long,
+        about = "Extra header to send with webhook requests, as `Name: value`. Useful for auth tokens."
+    )]
+    webhook_header: Option<String>,
     #[clap(subcommand)]
     subcommand: ColliderCmd,
 }
@@ -82,10 +104,16 @@ impl Collider {
                 .pkg_root(collider.root.clone())
                 .load()?
         };
-        collider.layer_config(&matches, &cfg)?;
+        collider.layer_config(&matches, cfg.config())?;
         collider
             .setup_logging()
             .context("Failed to setup logging")?;
+        if cfg.loaded_files().is_empty() {
+            tracing::debug!("No config files found.");
+        }
+        for file in cfg.loaded_files() {
+            tracing::debug!("Loaded config file at {}", file.display());
+        }
         collider.execute().await?;
         tracing::info!("Ran in {}s", start.elapsed().as_millis() as f32 / 1000.0);
         Ok(())
@@ -101,6 +129,48 @@ pub enum ColliderCmd {
         setting = clap::AppSettings::DeriveDisplayOrder,
     )]
     Bisect(collider_cmd_bisect::BisectCmd),
+    #[clap(
+        about = "Manage collider's cache of downloaded Electron builds.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    Cache(collider_cmd_cache::CacheCmd),
+    #[clap(
+        about = "Prune collider's cache of downloaded Electron builds. Same as `collider cache clean`.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    Clean(collider_cmd_cache::CleanCmd),
+    #[clap(
+        about = "Check that everything collider relies on is installed and working. Exits non-zero if anything required is missing.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    Doctor(collider_cmd_info::DoctorCmd),
+    #[clap(
+        about = "Print shell setup that puts collider's Electron on PATH as `electron`, e.g. `eval \"$(collider env)\"`.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    Env(collider_cmd_env::EnvCmd),
+    #[clap(
+        about = "Show example invocations of collider commands.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    Examples(examples::ExamplesCmd),
+    #[clap(
+        about = "Print details about this machine and collider's cache, for bug reports.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    Info(collider_cmd_info::InfoCmd),
     #[clap(
         about = "Scaffold a new Electron application based on a workload.",
         setting = clap::AppSettings::ColoredHelp,
@@ -115,6 +185,13 @@ pub enum ColliderCmd {
         setting = clap::AppSettings::DeriveDisplayOrder,
     )]
     Pack(collider_cmd_pack::PackCmd),
+    #[clap(
+        about = "Update collider to its latest release.",
+        setting = clap::AppSettings::ColoredHelp,
+        setting = clap::AppSettings::DisableHelpSubcommand,
+        setting = clap::AppSettings::DeriveDisplayOrder,
+    )]
+    SelfUpdate(collider_cmd_self_update::SelfUpdateCmd),
     #[clap(
         about = "Start your Electron application.",
         setting = clap::AppSettings::ColoredHelp,
@@ -131,8 +208,15 @@ impl ColliderCommand for Collider {
         use ColliderCmd::*;
         match self.subcommand {
             Bisect(cmd) => cmd.execute().await,
+            Cache(cmd) => cmd.execute().await,
+            Clean(cmd) => cmd.execute().await,
+            Doctor(cmd) => cmd.execute().await,
+            Env(cmd) => cmd.execute().await,
+            Examples(cmd) => cmd.execute().await,
+            Info(cmd) => cmd.execute().await,
             New(cmd) => cmd.execute().await,
             Pack(cmd) => cmd.execute().await,
+            SelfUpdate(cmd) => cmd.current_version(clap::crate_version!()).execute().await,
             Start(cmd) => cmd.execute().await,
         }
     }
@@ -143,8 +227,15 @@ impl ColliderConfigLayer for Collider {
         use ColliderCmd::*;
         let (cmd, match_name): (&mut dyn ColliderConfigLayer, &str) = match self.subcommand {
             Bisect(ref mut cmd) => (cmd, "bisect"),
+            Cache(ref mut cmd) => (cmd, "cache"),
+            Clean(ref mut cmd) => (cmd, "clean"),
+            Doctor(ref mut cmd) => (cmd, "doctor"),
+            Env(ref mut cmd) => (cmd, "env"),
+            Examples(ref mut cmd) => (cmd, "examples"),
+            Info(ref mut cmd) => (cmd, "info"),
             New(ref mut cmd) => (cmd, "new"),
             Pack(ref mut cmd) => (cmd, "pack"),
+            SelfUpdate(ref mut cmd) => (cmd, "self-update"),
             Start(ref mut cmd) => (cmd, "start"),
         };
         cmd.layer_config(args.subcommand_matches(match_name).unwrap(), conf)